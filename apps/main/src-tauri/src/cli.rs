@@ -2,15 +2,46 @@
 //!
 //! Provides command-line access to notes:
 //! - irisnotes list - List all notes
-//! - irisnotes search <query> - Full-text search
-//! - irisnotes open <title> - Open note by title
+//! - irisnotes recent - List recently modified notes (--created for newest created)
+//! - irisnotes search <query> - Full-text search (--select to pick and open a hit).
+//!   Words match as prefixes; "quoted phrases" and AND/OR/NOT are understood.
+//!   --raw-query passes the query to FTS5 MATCH unchanged and skips the substring fallback.
+//!   That fallback matches `%` and `_` literally. --case-sensitive matches exact case.
+//! - irisnotes open <title> - Open note by title (--fuzzy to offer the closest titles)
 //! - irisnotes id <id> - Open note by ID
-//! - irisnotes tree - Show hierarchical tree view
-//! - irisnotes show <title> - Show note content
+//! - irisnotes path <id|title> - Print an item's hierarchy path (--all for every title match)
+//! - irisnotes tree - Show hierarchical tree view (--counts for notes per container, --book/--depth to trim it)
+//! - irisnotes show <title> - Show note content (--markdown to keep its structure, --links to list its hyperlinks, --fuzzy as for open)
+//! - irisnotes stats - Show item counts, word totals and database size
+//! - irisnotes optimize - Optimize the search index and VACUUM the database (alias: vacuum)
+//! - irisnotes backup - Copy the live database to a timestamped file (--out <dir>, --keep <n>)
+//! - irisnotes reindex - Rebuild the full-text search index from the items table
+//! - irisnotes migrate - Create the database schema or upgrade it to the latest version
+//! - irisnotes encrypt - Encrypt a plaintext database with the configured key
+//! - irisnotes decrypt - Decrypt the database back to plain SQLite
+//! - irisnotes doctor - Check the database for corruption, orphans, unindexed notes and cycles (--fix)
+//! - irisnotes tag-search <query> <tag> - Bulk-tag notes matching a search
+//! - irisnotes tags add|remove|list - Manage an item's tags (search --tag filters by one)
+//! - irisnotes tail - Show the item change log (--follow to stream new entries)
+//! - irisnotes config-validate - Check config.toml against the config schema
+//! - irisnotes config get|set|path - Print or change a config value, or print the config file's location
+//! - irisnotes create <title> - Create a note (body from --content or stdin)
+//! - irisnotes delete <title> - Soft-delete an item (--hard to remove it for good)
+//! - irisnotes edit <title> - Edit a note's body in $EDITOR
+//! - irisnotes export [id|title] - Export notes as Markdown, JSON or HTML (--out <dir>, --jobs <n>)
+//! - irisnotes import <path> - Import Markdown files, folders becoming books and sections
+//! - irisnotes move <id|title> --parent <id> - Reparent an item (--to-root for the root level)
+//! - irisnotes rename <id|title> <new-title> - Rename an item
+//! - irisnotes restore <title> - Restore a soft-deleted item (--with-parents for its ancestors)
 
-use clap::{Parser, Subcommand};
+use crate::export::ExportFormat;
+use crate::notes::{get_item_path, get_item_tags};
+use clap::{Parser, Subcommand, ValueEnum};
 use colored::Colorize;
-use rusqlite::{Connection, Result as SqliteResult};
+use irisnotes_text::{strip_html, word_count};
+use rusqlite::{Connection, OptionalExtension, Result as SqliteResult};
+use serde::Serialize;
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::process::Command;
 
@@ -23,11 +54,142 @@ pub struct Cli {
     /// Open a specific note by ID (used by quick app)
     #[arg(long = "open-note", global = true)]
     pub open_note: Option<String>,
-    
+
+    /// Term to highlight in the note opened with --open-note
+    #[arg(long, global = true, requires = "open_note")]
+    pub highlight: Option<String>,
+
+    /// Emit JSON instead of colored text (supported by: list, recent, search, path, tree, show, stats, tags list).
+    /// Errors are then written to stderr as `{"error": {"kind", "message"}}`
+    #[arg(long, global = true)]
+    pub json: bool,
+
+    /// Output layout for note listings (supported by: list, recent, search)
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Plain)]
+    pub format: OutputFormat,
+
+    /// Disable colored output
+    #[arg(long, global = true)]
+    pub no_color: bool,
+
+    /// strftime-style format for displayed timestamps (overrides `[cli] date_format`)
+    #[arg(long, global = true)]
+    pub date_format: Option<String>,
+
     #[command(subcommand)]
     pub command: Option<Commands>,
 }
 
+/// Layout for note listings
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// One `title - preview` line per note
+    Plain,
+    /// Aligned table with box-drawing borders
+    Table,
+}
+
+/// Category of a CLI failure, reported as `error.kind` under `--json`
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// No subcommand was given; the caller starts the GUI instead
+    NoCommand,
+    /// No item matched the given title, ID or query
+    NotFound,
+    /// Several items matched and none was selected
+    Ambiguous,
+    /// Arguments were rejected before touching the database
+    InvalidInput,
+    /// The config file is missing, unreadable or invalid
+    Config,
+    /// Opening, reading or writing the database failed
+    Database,
+    /// A confirmation prompt was declined
+    Cancelled,
+    /// `doctor` found problems it did not fix
+    Unhealthy,
+    /// Anything else (I/O, serialization, file watching)
+    Other,
+}
+
+/// A CLI failure with a machine-readable kind
+#[derive(Serialize, Debug)]
+pub struct CliError {
+    pub kind: ErrorKind,
+    pub message: String,
+    /// Individual problems behind the error, e.g. each config-validate finding
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<String>,
+}
+
+impl CliError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            details: Vec::new(),
+        }
+    }
+
+    fn not_found(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::NotFound, message)
+    }
+
+    fn invalid_input(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::InvalidInput, message)
+    }
+
+    fn config(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Config, message)
+    }
+
+    fn database(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Database, message)
+    }
+
+    /// Print to stderr: `{ "error": { ... } }` under `--json`, colored text otherwise
+    pub fn report(&self, json: bool) {
+        if json {
+            let envelope = serde_json::json!({ "error": self });
+            eprintln!("{}", envelope);
+            return;
+        }
+        for detail in &self.details {
+            eprintln!("{}", detail);
+        }
+        eprintln!("{}: {}", "Error".red(), self.message);
+    }
+}
+
+/// Helpers still report plain strings; those surface with kind `other`
+impl From<String> for CliError {
+    fn from(message: String) -> Self {
+        Self::new(ErrorKind::Other, message)
+    }
+}
+
+/// Shared note operations (see notes.rs) keep their error category
+impl From<crate::error::AppError> for CliError {
+    fn from(e: crate::error::AppError) -> Self {
+        use crate::error::AppError;
+        let kind = match e {
+            AppError::NotFound(_) => ErrorKind::NotFound,
+            AppError::InvalidInput(_) => ErrorKind::InvalidInput,
+            AppError::Config(_) => ErrorKind::Config,
+            AppError::Db(_) => ErrorKind::Database,
+            _ => ErrorKind::Other,
+        };
+        Self::new(kind, e.message())
+    }
+}
+
+impl std::fmt::Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
 #[derive(Subcommand, Debug)]
 pub enum Commands {
     /// List all notes
@@ -35,27 +197,115 @@ pub enum Commands {
         /// Show full content instead of truncated
         #[arg(short, long)]
         full: bool,
+        /// Include full note HTML in --json output (metadata only by default)
+        #[arg(long)]
+        include_content: bool,
+        /// Also list notes in the trash
+        #[arg(long)]
+        include_deleted: bool,
+    },
+    /// List the most recently modified notes
+    Recent {
+        /// Number of notes to show
+        #[arg(short, long, default_value_t = 10)]
+        limit: usize,
+        /// Order by creation time instead of last modification
+        #[arg(long)]
+        created: bool,
     },
     /// Search notes by content or title
     Search {
         /// Search query
         query: String,
+        /// Pick one of the results interactively and open it in the GUI
+        #[arg(short, long)]
+        select: bool,
+        /// Comma-separated fields to include in --json output
+        /// (id, title, type, parent_id, path, preview, words, updated_at, content)
+        #[arg(long, value_delimiter = ',')]
+        fields: Option<Vec<String>>,
+        /// Also search notes in the trash
+        #[arg(long)]
+        include_deleted: bool,
+        /// Only show notes carrying this tag
+        #[arg(short, long)]
+        tag: Option<String>,
+        /// Show at most this many results
+        #[arg(short, long, default_value_t = 20, conflicts_with = "all")]
+        limit: usize,
+        /// Show every result instead of the first --limit
+        #[arg(short, long)]
+        all: bool,
+        /// Match note titles only, ignoring their content
+        #[arg(long)]
+        titles_only: bool,
+        /// Only search notes inside this book (ID or title), at any depth
+        #[arg(short, long)]
+        book: Option<String>,
+        /// Pass the query to FTS5 MATCH unchanged (column filters, NEAR, `*`)
+        /// instead of turning every word into a prefix search
+        #[arg(long, conflicts_with = "titles_only")]
+        raw_query: bool,
+        /// Match the query's exact case; skips the full-text index, which ignores case
+        #[arg(short = 'C', long, conflicts_with = "raw_query")]
+        case_sensitive: bool,
+        /// Show each result's relevance (normalized BM25, 0-1; `-` for substring matches)
+        #[arg(long)]
+        score: bool,
     },
     /// Open a note by title in the GUI
+    ///
+    /// Prints "Opened in existing window" once a running IrisNotes has taken
+    /// the note. "Launched new window" is a guess: the process it started was
+    /// still running after 3 seconds, which usually means a new window.
     Open {
         /// Note title (case-insensitive)
         title: String,
         /// If multiple notes match, select by number (1-based)
         #[arg(short, long)]
         number: Option<usize>,
+        /// When no title matches exactly, offer the closest titles instead (3+ characters)
+        #[arg(short, long)]
+        fuzzy: bool,
     },
     /// Open a note by ID in the GUI
+    ///
+    /// Prints "Opened in existing window" once a running IrisNotes has taken
+    /// the note. "Launched new window" is a guess: the process it started was
+    /// still running after 3 seconds, which usually means a new window.
     Id {
         /// Note ID (UUID)
         id: String,
     },
+    /// Print an item's full `/`-joined hierarchy path
+    Path {
+        /// ID or title (case-insensitive) of the item
+        target: String,
+        /// If multiple items match the title, select by number (1-based)
+        #[arg(short, long, conflicts_with = "all")]
+        number: Option<usize>,
+        /// Print the path of every item matching the title, one per line
+        #[arg(short, long)]
+        all: bool,
+    },
     /// Show hierarchical tree of all items
-    Tree,
+    Tree {
+        /// Show how many notes each book/section contains (recursively)
+        #[arg(short, long)]
+        counts: bool,
+        /// Only show the contents of this book (ID or title)
+        #[arg(short, long)]
+        book: Option<String>,
+        /// Show at most this many levels
+        #[arg(short, long)]
+        depth: Option<usize>,
+        /// Append each item's ID, e.g. for scripting move or delete
+        #[arg(long)]
+        show_ids: bool,
+        /// Show how many direct children each book/section has, as `[N]`
+        #[arg(long)]
+        show_counts: bool,
+    },
     /// Show note content
     Show {
         /// Note title (case-insensitive)
@@ -66,401 +316,3252 @@ pub enum Commands {
         /// Output raw HTML instead of plain text
         #[arg(short, long)]
         raw: bool,
+        /// Output Markdown, keeping headings, lists, links and code blocks
+        #[arg(short, long, conflicts_with_all = ["raw", "standalone"])]
+        markdown: bool,
+        /// Output a self-contained HTML document (doctype, head, styles)
+        #[arg(long)]
+        standalone: bool,
+        /// List the note's hyperlinks after the body (note:// links show their target title)
+        #[arg(long, conflicts_with = "standalone")]
+        links: bool,
+        /// When no title matches exactly, offer the closest titles instead (3+ characters)
+        #[arg(short, long)]
+        fuzzy: bool,
+    },
+    /// Show item counts, word totals and database size
+    Stats,
+    /// Optimize the search index and compact the database file
+    #[command(alias = "vacuum")]
+    Optimize,
+    /// Rebuild the full-text search index from scratch
+    Reindex,
+    /// Copy the database to notes-<timestamp>.db; safe while IrisNotes is running
+    Backup {
+        /// Directory to write the backup to (default: the config directory)
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+        /// Keep only the newest N backups in the directory, deleting older ones
+        #[arg(short, long)]
+        keep: Option<usize>,
+    },
+    /// Create the database schema, or upgrade it to the latest version
+    Migrate,
+    /// Encrypt a plaintext database with the configured key (IRISNOTES_DB_KEY
+    /// or [storage] encryption_key). Refuses while IrisNotes or the quick app
+    /// has it open. The GUI cannot read an encrypted database yet; the CLI and
+    /// the quick app can.
+    Encrypt,
+    /// Decrypt the database with the configured key, back to plain SQLite.
+    /// Refuses while IrisNotes or the quick app has it open.
+    Decrypt,
+    /// Check the database for corruption, orphaned items, unindexed notes and
+    /// parent cycles
+    Doctor {
+        /// Move orphaned notes to the root and rebuild missing search index rows
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Add (or remove) a tag on every note matching a search query
+    TagSearch {
+        /// Search query selecting the notes to tag
+        query: String,
+        /// Tag name
+        tag: String,
+        /// Remove the tag instead of adding it
+        #[arg(long)]
+        remove: bool,
+        /// Show which notes would be affected without changing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Confirm changes affecting many notes
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Add, remove or list tags
+    Tags {
+        #[command(subcommand)]
+        action: TagAction,
+    },
+    /// Check config.toml (or config.json) for syntax and schema errors
+    ConfigValidate,
+    /// Print or change config values, checked against the same schema as the GUI
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Show recent entries from the item change log
+    Tail {
+        /// Keep running and print new entries as they are written
+        #[arg(short, long)]
+        follow: bool,
+        /// Number of recent entries to show first
+        #[arg(short = 'n', long, default_value_t = 20)]
+        lines: usize,
+    },
+    /// Create a note; the body comes from --content or piped stdin
+    Create {
+        /// Note title
+        title: String,
+        /// ID of the book or section to create the note in (root when omitted)
+        #[arg(short, long)]
+        parent: Option<String>,
+        /// Plain-text body (blank lines separate paragraphs)
+        #[arg(short, long)]
+        content: Option<String>,
+        /// Place the note directly before this sibling (ID or title)
+        #[arg(long, conflicts_with = "after")]
+        before: Option<String>,
+        /// Place the note directly after this sibling (ID or title)
+        #[arg(long)]
+        after: Option<String>,
+    },
+    /// Import Markdown files as notes, mirroring folders as books and sections
+    Import {
+        /// A .md file, or a directory whose contents are imported recursively
+        path: PathBuf,
+        /// ID of the book or section to import into (root when omitted)
+        #[arg(short, long)]
+        parent: Option<String>,
+    },
+    /// Export notes as Markdown, JSON or HTML
+    Export {
+        /// ID or title of a note, book or section to export (everything when omitted)
+        target: Option<String>,
+        /// Output format (--json is shorthand for `--to json`)
+        #[arg(short, long, value_enum, default_value_t = ExportFormat::Markdown)]
+        to: ExportFormat,
+        /// Directory to write into, mirroring books and sections as folders.
+        /// A single note is written to stdout when omitted.
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+        /// If multiple items match the title, select by number (1-based)
+        #[arg(short, long)]
+        number: Option<usize>,
+        /// Notes to convert and write at once (one per CPU when omitted)
+        #[arg(short, long, value_parser = clap::value_parser!(u16).range(1..))]
+        jobs: Option<u16>,
+    },
+    /// Edit a note's body in $EDITOR (as plain text; formatting is dropped unless --raw)
+    Edit {
+        /// Note title (case-insensitive)
+        title: String,
+        /// If multiple notes match, select by number (1-based)
+        #[arg(short, long)]
+        number: Option<usize>,
+        /// Edit the stored HTML instead of plain text
+        #[arg(short, long)]
+        raw: bool,
+    },
+    /// Move an item (and its contents) under another parent or to the root
+    Move {
+        /// ID or title (case-insensitive) of the item to move
+        target: String,
+        /// ID of the new parent book or section
+        #[arg(short, long, required_unless_present = "to_root", conflicts_with = "to_root")]
+        parent: Option<String>,
+        /// Move the item to the root level
+        #[arg(long)]
+        to_root: bool,
+        /// If multiple items match the title, select by number (1-based)
+        #[arg(short, long)]
+        number: Option<usize>,
+        /// Place the item directly before this sibling at the destination (ID or title)
+        #[arg(long, conflicts_with = "after")]
+        before: Option<String>,
+        /// Place the item directly after this sibling at the destination (ID or title)
+        #[arg(long)]
+        after: Option<String>,
+        /// Required to move a book or section, which carries its contents along
+        #[arg(short, long)]
+        recursive: bool,
+    },
+    /// Rename an item
+    Rename {
+        /// ID or title (case-insensitive) of the item to rename
+        target: String,
+        /// New title
+        new_title: String,
+        /// If multiple items match the title, select by number (1-based)
+        #[arg(short, long)]
+        number: Option<usize>,
+        /// Rename even if a sibling already has the new title
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Bring an item (and anything deleted together with it) back from the trash
+    Restore {
+        /// Item title (case-insensitive)
+        #[arg(required_unless_present = "id", conflicts_with = "id")]
+        title: Option<String>,
+        /// Item ID
+        #[arg(long)]
+        id: Option<String>,
+        /// If multiple deleted items match, select by number (1-based)
+        #[arg(short, long)]
+        number: Option<usize>,
+        /// Also restore deleted parents so the item is visible in the tree again
+        #[arg(long)]
+        with_parents: bool,
+    },
+    /// Move an item to the trash (soft delete), or remove it for good with --hard
+    Delete {
+        /// Item title (case-insensitive)
+        #[arg(required_unless_present = "id", conflicts_with = "id")]
+        title: Option<String>,
+        /// Item ID
+        #[arg(long)]
+        id: Option<String>,
+        /// If multiple items match, select by number (1-based)
+        #[arg(short, long)]
+        number: Option<usize>,
+        /// Delete the row instead of setting deleted_at (cannot be restored)
+        #[arg(long)]
+        hard: bool,
+        /// Delete a book or section together with its contents without asking
+        #[arg(short, long)]
+        recursive: bool,
     },
 }
 
-#[derive(Debug)]
+#[derive(Subcommand, Debug)]
+pub enum ConfigAction {
+    /// Print a value; strings print bare, tables as TOML
+    Get {
+        /// Dotted key as written in the file, e.g. `editor.lineWrapping`
+        key: String,
+    },
+    /// Set a value, creating the config file with the GUI's defaults if needed
+    Set {
+        /// Dotted key as written in the file, e.g. `quick.search_limit`
+        key: String,
+        /// A TOML value (`true`, `30`, `["b", "i"]`); anything else is taken as a string
+        value: String,
+    },
+    /// Print the location of the config file
+    Path,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TagAction {
+    /// Tag an item
+    Add {
+        /// ID or title (case-insensitive) of the item to tag
+        target: String,
+        /// Tags to add (a leading `#` is ignored)
+        #[arg(required = true)]
+        tags: Vec<String>,
+        /// If multiple items match the title, select by number (1-based)
+        #[arg(short, long)]
+        number: Option<usize>,
+    },
+    /// Remove a tag from an item
+    Remove {
+        /// ID or title (case-insensitive) of the item
+        target: String,
+        /// Tag to remove
+        tag: String,
+        /// If multiple items match the title, select by number (1-based)
+        #[arg(short, long)]
+        number: Option<usize>,
+    },
+    /// List all tags with how many live items carry each
+    List,
+}
+
+/// Insert a single note in its own transaction, returning its ID
+fn create_note(
+    conn: &Connection,
+    title: &str,
+    html: &str,
+    parent_id: Option<&str>,
+    placement: crate::sort_order::Placement,
+) -> Result<String, CliError> {
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| CliError::database(format!("Failed to start transaction: {}", e)))?;
+    let id = crate::notes::insert_item(&tx, "note", title, html, parent_id, placement)?;
+    tx.commit()
+        .map_err(|e| CliError::database(format!("Failed to commit note: {}", e)))?;
+    Ok(id)
+}
+
+/// Bulk tag changes above this many notes require `--yes`
+const BULK_TAG_CONFIRM_THRESHOLD: usize = 50;
+
+#[derive(Debug, Clone)]
 struct Note {
     id: String,
     title: String,
     content: String,
     item_type: String,
     parent_id: Option<String>,
+    updated_at: String,
 }
 
-/// Strip HTML tags and decode common entities for plain text output
-fn strip_html(html: &str) -> String {
-    // Remove HTML tags
-    let mut result = String::with_capacity(html.len());
-    let mut in_tag = false;
-    let mut chars = html.chars().peekable();
-    
-    while let Some(c) = chars.next() {
-        match c {
-            '<' => in_tag = true,
-            '>' => in_tag = false,
-            _ if !in_tag => result.push(c),
-            _ => {}
-        }
-    }
-    
-    // Decode common HTML entities
-    let result = result
-        .replace("&nbsp;", " ")
-        .replace("&amp;", "&")
-        .replace("&lt;", "<")
-        .replace("&gt;", ">")
-        .replace("&quot;", "\"")
-        .replace("&#39;", "'")
-        .replace("&apos;", "'");
-    
-    // Collapse multiple spaces and trim
-    let mut prev_space = false;
-    let collapsed: String = result
-        .chars()
-        .filter(|c| {
-            if c.is_whitespace() && *c != '\n' {
-                if prev_space {
-                    return false;
-                }
-                prev_space = true;
-            } else {
-                prev_space = false;
-            }
-            true
-        })
-        .collect();
-    
-    collapsed.trim().to_string()
+/// JSON representation of a note for `--json` output
+#[derive(Serialize)]
+struct NoteJson<'a> {
+    id: &'a str,
+    title: &'a str,
+    #[serde(rename = "type")]
+    item_type: &'a str,
+    parent_id: Option<&'a str>,
+    path: String,
+    updated_at: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<&'a str>,
 }
 
-fn get_db_path() -> PathBuf {
-    // Check if we're in development mode
-    let is_dev = cfg!(debug_assertions) || std::env::var("TAURI_ENV").as_deref() == Ok("dev");
-
-    if is_dev {
-        // Find project root by looking for pnpm-workspace.yaml
-        let mut path = std::env::current_dir().unwrap_or_default();
-        loop {
-            if path.join("pnpm-workspace.yaml").exists() {
-                return path.join("dev").join("notes.db");
-            }
-            if !path.pop() {
-                break;
-            }
+impl<'a> NoteJson<'a> {
+    fn new(conn: &Connection, note: &'a Note, include_content: bool) -> Self {
+        Self {
+            id: &note.id,
+            title: &note.title,
+            item_type: &note.item_type,
+            parent_id: note.parent_id.as_deref(),
+            path: get_item_path(conn, &note.id).unwrap_or_else(|_| note.title.clone()),
+            updated_at: &note.updated_at,
+            content: include_content.then_some(note.content.as_str()),
         }
-        // Fallback: try current dir
-        PathBuf::from("dev/notes.db")
-    } else {
-        // Production: ~/.config/irisnotes/notes.db
-        dirs::config_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join("irisnotes")
-            .join("notes.db")
     }
 }
 
-fn open_connection() -> SqliteResult<Connection> {
-    let db_path = get_db_path();
-    Connection::open(&db_path)
+/// One item of `tree --json`, with its children nested
+#[derive(Serialize)]
+struct TreeNodeJson<'a> {
+    id: &'a str,
+    title: &'a str,
+    #[serde(rename = "type")]
+    item_type: &'a str,
+    /// Notes contained (recursively); only for books/sections with `--counts`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    note_count: Option<usize>,
+    children: Vec<TreeNodeJson<'a>>,
 }
 
-fn get_all_notes(conn: &Connection) -> SqliteResult<Vec<Note>> {
-    let mut stmt = conn.prepare(
-        "SELECT id, title, content, type, parent_id FROM items ORDER BY sort_order",
-    )?;
-
-    let notes = stmt
-        .query_map([], |row| {
-            Ok(Note {
-                id: row.get(0)?,
-                title: row.get(1)?,
-                content: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
-                item_type: row.get(3)?,
-                parent_id: row.get(4)?,
-            })
-        })?
-        .collect::<SqliteResult<Vec<_>>>()?;
-
-    Ok(notes)
+/// Build the nested `tree --json` structure below `parent_id`
+/// `depth` is how many more levels to include (`None` for all)
+fn tree_json<'a>(
+    children: &ChildMap<'a>,
+    parent_id: Option<&str>,
+    note_counts: Option<&HashMap<String, usize>>,
+    depth: Option<usize>,
+) -> Vec<TreeNodeJson<'a>> {
+    if depth == Some(0) {
+        return Vec::new();
+    }
+    let depth = depth.map(|depth| depth - 1);
+    children
+        .get(&parent_id)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .map(|note| TreeNodeJson {
+            id: &note.id,
+            title: &note.title,
+            item_type: &note.item_type,
+            note_count: note_counts
+                .filter(|_| note.item_type != "note")
+                .map(|counts| counts.get(&note.id).copied().unwrap_or(0)),
+            children: tree_json(children, Some(&note.id), note_counts, depth),
+        })
+        .collect()
 }
 
-fn find_notes_by_title(conn: &Connection, title: &str) -> SqliteResult<Vec<Note>> {
-    let mut stmt = conn.prepare(
-        "SELECT id, title, content, type, parent_id FROM items 
-         WHERE LOWER(title) = LOWER(?1) AND type = 'note'",
-    )?;
-
-    let notes = stmt
-        .query_map([title], |row| {
-            Ok(Note {
-                id: row.get(0)?,
-                title: row.get(1)?,
-                content: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
-                item_type: row.get(3)?,
-                parent_id: row.get(4)?,
-            })
-        })?
-        .collect::<SqliteResult<Vec<_>>>()?;
+/// `show --json` output: the note with its path, HTML and plain text
+#[derive(Serialize)]
+struct ShowJson<'a> {
+    #[serde(flatten)]
+    note: NoteJson<'a>,
+    text: String,
+    tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    links: Option<Vec<LinkJson>>,
+}
 
-    Ok(notes)
+/// A link in `show --json --links`; `note://` links carry the target's title
+#[derive(Serialize)]
+struct LinkJson {
+    href: String,
+    text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    note_id: Option<String>,
+    /// `null` when the linked note no longer exists
+    #[serde(skip_serializing_if = "Option::is_none")]
+    note_title: Option<Option<String>>,
 }
 
-fn search_notes(conn: &Connection, query: &str) -> SqliteResult<Vec<Note>> {
-    // Try FTS5 search first
-    let fts_result: SqliteResult<Vec<Note>> = (|| {
-        let mut stmt = conn.prepare(
-            "SELECT items.id, items.title, items.content, items.type, items.parent_id
-             FROM items_fts
-             JOIN items ON items.id = items_fts.id
-             WHERE items_fts MATCH ?1
-             ORDER BY rank",
-        )?;
+/// Fields `search --json --fields` can project to
+const SEARCH_JSON_FIELDS: &[&str] = &[
+    "id",
+    "title",
+    "type",
+    "parent_id",
+    "path",
+    "preview",
+    "words",
+    "updated_at",
+    "content",
+];
 
-        let rows = stmt.query_map([query], |row| {
-            Ok(Note {
-                id: row.get(0)?,
-                title: row.get(1)?,
-                content: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
-                item_type: row.get(3)?,
-                parent_id: row.get(4)?,
-            })
-        })?;
-        let result: SqliteResult<Vec<_>> = rows.collect();
-        result
-    })();
+/// Fields emitted by `search --json` when `--fields` is not given
+const SEARCH_JSON_DEFAULT_FIELDS: &[&str] = &["id", "title", "type", "parent_id", "path", "preview"];
 
-    // If FTS fails, fall back to LIKE search
-    match fts_result {
-        Ok(notes) if !notes.is_empty() => Ok(notes),
-        _ => {
-            let pattern = format!("%{}%", query);
-            let mut stmt = conn.prepare(
-                "SELECT id, title, content, type, parent_id FROM items 
-                 WHERE title LIKE ?1 OR content LIKE ?1
-                 ORDER BY sort_order",
-            )?;
+/// Check requested field names against `SEARCH_JSON_FIELDS`, dropping duplicates
+fn parse_search_fields(fields: Option<Vec<String>>) -> Result<Vec<String>, CliError> {
+    let Some(fields) = fields else {
+        return Ok(SEARCH_JSON_DEFAULT_FIELDS.iter().map(|f| f.to_string()).collect());
+    };
 
-            let rows = stmt.query_map([&pattern], |row| {
-                Ok(Note {
-                    id: row.get(0)?,
-                    title: row.get(1)?,
-                    content: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
-                    item_type: row.get(3)?,
-                    parent_id: row.get(4)?,
-                })
-            })?;
-            rows.collect()
+    let mut selected: Vec<String> = Vec::new();
+    for field in fields.iter().map(|f| f.trim()).filter(|f| !f.is_empty()) {
+        if !SEARCH_JSON_FIELDS.contains(&field) {
+            return Err(CliError::invalid_input(format!(
+                "Unknown field '{}' (expected one of: {})",
+                field,
+                SEARCH_JSON_FIELDS.join(", ")
+            )));
+        }
+        if !selected.iter().any(|f| f == field) {
+            selected.push(field.to_string());
         }
     }
+    if selected.is_empty() {
+        return Err(CliError::invalid_input("--fields needs at least one field"));
+    }
+    Ok(selected)
 }
 
-fn get_note_by_id(conn: &Connection, id: &str) -> SqliteResult<Option<Note>> {
-    let mut stmt = conn.prepare(
-        "SELECT id, title, content, type, parent_id FROM items WHERE id = ?1",
-    )?;
+/// Project a note onto the requested fields. Path, preview and word count are
+/// only computed when asked for, since each costs a lookup or an HTML strip.
+fn project_note(
+    conn: &Connection,
+    note: &Note,
+    fields: &[String],
+) -> serde_json::Map<String, serde_json::Value> {
+    use serde_json::Value;
 
-    let mut rows = stmt.query([id])?;
-    if let Some(row) = rows.next()? {
-        Ok(Some(Note {
-            id: row.get(0)?,
-            title: row.get(1)?,
-            content: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
-            item_type: row.get(3)?,
-            parent_id: row.get(4)?,
-        }))
+    let needs_plain = fields.iter().any(|f| f == "preview" || f == "words");
+    let plain = if needs_plain {
+        strip_html(&note.content)
     } else {
-        Ok(None)
+        String::new()
+    };
+
+    let mut object = serde_json::Map::new();
+    for field in fields {
+        let value = match field.as_str() {
+            "id" => Value::from(note.id.as_str()),
+            "title" => Value::from(note.title.as_str()),
+            "type" => Value::from(note.item_type.as_str()),
+            "parent_id" => note.parent_id.as_deref().map_or(Value::Null, Value::from),
+            "path" => {
+                Value::from(get_item_path(conn, &note.id).unwrap_or_else(|_| note.title.clone()))
+            }
+            "preview" => Value::from(truncate(&plain.replace('\n', " "), PREVIEW_WIDTH)),
+            "words" => Value::from(word_count(&plain)),
+            "updated_at" => Value::from(note.updated_at.as_str()),
+            "content" => Value::from(note.content.as_str()),
+            _ => continue,
+        };
+        object.insert(field.clone(), value);
     }
+    object
 }
 
-fn get_item_path(conn: &Connection, id: &str) -> SqliteResult<String> {
-    let mut path_parts = Vec::new();
-    let mut current_id = Some(id.to_string());
+fn print_json<T: Serialize>(value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value)
+        .map_err(|e| format!("Failed to serialize JSON: {}", e))?;
+    println!("{}", json);
+    Ok(())
+}
 
-    while let Some(ref id) = current_id {
-        let mut stmt = conn.prepare("SELECT title, parent_id FROM items WHERE id = ?1")?;
-        let result: SqliteResult<(String, Option<String>)> = stmt.query_row([id], |row| {
-            Ok((row.get(0)?, row.get(1)?))
-        });
+/// A hyperlink found in a note body
+#[derive(Debug)]
+struct Link {
+    href: String,
+    text: String,
+}
+
+/// Value of `name` in the attribute section of a tag (`href="…"`, `href='…'` or bare)
+fn attribute_value(attrs: &str, name: &str) -> Option<String> {
+    let mut rest = attrs;
+    while let Some(start) = rest.find(|c: char| c.is_ascii_alphabetic()) {
+        rest = &rest[start..];
+        let name_end = rest
+            .find(|c: char| c == '=' || c.is_whitespace() || c == '/')
+            .unwrap_or(rest.len());
+        let attr = &rest[..name_end];
+        rest = rest[name_end..].trim_start();
 
-        match result {
-            Ok((title, parent)) => {
-                path_parts.push(title);
-                current_id = parent;
+        let value = match rest.strip_prefix('=') {
+            Some(after) => {
+                let after = after.trim_start();
+                let (value, remaining) = match after.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let inner = &after[1..];
+                        let end = inner.find(quote).unwrap_or(inner.len());
+                        (&inner[..end], inner.get(end + 1..).unwrap_or(""))
+                    }
+                    _ => {
+                        let end = after.find(char::is_whitespace).unwrap_or(after.len());
+                        (&after[..end], &after[end..])
+                    }
+                };
+                rest = remaining;
+                Some(value)
             }
-            Err(_) => break,
+            None => None,
+        };
+
+        if attr.eq_ignore_ascii_case(name) {
+            return value.map(strip_html);
         }
     }
-
-    path_parts.reverse();
-    Ok(path_parts.join(" / "))
+    None
 }
 
-fn truncate(s: &str, max_len: usize) -> String {
-    if s.len() <= max_len {
-        s.to_string()
-    } else {
-        format!("{}...", &s[..max_len.saturating_sub(3)])
+/// Find the end of a tag starting at `start` (the index of its `<`), skipping
+/// over quoted attribute values that may contain `>`
+fn tag_end(html: &str, start: usize) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in html[start..].char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '>') => return Some(start + i),
+            _ => {}
+        }
     }
+    None
 }
 
-fn open_note_in_gui(note_id: &str) {
-    // Get the path to the current executable
-    let exe = std::env::current_exe().unwrap_or_else(|_| PathBuf::from("irisnotes"));
+/// Extract `<a href>` links and their text, in document order
+fn extract_links(html: &str) -> Vec<Link> {
+    let lower = html.to_ascii_lowercase();
+    let mut links = Vec::new();
+    let mut pos = 0;
 
-    // Spawn the GUI with --open-note argument
-    let _ = Command::new(exe)
-        .arg(format!("--open-note={}", note_id))
-        .spawn();
-}
+    while let Some(offset) = lower[pos..].find("<a") {
+        let start = pos + offset;
+        let Some(end) = tag_end(html, start) else {
+            break;
+        };
+        pos = end + 1;
 
-fn print_note_list(notes: &[Note], full: bool) {
-    for note in notes {
-        if note.item_type != "note" {
+        // Skip <abbr>, <aside> etc.
+        let attrs = &html[start + 2..end];
+        if !attrs.is_empty() && !attrs.starts_with(|c: char| c.is_whitespace()) {
             continue;
         }
-        let title = note.title.cyan();
-        let plain_content = strip_html(&note.content);
-        let preview = if full {
-            plain_content
-        } else {
-            truncate(&plain_content.replace('\n', " "), 60)
+        let Some(href) = attribute_value(attrs, "href").filter(|h| !h.is_empty()) else {
+            continue;
         };
-        println!("{} - {}", title, preview.dimmed());
+
+        let close = lower[pos..].find("</a").map_or(html.len(), |i| pos + i);
+        let text = strip_html(&html[pos..close]).replace('\n', " ");
+        links.push(Link { href, text });
+        pos = close;
     }
+    links
 }
 
-fn select_note<'a>(notes: &'a [Note], number: Option<usize>) -> Option<&'a Note> {
-    if notes.is_empty() {
-        return None;
+/// For `note://<id>` links, the linked note's ID and title (`None` title if it is gone)
+fn link_target(conn: &Connection, link: &Link) -> Option<(String, Option<String>)> {
+    let id = link.href.strip_prefix("note://")?;
+    let title = get_note_by_id(conn, id).ok().flatten().map(|note| note.title);
+    Some((id.to_string(), title))
+}
+
+fn links_json(conn: &Connection, html: &str) -> Vec<LinkJson> {
+    extract_links(html)
+        .into_iter()
+        .map(|link| {
+            let target = link_target(conn, &link);
+            LinkJson {
+                note_id: target.as_ref().map(|(id, _)| id.clone()),
+                note_title: target.map(|(_, title)| title),
+                href: link.href,
+                text: link.text,
+            }
+        })
+        .collect()
+}
+
+/// Print a note's links as a numbered reference list, resolving internal
+/// `note://<id>` links to the title of the note they point at
+fn print_links(conn: &Connection, html: &str) {
+    let links = extract_links(html);
+    println!("{}", "─".repeat(50).dimmed());
+    if links.is_empty() {
+        println!("{}", "No links".dimmed());
+        return;
     }
-    if notes.len() == 1 {
-        return Some(&notes[0]);
+
+    println!("{} ({}):", "Links".bold(), links.len());
+    for (i, link) in links.iter().enumerate() {
+        let text = if link.text.is_empty() {
+            link.href.as_str()
+        } else {
+            link.text.as_str()
+        };
+        let target = match link_target(conn, link) {
+            Some((_, Some(title))) => format!(" → {}", title.cyan()),
+            Some((_, None)) => format!(" → {}", "missing note".red()),
+            None => String::new(),
+        };
+        println!("  {}. {}{} {}", i + 1, text, target, link.href.dimmed());
     }
+}
 
-    // If number specified, use it
+/// Escape text for safe inclusion in HTML element content or attributes
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Plain-text editing buffer for a note: block elements end in a blank line so
+/// `text_to_html` can rebuild the paragraphs after editing
+fn html_to_edit_buffer(html: &str) -> String {
+    let mut text = html.to_string();
+    for closing in [
+        "</p>", "</div>", "</li>", "</h1>", "</h2>", "</h3>", "</h4>", "</h5>", "</h6>",
+        "</blockquote>", "</pre>",
+    ] {
+        text = text.replace(closing, &format!("{}\n\n", closing));
+    }
+    for br in ["<br>", "<br/>", "<br />"] {
+        text = text.replace(br, "\n");
+    }
+    let stripped = strip_html(&text);
+    let lines: Vec<&str> = stripped.lines().map(str::trim).collect();
+    let mut buffer = lines.join("\n");
+    while buffer.contains("\n\n\n") {
+        buffer = buffer.replace("\n\n\n", "\n\n");
+    }
+    buffer.push('\n');
+    buffer
+}
+
+/// Open `initial` in `$EDITOR` (falling back to `vi`) and return the saved text
+fn edit_in_editor(initial: &str, extension: &str) -> Result<String, CliError> {
+    use std::io::Write;
+
+    let path = std::env::temp_dir().join(format!(
+        "irisnotes-{}.{}",
+        uuid::Uuid::new_v4(),
+        extension
+    ));
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    file.write_all(initial.as_bytes())
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    drop(file);
+
+    // $EDITOR may carry arguments, e.g. "code --wait"
+    let editor = std::env::var("EDITOR")
+        .ok()
+        .filter(|e| !e.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().unwrap_or("vi");
+    let status = Command::new(program).args(parts).arg(&path).status();
+
+    let result = match status {
+        Ok(status) if status.success() => std::fs::read_to_string(&path)
+            .map_err(|e| CliError::from(format!("Failed to read {}: {}", path.display(), e))),
+        Ok(status) => Err(CliError::new(
+            ErrorKind::Cancelled,
+            format!("Editor '{}' exited with {}; note left unchanged", editor, status),
+        )),
+        Err(e) => Err(CliError::from(format!(
+            "Failed to launch editor '{}': {}",
+            editor, e
+        ))),
+    };
+    let _ = std::fs::remove_file(&path);
+    result
+}
+
+/// Convert plain text to the editor's HTML: blank lines separate paragraphs,
+/// single newlines become `<br>`
+fn text_to_html(text: &str) -> String {
+    let normalized = text.replace("\r\n", "\n");
+    normalized
+        .split("\n\n")
+        .map(str::trim)
+        .filter(|paragraph| !paragraph.is_empty())
+        .map(|paragraph| {
+            let lines: Vec<String> = paragraph.lines().map(escape_html).collect();
+            format!("<p>{}</p>", lines.join("<br>"))
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+struct ChangelogEntry {
+    seq: i64,
+    item_id: String,
+    operation: String,
+    changed_at: String,
+    title: Option<String>,
+}
+
+/// Changelog entries after `after_seq`, oldest first.
+/// With `last`, only the most recent `last` of those are returned.
+fn get_changelog(
+    conn: &Connection,
+    after_seq: i64,
+    last: Option<usize>,
+) -> SqliteResult<Vec<ChangelogEntry>> {
+    let limit = last.map(|n| n as i64).unwrap_or(-1);
+    let mut stmt = conn.prepare(
+        "SELECT * FROM (
+            SELECT c.seq, c.item_id, c.operation, c.changed_at, i.title
+            FROM changelog c
+            LEFT JOIN items i ON i.id = c.item_id
+            WHERE c.seq > ?1
+            ORDER BY c.seq DESC
+            LIMIT ?2
+         ) ORDER BY seq ASC",
+    )?;
+
+    let entries = stmt
+        .query_map(rusqlite::params![after_seq, limit], |row| {
+            Ok(ChangelogEntry {
+                seq: row.get(0)?,
+                item_id: row.get(1)?,
+                operation: row.get(2)?,
+                changed_at: row.get(3)?,
+                title: row.get(4)?,
+            })
+        })?
+        .collect::<SqliteResult<Vec<_>>>()?;
+
+    Ok(entries)
+}
+
+fn print_changelog_entry(entry: &ChangelogEntry) {
+    let operation = match entry.operation.as_str() {
+        "insert" => entry.operation.green(),
+        "delete" => entry.operation.red(),
+        _ => entry.operation.yellow(),
+    };
+    println!(
+        "{} {:<6} {} {}",
+        entry.changed_at.dimmed(),
+        operation,
+        entry.item_id,
+        entry.title.as_deref().unwrap_or("").cyan()
+    );
+}
+
+/// Block forever, printing changelog entries newer than `last_seq` whenever the
+/// database (or its WAL) is written
+fn follow_changelog(
+    conn: &Connection,
+    db_path: &std::path::Path,
+    mut last_seq: i64,
+) -> Result<(), String> {
+    use notify::{Config, Event, RecommendedWatcher, RecursiveMode, Watcher};
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    let db_dir = db_path
+        .parent()
+        .ok_or_else(|| "Database path has no parent directory".to_string())?;
+    let db_name = db_path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let wal_name = format!("{}-wal", db_name);
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = RecommendedWatcher::new(
+        move |res: Result<Event, notify::Error>| {
+            if let Ok(event) = res {
+                let _ = tx.send(event);
+            }
+        },
+        Config::default(),
+    )
+    .map_err(|e| format!("Failed to create file watcher: {}", e))?;
+
+    // Watch the directory: SQLite replaces the WAL file, which would drop a file-level watch
+    watcher
+        .watch(db_dir, RecursiveMode::NonRecursive)
+        .map_err(|e| format!("Failed to watch database directory: {}", e))?;
+
+    loop {
+        match rx.recv_timeout(Duration::from_secs(2)) {
+            Ok(event) => {
+                let touches_db = event.paths.iter().any(|path| {
+                    path.file_name()
+                        .map(|n| n.to_string_lossy())
+                        .is_some_and(|n| n == db_name || n == wal_name)
+                });
+                if !touches_db {
+                    continue;
+                }
+            }
+            // Poll on timeout as a fallback for filesystems without change notifications
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                return Err("File watcher stopped unexpectedly".to_string());
+            }
+        }
+
+        let entries = get_changelog(conn, last_seq, None)
+            .map_err(|e| format!("Failed to read change log: {}", e))?;
+        for entry in &entries {
+            print_changelog_entry(entry);
+            last_seq = entry.seq;
+        }
+    }
+}
+
+/// Config file the GUI would load: config.toml, falling back to config.json
+fn get_config_file_path() -> PathBuf {
+    let config_dir = irisnotes_paths::config_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let toml_path = config_dir.join("config.toml");
+    if toml_path.exists() {
+        return toml_path;
+    }
+    let json_path = config_dir.join("config.json");
+    if json_path.exists() {
+        json_path
+    } else {
+        toml_path
+    }
+}
+
+/// `irisnotes config`: values are read and written through `AppConfig`, so the
+/// file stays one the GUI accepts
+fn run_config_action(action: &ConfigAction, json: bool) -> Result<(), CliError> {
+    let path = get_config_file_path();
+    let config = || {
+        if path.exists() {
+            crate::config::parse_config_file(&path).map_err(CliError::config)
+        } else {
+            // What the GUI writes on first run
+            Ok(crate::config::AppConfig::defaults())
+        }
+    };
+
+    match action {
+        ConfigAction::Path => println!("{}", path.display()),
+        ConfigAction::Get { key } => {
+            let value = config()?
+                .get(key)
+                .map_err(CliError::invalid_input)?
+                .ok_or_else(|| CliError::not_found(format!("'{}' is not set", key)))?;
+            if json {
+                print_json(&value)?;
+            } else {
+                match &value {
+                    toml::Value::String(text) => println!("{}", text),
+                    toml::Value::Table(table) => {
+                        let text = toml::to_string_pretty(table)
+                            .map_err(|e| format!("Failed to serialize TOML: {}", e))?;
+                        print!("{}", text);
+                    }
+                    value => println!("{}", value),
+                }
+            }
+        }
+        ConfigAction::Set { key, value } => {
+            let updated = config()?.with_value(key, value).map_err(CliError::config)?;
+            // A legacy config.json stays JSON; the GUI migrates it on its next start
+            let contents = if path.extension().is_some_and(|ext| ext == "json") {
+                serde_json::to_string_pretty(&updated)
+                    .map_err(|e| format!("Failed to serialize JSON: {}", e))?
+            } else {
+                updated.to_toml()?
+            };
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                std::fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+            }
+            crate::write_atomic(&path, &contents)
+                .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+            if let Some(stored) = updated.get(key)? {
+                println!("{} = {}", key, stored);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Timestamp format from `--date-format`, else `[cli] date_format` in the config file
+fn resolve_date_format(flag: Option<String>) -> Result<Option<String>, CliError> {
+    let format = match flag {
+        Some(format) => Some(format),
+        None => {
+            let path = get_config_file_path();
+            if !path.exists() {
+                return Ok(None);
+            }
+            // A broken config should not stop read-only commands; config-validate reports it
+            match crate::config::parse_config_file(&path) {
+                Ok(config) => config.cli.and_then(|c| c.date_format),
+                Err(e) => {
+                    eprintln!("{}: ignoring config: {}", "Warning".yellow(), e);
+                    None
+                }
+            }
+        }
+    };
+
+    if let Some(format) = &format {
+        crate::timestamp::validate_date_format(format).map_err(CliError::invalid_input)?;
+    }
+    Ok(format)
+}
+
+/// The database file, resolved the same way as the GUI and the quick app.
+/// Falls back to `notes.db` in the current directory when no location can be
+/// determined.
+fn get_db_path() -> PathBuf {
+    irisnotes_paths::resolve_database_path().unwrap_or_else(|_| PathBuf::from("notes.db"))
+}
+
+fn open_connection() -> Result<Connection, String> {
+    let db_path = get_db_path();
+    crate::open_database(&db_path)
+}
+
+fn get_all_notes(conn: &Connection, include_deleted: bool) -> SqliteResult<Vec<Note>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, title, content, type, parent_id, updated_at FROM items
+         WHERE ?1 OR deleted_at IS NULL
+         ORDER BY sort_order",
+    )?;
+
+    let notes = stmt
+        .query_map([include_deleted], |row| {
+            Ok(Note {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                content: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                item_type: row.get(3)?,
+                parent_id: row.get(4)?,
+                updated_at: row.get(5)?,
+            })
+        })?
+        .collect::<SqliteResult<Vec<_>>>()?;
+
+    Ok(notes)
+}
+
+/// Like `get_all_notes`, but leaves `content` empty so large bodies are never read
+fn get_all_notes_metadata(conn: &Connection, include_deleted: bool) -> SqliteResult<Vec<Note>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, title, type, parent_id, updated_at FROM items
+         WHERE ?1 OR deleted_at IS NULL
+         ORDER BY sort_order",
+    )?;
+
+    let notes = stmt
+        .query_map([include_deleted], |row| {
+            Ok(Note {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                content: String::new(),
+                item_type: row.get(2)?,
+                parent_id: row.get(3)?,
+                updated_at: row.get(4)?,
+            })
+        })?
+        .collect::<SqliteResult<Vec<_>>>()?;
+
+    Ok(notes)
+}
+
+/// Live notes ordered newest first by `updated_at` (or `created_at`), each
+/// with the timestamp it was ordered by
+fn get_recent_notes(
+    conn: &Connection,
+    by_created: bool,
+    limit: usize,
+) -> SqliteResult<Vec<(Note, String)>> {
+    let column = if by_created { "created_at" } else { "updated_at" };
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, title, content, type, parent_id, updated_at, {column} FROM items
+         WHERE type = 'note' AND deleted_at IS NULL
+         ORDER BY {column} DESC, id
+         LIMIT ?1"
+    ))?;
+
+    let notes = stmt
+        .query_map([limit as i64], |row| {
+            Ok((
+                Note {
+                    id: row.get(0)?,
+                    title: row.get(1)?,
+                    content: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                    item_type: row.get(3)?,
+                    parent_id: row.get(4)?,
+                    updated_at: row.get(5)?,
+                },
+                row.get(6)?,
+            ))
+        })?
+        .collect::<SqliteResult<Vec<_>>>()?;
+
+    Ok(notes)
+}
+
+fn find_notes_by_title(conn: &Connection, title: &str) -> SqliteResult<Vec<Note>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, title, content, type, parent_id, updated_at FROM items 
+         WHERE LOWER(title) = LOWER(?1) AND type = 'note'",
+    )?;
+
+    let notes = stmt
+        .query_map([title], |row| {
+            Ok(Note {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                content: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                item_type: row.get(3)?,
+                parent_id: row.get(4)?,
+                updated_at: row.get(5)?,
+            })
+        })?
+        .collect::<SqliteResult<Vec<_>>>()?;
+
+    Ok(notes)
+}
+
+/// Most candidates a fuzzy title lookup offers
+const FUZZY_CANDIDATES: usize = 5;
+/// Shortest query a fuzzy title lookup accepts; one or two letters are a
+/// typo away from, or scattered through, nearly every title
+const FUZZY_MIN_CHARS: usize = 3;
+
+/// How closely `title` matches `query` (case-insensitive), lower is better:
+/// titles containing the query, then titles where some part is a few typos
+/// away (`meetng`), then titles holding the query's letters in order
+/// close together (`mtgnotes`). `None` when the title matches in none of these ways.
+fn fuzzy_title_score(query: &str, title: &str) -> Option<(usize, usize)> {
+    let query: Vec<char> = query.to_lowercase().chars().collect();
+    let title: Vec<char> = title.to_lowercase().chars().collect();
+    if query.is_empty() {
+        return None;
+    }
+
+    // Edit distance from the query to the closest substring of the title
+    let mut previous = vec![0; title.len() + 1];
+    for (i, &q) in query.iter().enumerate() {
+        let mut current = vec![i + 1; title.len() + 1];
+        for (j, &t) in title.iter().enumerate() {
+            let substitution = previous[j] + usize::from(q != t);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    let distance = previous.into_iter().min().unwrap_or(query.len());
+    let max_typos = (query.len() / 4).max(1);
+    if distance <= max_typos {
+        return Some((usize::from(distance > 0), distance));
+    }
+
+    // Letters in order, penalized by how many title characters they skip;
+    // letters scattered over more than twice the query's length are a coincidence
+    let mut positions = Vec::with_capacity(query.len());
+    let mut rest = title.iter().enumerate();
+    for &q in &query {
+        positions.push(rest.find(|(_, &t)| t == q)?.0);
+    }
+    let span = positions.last()? - positions.first()? + 1;
+    (span <= query.len() * 2).then_some((2, span - query.len()))
+}
+
+/// Live notes whose titles fuzzily match `title`, best first
+fn find_notes_fuzzy(conn: &Connection, title: &str) -> SqliteResult<Vec<Note>> {
+    let mut scored: Vec<((usize, usize), Note)> = get_all_notes(conn, false)?
+        .into_iter()
+        .filter(|note| note.item_type == "note")
+        .filter_map(|note| fuzzy_title_score(title, &note.title).map(|score| (score, note)))
+        .collect();
+    scored.sort_by(|(a, x), (b, y)| {
+        a.cmp(b)
+            .then_with(|| x.title.len().cmp(&y.title.len()))
+            .then_with(|| x.title.cmp(&y.title))
+    });
+    scored.truncate(FUZZY_CANDIDATES);
+    Ok(scored.into_iter().map(|(_, note)| note).collect())
+}
+
+/// The note titled `title`, or with `fuzzy` and no exact match, one of the
+/// closest titles. Several candidates are listed for the user to pick with --number.
+fn resolve_note_by_title(
+    conn: &Connection,
+    title: &str,
+    number: Option<usize>,
+    fuzzy: bool,
+) -> Result<Note, CliError> {
+    let notes = find_notes_by_title(conn, title)
+        .map_err(|e| CliError::database(format!("Failed to find note: {}", e)))?;
+    if !notes.is_empty() {
+        return select_note(&notes, number).cloned();
+    }
+    if !fuzzy {
+        return Err(CliError::not_found(format!(
+            "No note found with title '{}'",
+            title
+        )));
+    }
+
+    if title.trim().chars().count() < FUZZY_MIN_CHARS {
+        return Err(CliError::invalid_input(format!(
+            "No note titled '{}'; --fuzzy needs at least {} characters",
+            title, FUZZY_MIN_CHARS
+        )));
+    }
+
+    let candidates = find_notes_fuzzy(conn, title)
+        .map_err(|e| CliError::database(format!("Failed to find note: {}", e)))?;
+    if candidates.is_empty() {
+        return Err(CliError::not_found(format!(
+            "No note found with a title like '{}'",
+            title
+        )));
+    }
+    select_note(&candidates, number).cloned().map_err(|mut e| {
+        if e.kind == ErrorKind::Ambiguous {
+            e.message = format!(
+                "No note titled '{}'; closest matches are listed above. Use --number <N> to pick one.",
+                title
+            );
+        }
+        e
+    })
+}
+
+/// Filters applied by `search_notes` on top of the query
+#[derive(Debug, Default, Clone, Copy)]
+struct SearchOptions<'a> {
+    /// Also match items in the trash
+    include_deleted: bool,
+    /// Only match items carrying this tag (case-insensitive)
+    tag: Option<&'a str>,
+    /// Return at most this many rows (unlimited when `None`)
+    limit: Option<usize>,
+    /// Match the title only, skipping FTS and ordering by `sort_order`
+    titles_only: bool,
+    /// Only match descendants (at any depth) of this book or section ID
+    within: Option<&'a str>,
+    /// Use the query as a literal FTS5 expression, with no LIKE fallback
+    raw_query: bool,
+    /// Match the exact case of the query as a substring, skipping FTS
+    case_sensitive: bool,
+}
+
+/// Trash, tag (?3) and subtree (?6) filters shared by both search queries
+const SEARCH_FILTERS: &str = "(?2 OR items.deleted_at IS NULL)
+    AND (?3 IS NULL OR items.id IN (
+        SELECT item_tags.item_id FROM item_tags
+        JOIN tags ON tags.id = item_tags.tag_id
+        WHERE tags.name = ?3 COLLATE NOCASE))
+    AND (?6 IS NULL OR items.id IN (
+        WITH RECURSIVE subtree(id) AS (
+            SELECT id FROM items WHERE parent_id = ?6
+            UNION ALL
+            SELECT child.id FROM items child JOIN subtree ON child.parent_id = subtree.id
+        )
+        SELECT id FROM subtree))";
+
+/// Full-text search over live items (trashed ones too with `include_deleted`).
+/// Quoted phrases and `AND`/`OR`/`NOT` reach FTS5 intact; other words match
+/// as prefixes. Falls back to a substring match when FTS finds nothing.
+/// Each note comes with its normalized BM25 score, or `None` for substring matches.
+fn search_notes(
+    conn: &Connection,
+    query: &str,
+    options: SearchOptions,
+) -> SqliteResult<Vec<(Note, Option<f64>)>> {
+    let SearchOptions {
+        include_deleted,
+        tag,
+        limit,
+        titles_only,
+        within,
+        raw_query,
+        case_sensitive,
+    } = options;
+    // SQLite treats a negative LIMIT as no limit
+    let limit = limit.map_or(-1, |limit| limit as i64);
+
+    // Try FTS5 search first (it indexes content too, so title-only searches skip it,
+    // and it folds case, so case-sensitive searches do too). Its tokenizer drops
+    // `%` and `_`, so `50%` would prefix-match "500": those go to LIKE directly.
+    let fts_result: SqliteResult<Vec<(Note, Option<f64>)>> = (|| {
+        let expression = if raw_query {
+            Some(query.to_string())
+        } else {
+            irisnotes_text::fts_match_query(query)
+        };
+        let literal_wildcards = !raw_query && query.contains(['%', '_']);
+        let Some(expression) =
+            expression.filter(|_| !titles_only && !case_sensitive && !literal_wildcards)
+        else {
+            return Ok(Vec::new());
+        };
+        let mut stmt = conn.prepare(&format!(
+            "SELECT items.id, items.title, items.content, items.type, items.parent_id, items.updated_at,
+                    rank
+             FROM items_fts
+             JOIN items ON items.id = items_fts.item_id
+             WHERE items_fts MATCH ?1 AND {SEARCH_FILTERS}
+             ORDER BY rank
+             LIMIT ?4"
+        ))?;
+
+        // ?5 is only read by the LIKE query, but both take the same parameters
+        let params =
+            rusqlite::params![expression, include_deleted, tag, limit, titles_only, within];
+        let rows = stmt.query_map(params, |row| {
+            let note = Note {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                content: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                item_type: row.get(3)?,
+                parent_id: row.get(4)?,
+                updated_at: row.get(5)?,
+            };
+            let score = irisnotes_text::normalize_rank(row.get(6)?);
+            Ok((note, Some(score)))
+        })?;
+        let result: SqliteResult<Vec<_>> = rows.collect();
+        result
+    })();
+
+    // If FTS fails, fall back to LIKE search; a raw query's error is the user's to fix
+    match fts_result {
+        Ok(notes) if !notes.is_empty() || raw_query => Ok(notes),
+        Err(e) if raw_query => Err(e),
+        _ => {
+            let query = query.replace('"', "");
+            // instr() compares bytes; LIKE ignores ASCII case, with its wildcards escaped
+            let (pattern, matches) = if case_sensitive {
+                (query, "instr(title, ?1) > 0 OR (NOT ?5 AND instr(content, ?1) > 0)")
+            } else {
+                (
+                    format!("%{}%", escape_like(&query)),
+                    "title LIKE ?1 ESCAPE '\\' OR (NOT ?5 AND content LIKE ?1 ESCAPE '\\')",
+                )
+            };
+            let mut stmt = conn.prepare(&format!(
+                "SELECT id, title, content, type, parent_id, updated_at FROM items
+                 WHERE ({matches}) AND {SEARCH_FILTERS}
+                 ORDER BY sort_order
+                 LIMIT ?4"
+            ))?;
+
+            let params = rusqlite::params![pattern, include_deleted, tag, limit, titles_only, within];
+            let rows = stmt.query_map(params, |row| {
+                let note = Note {
+                    id: row.get(0)?,
+                    title: row.get(1)?,
+                    content: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                    item_type: row.get(3)?,
+                    parent_id: row.get(4)?,
+                    updated_at: row.get(5)?,
+                };
+                Ok((note, None))
+            })?;
+            rows.collect()
+        }
+    }
+}
+
+/// Escape LIKE wildcards so `50%` or `snake_case` match literally (pair with `ESCAPE '\'`)
+fn escape_like(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+/// Items of any type with the given title (case-insensitive), either live or in the trash
+fn find_items_by_title(conn: &Connection, title: &str, deleted: bool) -> SqliteResult<Vec<Note>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, title, content, type, parent_id, updated_at FROM items
+         WHERE LOWER(title) = LOWER(?1) AND (deleted_at IS NOT NULL) = ?2
+         ORDER BY type, sort_order",
+    )?;
+
+    let items = stmt
+        .query_map(rusqlite::params![title, deleted], |row| {
+            Ok(Note {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                content: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                item_type: row.get(3)?,
+                parent_id: row.get(4)?,
+                updated_at: row.get(5)?,
+            })
+        })?
+        .collect::<SqliteResult<Vec<_>>>()?;
+
+    Ok(items)
+}
+
+fn get_note_by_id(conn: &Connection, id: &str) -> SqliteResult<Option<Note>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, title, content, type, parent_id, updated_at FROM items WHERE id = ?1",
+    )?;
+
+    let mut rows = stmt.query([id])?;
+    if let Some(row) = rows.next()? {
+        Ok(Some(Note {
+            id: row.get(0)?,
+            title: row.get(1)?,
+            content: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+            item_type: row.get(3)?,
+            parent_id: row.get(4)?,
+            updated_at: row.get(5)?,
+        }))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Items grouped by `parent_id` (`None` for the root), each group in input order
+type ChildMap<'a> = HashMap<Option<&'a str>, Vec<&'a Note>>;
+
+/// Group `notes` into a `ChildMap`
+fn group_children(notes: &[Note]) -> ChildMap<'_> {
+    let mut children: ChildMap = HashMap::new();
+    for note in notes {
+        children.entry(note.parent_id.as_deref()).or_default().push(note);
+    }
+    children
+}
+
+/// Count the notes contained (at any depth) in every item, keyed by item ID.
+/// Single post-order pass over a parent -> children map, so O(n) overall.
+fn count_descendant_notes(children: &ChildMap) -> HashMap<String, usize> {
+    fn visit(note: &Note, children: &ChildMap, counts: &mut HashMap<String, usize>) -> usize {
+        let mut total = 0;
+        if let Some(kids) = children.get(&Some(note.id.as_str())) {
+            for kid in kids {
+                if kid.item_type == "note" {
+                    total += 1;
+                }
+                total += visit(kid, children, counts);
+            }
+        }
+        counts.insert(note.id.clone(), total);
+        total
+    }
+
+    let mut counts = HashMap::new();
+    if let Some(roots) = children.get(&None) {
+        for root in roots {
+            visit(root, children, &mut counts);
+        }
+    }
+    counts
+}
+
+/// The note with the most words, for `stats`
+#[derive(Serialize)]
+struct LargestNote {
+    id: String,
+    title: String,
+    path: String,
+    words: usize,
+}
+
+/// Collection overview printed by `stats`
+#[derive(Serialize, Default)]
+struct Stats {
+    books: usize,
+    sections: usize,
+    notes: usize,
+    /// Items in the trash, not counted above
+    deleted: usize,
+    /// Across live notes, counted on the plain text like `word_count`
+    words: usize,
+    largest_note: Option<LargestNote>,
+    database_bytes: u64,
+}
+
+fn collect_stats(conn: &Connection) -> SqliteResult<Stats> {
+    let mut stats = Stats {
+        database_bytes: file_size(&get_db_path()),
+        ..Stats::default()
+    };
+    let mut largest: Option<(usize, String, String)> = None;
+
+    let mut stmt =
+        conn.prepare("SELECT id, type, title, content, deleted_at IS NOT NULL FROM items")?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        if row.get::<_, bool>(4)? {
+            stats.deleted += 1;
+            continue;
+        }
+        match row.get_ref(1)?.as_str().unwrap_or_default() {
+            "book" => stats.books += 1,
+            "section" => stats.sections += 1,
+            "note" => {
+                stats.notes += 1;
+                let content: Option<String> = row.get(3)?;
+                let words = word_count(&strip_html(content.as_deref().unwrap_or_default()));
+                stats.words += words;
+                if largest.as_ref().is_none_or(|(most, _, _)| words > *most) {
+                    largest = Some((words, row.get(0)?, row.get(2)?));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    stats.largest_note = largest.map(|(words, id, title)| LargestNote {
+        path: get_item_path(conn, &id).unwrap_or_else(|_| title.clone()),
+        id,
+        title,
+        words,
+    });
+    Ok(stats)
+}
+
+/// Format a byte count for display (e.g. "1.4 MB")
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Refill `items_fts` from `items`, returning the number of rows indexed.
+/// Each item's `content_plaintext` is first recomputed from its HTML, so a
+/// stale copy left by an older version or an outside edit is fixed too.
+/// `items_fts` keeps its own copy of the text, so FTS5's `rebuild` command
+/// would only re-tokenize that copy; the rows are reinserted instead. Every
+/// item is indexed, trashed ones included, as the insert trigger does, so
+/// `search --include-deleted` keeps working.
+fn rebuild_search_index(conn: &Connection) -> Result<usize, CliError> {
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| CliError::database(format!("Failed to start transaction: {}", e)))?;
+
+    let stale: Vec<(String, String)> = {
+        let mut stmt = tx
+            .prepare("SELECT id, COALESCE(content, ''), COALESCE(content_plaintext, '') FROM items")
+            .map_err(|e| CliError::database(format!("Failed to read items: {}", e)))?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+            })
+            .map_err(|e| CliError::database(format!("Failed to read items: {}", e)))?;
+        let mut stale = Vec::new();
+        for row in rows {
+            let (id, content, plaintext) =
+                row.map_err(|e| CliError::database(format!("Failed to read items: {}", e)))?;
+            let fresh = strip_html(&content);
+            if fresh != plaintext {
+                stale.push((id, fresh));
+            }
+        }
+        stale
+    };
+    // Raise the sync flag so the timestamp trigger leaves updated_at alone:
+    // only derived text changes here, not the note
+    tx.execute("UPDATE sync_ctl SET applying = 1 WHERE id = 0", [])
+        .map_err(|e| CliError::database(format!("Failed to update sync flag: {}", e)))?;
+    for (id, plaintext) in &stale {
+        tx.execute(
+            "UPDATE items SET content_plaintext = ?1 WHERE id = ?2",
+            [plaintext, id],
+        )
+        .map_err(|e| CliError::database(format!("Failed to update plain text: {}", e)))?;
+    }
+    tx.execute("UPDATE sync_ctl SET applying = 0 WHERE id = 0", [])
+        .map_err(|e| CliError::database(format!("Failed to update sync flag: {}", e)))?;
+
+    tx.execute("DELETE FROM items_fts", [])
+        .map_err(|e| CliError::database(format!("Failed to clear search index: {}", e)))?;
+    let indexed = tx
+        .execute(
+            "INSERT INTO items_fts(item_id, title, content_plaintext)
+             SELECT id, title, content_plaintext FROM items",
+            [],
+        )
+        .map_err(|e| CliError::database(format!("Failed to rebuild search index: {}", e)))?;
+    tx.commit()
+        .map_err(|e| CliError::database(format!("Failed to commit search index: {}", e)))?;
+    Ok(indexed)
+}
+
+/// Run VACUUM without waiting on other connections, so an open IrisNotes
+/// fails it at once with a clear message instead of a stall, then checkpoint
+/// the WAL so the file size reflects the result
+fn vacuum(conn: &Connection) -> Result<(), CliError> {
+    use rusqlite::ErrorCode;
+
+    conn.busy_timeout(std::time::Duration::ZERO)
+        .map_err(|e| CliError::database(format!("Failed to set busy timeout: {}", e)))?;
+    // VACUUM cannot run inside a transaction
+    let result = conn.execute_batch("VACUUM;");
+    conn.busy_timeout(crate::BUSY_TIMEOUT)
+        .map_err(|e| CliError::database(format!("Failed to set busy timeout: {}", e)))?;
+
+    match result {
+        Err(rusqlite::Error::SqliteFailure(e, _))
+            if matches!(e.code, ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked) =>
+        {
+            return Err(CliError::database(
+                "The database is in use (is IrisNotes running?); close it and try again",
+            ));
+        }
+        Err(e) => return Err(CliError::database(format!("VACUUM failed: {}", e))),
+        Ok(()) => {}
+    }
+
+    // Reports (busy, log frames, checkpointed frames); a busy checkpoint only
+    // leaves the WAL file larger
+    conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))
+        .map_err(|e| CliError::database(format!("WAL checkpoint failed: {}", e)))
+}
+
+/// Backups written by `irisnotes backup`: `notes-<YYYYMMDD-HHMMSS>.db`
+const BACKUP_PREFIX: &str = "notes-";
+const BACKUP_TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+/// Copy the database into `dir` with SQLite's online backup API, which reads
+/// a consistent snapshot even while another connection writes (a plain file
+/// copy could miss pages still in the WAL). An encrypted database, which the
+/// backup API cannot copy, is exported instead, keyed with the same key.
+/// Returns the new file's path.
+fn backup_database(conn: &Connection, dir: &std::path::Path) -> Result<PathBuf, CliError> {
+    std::fs::create_dir_all(dir)
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    let timestamp = chrono::Local::now().format(BACKUP_TIMESTAMP_FORMAT);
+    let path = dir.join(format!("{}{}.db", BACKUP_PREFIX, timestamp));
+    if path.exists() {
+        return Err(CliError::invalid_input(format!(
+            "{} already exists; wait a second and try again",
+            path.display()
+        )));
+    }
+
+    let result = match irisnotes_paths::resolve_database_key()? {
+        Some(key) => crate::encryption::export_copy(conn, &path, &key),
+        None => conn
+            .backup(rusqlite::DatabaseName::Main, &path, None)
+            .map_err(|e| e.to_string()),
+    };
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&path);
+        return Err(CliError::database(format!("Backup failed: {}", e)));
+    }
+    Ok(path)
+}
+
+/// Delete all but the newest `keep` backups in `dir`, returning the deleted paths.
+/// Only files named like `backup_database` output are touched.
+fn prune_backups(dir: &std::path::Path, keep: usize) -> Result<Vec<PathBuf>, CliError> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+    let mut backups: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            let name = path.file_name().and_then(|name| name.to_str()).unwrap_or("");
+            name.strip_prefix(BACKUP_PREFIX)
+                .and_then(|rest| rest.strip_suffix(".db"))
+                .is_some_and(|timestamp| {
+                    chrono::NaiveDateTime::parse_from_str(timestamp, BACKUP_TIMESTAMP_FORMAT)
+                        .is_ok()
+                })
+        })
+        .collect();
+
+    // The timestamps sort chronologically by name; newest first
+    backups.sort();
+    backups.reverse();
+    let pruned: Vec<PathBuf> = backups.into_iter().skip(keep).collect();
+    for path in &pruned {
+        std::fs::remove_file(path)
+            .map_err(|e| format!("Failed to delete {}: {}", path.display(), e))?;
+    }
+    Ok(pruned)
+}
+
+fn file_size(path: &PathBuf) -> u64 {
+    std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+/// Normalize a user-supplied tag name ("#Finance " -> "Finance")
+fn normalize_tag(tag: &str) -> String {
+    tag.trim().trim_start_matches('#').trim().to_string()
+}
+
+fn find_tag_id(conn: &Connection, name: &str) -> SqliteResult<Option<String>> {
+    use rusqlite::OptionalExtension;
+
+    conn.query_row(
+        "SELECT id FROM tags WHERE name = ?1 COLLATE NOCASE",
+        [name],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+/// Look up a tag by name, creating it if it doesn't exist yet
+fn ensure_tag(conn: &Connection, name: &str) -> SqliteResult<String> {
+    if let Some(id) = find_tag_id(conn, name)? {
+        return Ok(id);
+    }
+    let id = uuid::Uuid::new_v4().to_string();
+    conn.execute("INSERT INTO tags (id, name) VALUES (?1, ?2)", [&id, name])?;
+    Ok(id)
+}
+
+/// Attach a tag to items, returning how many did not have it already
+fn add_tag_to_items(conn: &Connection, tag: &str, item_ids: &[&str]) -> SqliteResult<usize> {
+    let tag_id = ensure_tag(conn, tag)?;
+    let mut stmt = conn.prepare("INSERT OR IGNORE INTO item_tags (item_id, tag_id) VALUES (?1, ?2)")?;
+    let mut affected = 0;
+    for item_id in item_ids {
+        affected += stmt.execute([item_id, &tag_id.as_str()])?;
+    }
+    Ok(affected)
+}
+
+/// Detach a tag from items, returning how many actually had it
+fn remove_tag_from_items(conn: &Connection, tag: &str, item_ids: &[&str]) -> SqliteResult<usize> {
+    let Some(tag_id) = find_tag_id(conn, tag)? else {
+        return Ok(0);
+    };
+    let mut stmt = conn.prepare("DELETE FROM item_tags WHERE item_id = ?1 AND tag_id = ?2")?;
+    let mut affected = 0;
+    for item_id in item_ids {
+        affected += stmt.execute([item_id, &tag_id.as_str()])?;
+    }
+    Ok(affected)
+}
+
+/// Tag usage for `tags list`
+#[derive(Serialize)]
+struct TagCount {
+    name: String,
+    count: usize,
+}
+
+/// Every tag with the number of live items carrying it, unused tags included
+fn get_tag_counts(conn: &Connection) -> SqliteResult<Vec<TagCount>> {
+    let mut stmt = conn.prepare(
+        "SELECT tags.name, COUNT(items.id) FROM tags
+         LEFT JOIN item_tags ON item_tags.tag_id = tags.id
+         LEFT JOIN items ON items.id = item_tags.item_id AND items.deleted_at IS NULL
+         GROUP BY tags.id
+         ORDER BY tags.name COLLATE NOCASE",
+    )?;
+    let counts = stmt
+        .query_map([], |row| {
+            Ok(TagCount {
+                name: row.get(0)?,
+                count: row.get::<_, i64>(1)? as usize,
+            })
+        })?
+        .collect::<SqliteResult<Vec<_>>>()?;
+    Ok(counts)
+}
+
+fn truncate(s: &str, max_len: usize) -> String {
+    if s.chars().count() <= max_len {
+        s.to_string()
+    } else {
+        let cut: String = s.chars().take(max_len.saturating_sub(3)).collect();
+        format!("{}...", cut)
+    }
+}
+
+/// Characters of note text shown per search result
+const PREVIEW_WIDTH: usize = 60;
+
+/// About `width` characters of `text` around the first occurrence of any of
+/// `terms` (case-insensitive), with every occurrence in view highlighted.
+/// `None` when no term appears, so the caller can fall back to a plain preview.
+fn highlight_snippet(text: &str, terms: &[&str], width: usize) -> Option<String> {
+    // Fold case per character so positions in the folded text match the original
+    let fold = |c: char| c.to_lowercase().next().unwrap_or(c);
+    let chars: Vec<char> = text.chars().collect();
+    let folded: Vec<char> = chars.iter().map(|&c| fold(c)).collect();
+    let terms: Vec<Vec<char>> = terms
+        .iter()
+        .map(|term| term.chars().map(fold).collect::<Vec<_>>())
+        .filter(|term| !term.is_empty())
+        .collect();
+    // Length of the longest term starting at `i`
+    let match_at = |i: usize| {
+        terms
+            .iter()
+            .filter(|term| folded[i..].starts_with(term))
+            .map(|term| term.len())
+            .max()
+    };
+
+    let first = (0..folded.len()).find(|&i| match_at(i).is_some())?;
+    let first_len = match_at(first).unwrap_or(0);
+    // Center the first hit, shifting the window back when it runs past the end
+    let start = first.saturating_sub(width.saturating_sub(first_len) / 2);
+    let end = (start + width).min(chars.len());
+    let start = start.min(end.saturating_sub(width));
+
+    let mut snippet = String::new();
+    let mut plain = String::from(if start > 0 { "..." } else { "" });
+    let mut i = start;
+    while i < end {
+        match match_at(i) {
+            Some(len) => {
+                let len = len.min(end - i);
+                if !plain.is_empty() {
+                    snippet.push_str(&plain.dimmed().to_string());
+                    plain.clear();
+                }
+                let hit: String = chars[i..i + len].iter().collect();
+                snippet.push_str(&hit.bold().yellow().to_string());
+                i += len;
+            }
+            None => {
+                plain.push(chars[i]);
+                i += 1;
+            }
+        }
+    }
+    if end < chars.len() {
+        plain.push_str("...");
+    }
+    if !plain.is_empty() {
+        snippet.push_str(&plain.dimmed().to_string());
+    }
+    Some(snippet)
+}
+
+/// How long `open_note_in_gui` watches the process it started. A second
+/// instance hands `--open-note` to the running window (the single-instance
+/// plugin) and exits well within this; one still running is a new window.
+const GUI_HANDOFF_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+const GUI_HANDOFF_POLL: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Open a note in the GUI by starting `irisnotes --open-note=<id>`, and report
+/// whether a running window took it or a new one was launched
+fn open_note_in_gui(note_id: &str) -> Result<(), CliError> {
+    let exe = std::env::current_exe().unwrap_or_else(|_| PathBuf::from("irisnotes"));
+    let mut child = Command::new(exe)
+        .arg(format!("--open-note={}", note_id))
+        .stdin(std::process::Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to start IrisNotes: {}", e))?;
+
+    let deadline = std::time::Instant::now() + GUI_HANDOFF_TIMEOUT;
+    while std::time::Instant::now() < deadline {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => {
+                println!("Opened in existing window");
+                return Ok(());
+            }
+            Ok(Some(status)) => return Err(format!("IrisNotes exited with {}", status).into()),
+            Ok(None) => std::thread::sleep(GUI_HANDOFF_POLL),
+            Err(e) => return Err(format!("Failed to wait for IrisNotes: {}", e).into()),
+        }
+    }
+    // Left running on its own when the CLI exits
+    println!("Launched new window");
+    Ok(())
+}
+
+fn print_note_list(notes: &[Note], full: bool) {
+    for note in notes {
+        if note.item_type != "note" {
+            continue;
+        }
+        let title = note.title.cyan();
+        let plain_content = strip_html(&note.content);
+        let preview = if full {
+            plain_content
+        } else {
+            truncate(&plain_content.replace('\n', " "), 60)
+        };
+        println!("{} - {}", title, preview.dimmed());
+    }
+}
+
+/// Search score for display: three decimals, or `-` when FTS did not rank the note
+fn format_score(score: Option<f64>) -> String {
+    score.map_or_else(|| "-".to_string(), |score| format!("{:.3}", score))
+}
+
+/// Render notes as a path / title / words / updated table sized to the terminal.
+/// `scores` (one per note) adds a Score column.
+fn print_note_table(
+    conn: &Connection,
+    notes: &[Note],
+    scores: Option<&[Option<f64>]>,
+    date_format: Option<&str>,
+) {
+    use crate::table::{self, Column};
+
+    let mut columns = vec![
+        Column::left("Path"),
+        Column::left("Title"),
+        Column::right("Words"),
+        Column::left("Updated"),
+    ];
+    if scores.is_some() {
+        columns.push(Column::right("Score"));
+    }
+    let rows: Vec<Vec<String>> = notes
+        .iter()
+        .enumerate()
+        .filter(|(_, note)| note.item_type == "note")
+        .map(|(i, note)| {
+            let path = match note.parent_id.as_deref() {
+                Some(parent_id) => get_item_path(conn, parent_id).unwrap_or_default(),
+                None => String::new(),
+            };
+            let words = word_count(&strip_html(&note.content));
+            let mut row = vec![
+                path,
+                note.title.clone(),
+                words.to_string(),
+                crate::timestamp::format_timestamp(&note.updated_at, date_format),
+            ];
+            if let Some(scores) = scores {
+                row.push(format_score(scores[i]));
+            }
+            row
+        })
+        .collect();
+
+    println!("{}", table::render(&columns, &rows, table::terminal_width()));
+}
+
+fn select_note(notes: &[Note], number: Option<usize>) -> Result<&Note, CliError> {
+    if notes.len() == 1 {
+        return Ok(&notes[0]);
+    }
+
+    // If number specified, use it
     if let Some(n) = number {
         if n > 0 && n <= notes.len() {
-            return Some(&notes[n - 1]);
+            return Ok(&notes[n - 1]);
+        }
+        return Err(CliError::invalid_input(format!(
+            "Invalid number {}. Use 1-{}",
+            n,
+            notes.len()
+        )));
+    }
+
+    // List the candidates so the user can pick one with --number
+    let mut error = CliError::new(
+        ErrorKind::Ambiguous,
+        "Multiple notes found with the same title. Use --number <N> to select a specific note.",
+    );
+    error.details = notes
+        .iter()
+        .enumerate()
+        .map(|(i, note)| format!("  {}. {} (ID: {})", i + 1, note.title, note.id))
+        .collect();
+    Err(error)
+}
+
+/// Resolve an item from `--id` or a title (any type), disambiguated by `--number`.
+/// `deleted` selects between live items and items in the trash.
+fn resolve_item(
+    conn: &Connection,
+    title: Option<&str>,
+    id: Option<&str>,
+    number: Option<usize>,
+    deleted: bool,
+) -> Result<Note, CliError> {
+    if let Some(id) = id {
+        let is_deleted: Option<bool> = conn
+            .query_row(
+                "SELECT deleted_at IS NOT NULL FROM items WHERE id = ?1",
+                [id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| CliError::database(format!("Failed to find item: {}", e)))?;
+        return match is_deleted {
+            Some(is_deleted) if is_deleted == deleted => get_note_by_id(conn, id)
+                .map_err(|e| CliError::database(format!("Failed to find item: {}", e)))?
+                .ok_or_else(|| CliError::not_found(format!("No item found with ID '{}'", id))),
+            Some(true) => Err(CliError::not_found(format!(
+                "Item '{}' is in the trash",
+                id
+            ))),
+            Some(false) => Err(CliError::not_found(format!(
+                "Item '{}' is not in the trash",
+                id
+            ))),
+            None => Err(CliError::not_found(format!("No item found with ID '{}'", id))),
+        };
+    }
+
+    let title = title.unwrap_or_default();
+    let items = find_items_by_title(conn, title, deleted)
+        .map_err(|e| CliError::database(format!("Failed to find item: {}", e)))?;
+    if items.is_empty() {
+        return Err(CliError::not_found(format!(
+            "No {} found with title '{}'",
+            if deleted { "deleted item" } else { "item" },
+            title
+        )));
+    }
+    select_note(&items, number).cloned()
+}
+
+/// Resolve a live item from an argument that may be an ID or a title
+fn resolve_item_ref(
+    conn: &Connection,
+    id_or_title: &str,
+    number: Option<usize>,
+) -> Result<Note, CliError> {
+    let by_id = resolve_item(conn, None, Some(id_or_title), None, false);
+    match by_id {
+        Ok(item) => Ok(item),
+        Err(e) if e.kind == ErrorKind::NotFound => {
+            resolve_item(conn, Some(id_or_title), None, number, false)
         }
-        eprintln!(
-            "{}: Invalid number {}. Use 1-{}",
-            "Error".red(),
-            n,
-            notes.len()
-        );
-        return None;
+        Err(e) => Err(e),
     }
+}
 
-    // Show selection prompt
-    eprintln!(
-        "{}: Multiple notes found with the same title:",
-        "Note".yellow()
-    );
-    for (i, note) in notes.iter().enumerate() {
-        eprintln!("  {}. {} (ID: {})", i + 1, note.title, note.id);
+/// Find a live book by ID, or by case-insensitive title
+fn resolve_book(conn: &Connection, id_or_title: &str) -> Result<Note, CliError> {
+    let by_id = get_note_by_id(conn, id_or_title)
+        .map_err(|e| CliError::database(format!("Failed to find book: {}", e)))?;
+    if let Some(book) = by_id.filter(|item| item.item_type == "book") {
+        return Ok(book);
+    }
+
+    let books: Vec<Note> = find_items_by_title(conn, id_or_title, false)
+        .map_err(|e| CliError::database(format!("Failed to find book: {}", e)))?
+        .into_iter()
+        .filter(|item| item.item_type == "book")
+        .collect();
+    if books.is_empty() {
+        return Err(CliError::not_found(format!(
+            "No book found with ID or title '{}'",
+            id_or_title
+        )));
+    }
+    select_note(&books, None).cloned()
+}
+
+/// Ask a yes/no question on stderr; anything but `y`/`yes` declines
+fn confirm(question: &str) -> Result<bool, CliError> {
+    use std::io::{BufRead, Write};
+
+    eprint!("{} [y/N]: ", question);
+    let _ = std::io::stderr().flush();
+    let mut line = String::new();
+    std::io::stdin()
+        .lock()
+        .read_line(&mut line)
+        .map_err(|e| format!("Failed to read answer: {}", e))?;
+    Ok(matches!(line.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Prompt for one of several search hits by number (like `select_note`, but
+/// reading the choice from stdin). Only notes are offered since only notes open in the GUI.
+fn prompt_select_note<'a>(conn: &Connection, notes: &'a [Note]) -> Result<&'a Note, CliError> {
+    use std::io::{BufRead, Write};
+
+    let candidates: Vec<&Note> = notes.iter().filter(|n| n.item_type == "note").collect();
+    if candidates.is_empty() {
+        return Err(CliError::not_found("None of the results are notes"));
+    }
+    if candidates.len() == 1 {
+        return Ok(candidates[0]);
+    }
+
+    for (i, note) in candidates.iter().enumerate() {
+        let path = get_item_path(conn, &note.id).unwrap_or_else(|_| note.title.clone());
+        eprintln!("  {}. {} (ID: {})", i + 1, path.cyan(), note.id);
+    }
+    eprint!("\nSelect a note [1-{}]: ", candidates.len());
+    let _ = std::io::stderr().flush();
+
+    let mut line = String::new();
+    std::io::stdin()
+        .lock()
+        .read_line(&mut line)
+        .map_err(|e| format!("Failed to read selection: {}", e))?;
+    match line.trim().parse::<usize>() {
+        Ok(n) if n > 0 && n <= candidates.len() => Ok(candidates[n - 1]),
+        _ => Err(CliError::invalid_input(format!(
+            "Invalid selection '{}'",
+            line.trim()
+        ))),
     }
-    eprintln!("\nUse {} to select a specific note.", "--number <N>".cyan());
-    None
 }
 
-pub fn run_cli(cli: Cli) -> Result<(), String> {
+pub fn run_cli(cli: Cli) -> Result<(), CliError> {
     let Some(command) = cli.command else {
         // No subcommand - return to start GUI
-        return Err("no-command".to_string());
+        return Err(CliError::new(ErrorKind::NoCommand, "no command given"));
     };
 
-    let conn = open_connection().map_err(|e| format!("Failed to open database: {}", e))?;
+    if cli.no_color {
+        colored::control::set_override(false);
+    }
+
+    // Config validation must work before a database exists
+    if let Commands::ConfigValidate = command {
+        let path = get_config_file_path();
+        if !path.exists() {
+            return Err(CliError::config(format!(
+                "No config file found at {}",
+                path.display()
+            )));
+        }
+        let config = crate::config::parse_config_file(&path).map_err(CliError::config)?;
+        let problems = config.validate();
+        if !problems.is_empty() {
+            let mut error = CliError::config(format!(
+                "{} problem{} in config",
+                problems.len(),
+                if problems.len() == 1 { "" } else { "s" }
+            ));
+            error.details = problems
+                .iter()
+                .map(|problem| format!("{}: {}", path.display(), problem))
+                .collect();
+            return Err(error);
+        }
+        println!("config OK ({})", path.display());
+        return Ok(());
+    }
+
+    if let Commands::Config { action } = &command {
+        return run_config_action(action, cli.json);
+    }
+
+    // Migrating is how a database gets created from the CLI alone
+    if let Commands::Migrate = command {
+        let db_path = get_db_path();
+        if let Some(parent) = db_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                CliError::database(format!("Failed to create {}: {}", parent.display(), e))
+            })?;
+        }
+        let conn = open_connection()
+            .map_err(|e| CliError::database(format!("Failed to open database: {}", e)))?;
+        let applied = crate::migrations::migrate(&conn).map_err(CliError::database)?;
+        for migration in &applied {
+            println!("Applied migration {}: {}", migration.version, migration.description);
+        }
+
+        let version = crate::migrations::schema_version(&conn)
+            .map_err(|e| CliError::database(format!("Failed to read schema version: {}", e)))?;
+        let latest = crate::migrations::latest_version();
+        if version > latest {
+            eprintln!(
+                "{}: schema version {} is newer than this build knows ({})",
+                "Warning".yellow(),
+                version,
+                latest
+            );
+        } else if applied.is_empty() {
+            println!("{} is up to date (schema version {})", db_path.display(), version);
+        } else {
+            println!("{} is now at schema version {}", db_path.display(), version);
+        }
+        return Ok(());
+    }
+
+    // Converting opens the database with the key it has now, not the configured one
+    if matches!(command, Commands::Encrypt | Commands::Decrypt) {
+        let key = irisnotes_paths::resolve_database_key()?.ok_or_else(|| {
+            CliError::invalid_input(
+                "No encryption key configured; set IRISNOTES_DB_KEY or [storage] encryption_key",
+            )
+        })?;
+        let db_path = get_db_path();
+        if let Commands::Encrypt = command {
+            crate::encryption::convert(&db_path, None, Some(&key)).map_err(CliError::database)?;
+            println!("Encrypted {}", db_path.display());
+        } else {
+            crate::encryption::convert(&db_path, Some(&key), None).map_err(CliError::database)?;
+            println!("Decrypted {}", db_path.display());
+            eprintln!(
+                "Remove the key from IRISNOTES_DB_KEY and [storage] encryption_key, \
+                 or the apps will fail to open it"
+            );
+        }
+        return Ok(());
+    }
+
+    let date_format = resolve_date_format(cli.date_format.clone())?;
+    let date_format = date_format.as_deref();
+
+    let conn = open_connection()
+        .map_err(|e| CliError::database(format!("Failed to open database: {}", e)))?;
 
     match command {
-        Commands::List { full } => {
-            let notes = get_all_notes(&conn).map_err(|e| format!("Failed to list notes: {}", e))?;
-            print_note_list(&notes, full);
+        Commands::List {
+            full,
+            include_content,
+            include_deleted,
+        } => {
+            if cli.json {
+                let notes = if include_content {
+                    get_all_notes(&conn, include_deleted)
+                } else {
+                    get_all_notes_metadata(&conn, include_deleted)
+                }
+                .map_err(|e| CliError::database(format!("Failed to list notes: {}", e)))?;
+                let output: Vec<NoteJson> = notes
+                    .iter()
+                    .filter(|note| note.item_type == "note")
+                    .map(|note| NoteJson::new(&conn, note, include_content))
+                    .collect();
+                print_json(&output)?;
+            } else {
+                let notes =
+                    get_all_notes(&conn, include_deleted).map_err(|e| {
+                        CliError::database(format!("Failed to list notes: {}", e))
+                    })?;
+                match cli.format {
+                    OutputFormat::Plain => print_note_list(&notes, full),
+                    OutputFormat::Table => print_note_table(&conn, &notes, None, date_format),
+                }
+            }
+        }
+        Commands::Recent { limit, created } => {
+            let notes = get_recent_notes(&conn, created, limit)
+                .map_err(|e| CliError::database(format!("Failed to list notes: {}", e)))?;
+
+            if cli.json {
+                let output: Vec<NoteJson> = notes
+                    .iter()
+                    .map(|(note, _)| NoteJson::new(&conn, note, false))
+                    .collect();
+                return Ok(print_json(&output)?);
+            }
+            if notes.is_empty() {
+                println!("No notes yet");
+                return Ok(());
+            }
+
+            let rows: Vec<Vec<String>> = notes
+                .iter()
+                .map(|(note, timestamp)| {
+                    let preview = strip_html(&note.content).replace('\n', " ");
+                    vec![
+                        crate::timestamp::format_timestamp(timestamp, date_format),
+                        get_item_path(&conn, &note.id).unwrap_or_else(|_| note.title.clone()),
+                        truncate(preview.trim(), 60),
+                    ]
+                })
+                .collect();
+            match cli.format {
+                OutputFormat::Plain => {
+                    for row in &rows {
+                        println!("{}  {}  {}", row[0].dimmed(), row[1].cyan(), row[2].dimmed());
+                    }
+                }
+                OutputFormat::Table => {
+                    use crate::table::{self, Column};
+                    let columns = [
+                        Column::left(if created { "Created" } else { "Updated" }),
+                        Column::left("Path"),
+                        Column::left("Preview"),
+                    ];
+                    println!("{}", table::render(&columns, &rows, table::terminal_width()));
+                }
+            }
         }
-        Commands::Search { query } => {
-            let notes =
-                search_notes(&conn, &query).map_err(|e| format!("Search failed: {}", e))?;
+        Commands::Search {
+            query,
+            select,
+            fields,
+            include_deleted,
+            tag,
+            limit,
+            all,
+            titles_only,
+            book,
+            raw_query,
+            case_sensitive,
+            score,
+        } => {
+            use std::io::IsTerminal;
+
+            if fields.is_some() && !cli.json {
+                return Err(CliError::invalid_input(
+                    "--fields only applies to --json output",
+                ));
+            }
+
+            let tag = tag.as_deref().map(normalize_tag);
+            if tag.is_some() {
+                crate::migrations::migrate(&conn).map_err(CliError::database)?;
+            }
+            let book = match book.as_deref() {
+                Some(book) => Some(resolve_book(&conn, book)?),
+                None => None,
+            };
+            // One row past the limit tells whether the list was cut short
+            let limit = (!all).then_some(limit);
+            let options = SearchOptions {
+                include_deleted,
+                tag: tag.as_deref(),
+                limit: limit.map(|limit| limit + 1),
+                titles_only,
+                within: book.as_ref().map(|book| book.id.as_str()),
+                raw_query,
+                case_sensitive,
+            };
+            let hits = search_notes(&conn, &query, options).map_err(|e| {
+                if raw_query {
+                    CliError::invalid_input(format!("Invalid FTS5 query '{}': {}", query, e))
+                } else {
+                    CliError::database(format!("Search failed: {}", e))
+                }
+            })?;
+            let (mut notes, mut scores): (Vec<Note>, Vec<Option<f64>>) = hits.into_iter().unzip();
+            let truncated = limit.is_some_and(|limit| notes.len() > limit);
+            if let Some(limit) = limit {
+                notes.truncate(limit);
+                scores.truncate(limit);
+            }
+
+            if cli.json {
+                let fields = parse_search_fields(fields)?;
+                let output: Vec<_> = notes
+                    .iter()
+                    .zip(&scores)
+                    .map(|(note, note_score)| {
+                        let mut object = project_note(&conn, note, &fields);
+                        if score {
+                            object.insert("score".to_string(), serde_json::json!(note_score));
+                        }
+                        object
+                    })
+                    .collect();
+                return Ok(print_json(&output)?);
+            }
+
+            // Without a terminal to read from, --select degrades to the plain listing
+            let interactive = select && std::io::stdin().is_terminal();
             if notes.is_empty() {
                 println!("No notes found matching '{}'", query);
+            } else if interactive {
+                let note = prompt_select_note(&conn, &notes)?;
+                println!("Opening note: {}", note.title.cyan());
+                open_note_in_gui(&note.id)?;
+            } else if cli.format == OutputFormat::Table {
+                print_note_table(&conn, &notes, score.then_some(scores.as_slice()), date_format);
             } else {
-                println!(
-                    "Found {} result{}:",
-                    notes.len(),
-                    if notes.len() == 1 { "" } else { "s" }
-                );
-                for note in &notes {
+                if truncated {
+                    println!(
+                        "Showing the first {} results (more match; use --limit or --all):",
+                        notes.len()
+                    );
+                } else {
+                    println!(
+                        "Found {} result{}:",
+                        notes.len(),
+                        if notes.len() == 1 { "" } else { "s" }
+                    );
+                }
+                let terms = irisnotes_text::query_terms(&query);
+                for (note, note_score) in notes.iter().zip(&scores) {
                     let path =
                         get_item_path(&conn, &note.id).unwrap_or_else(|_| note.title.clone());
-                    let plain_content = strip_html(&note.content);
-                    let preview = truncate(&plain_content.replace('\n', " "), 60);
-                    println!("  {} - {}", path.cyan(), preview.dimmed());
+                    let plain_content = strip_html(&note.content).replace('\n', " ");
+                    // Title-only hits have nothing to highlight in the body
+                    let preview = highlight_snippet(&plain_content, &terms, PREVIEW_WIDTH)
+                        .unwrap_or_else(|| {
+                            truncate(&plain_content, PREVIEW_WIDTH).dimmed().to_string()
+                        });
+                    if score {
+                        print!("  {:>5}", format_score(*note_score).yellow());
+                    }
+                    println!("  {} - {}", path.cyan(), preview);
                 }
             }
         }
-        Commands::Open { title, number } => {
-            let notes = find_notes_by_title(&conn, &title)
-                .map_err(|e| format!("Failed to find note: {}", e))?;
+        Commands::Open {
+            title,
+            number,
+            fuzzy,
+        } => {
+            let note = resolve_note_by_title(&conn, &title, number, fuzzy)?;
+            println!("Opening note: {}", note.title.cyan());
+            open_note_in_gui(&note.id)?;
+        }
+        Commands::Id { id } => {
+            let note =
+                get_note_by_id(&conn, &id).map_err(|e| CliError::database(format!("Failed to find note: {}", e)))?;
 
-            if notes.is_empty() {
-                eprintln!("{}: No note found with title '{}'", "Error".red(), title);
-                std::process::exit(1);
+            let Some(note) = note else {
+                return Err(CliError::not_found(format!("No note found with ID '{}'", id)));
+            };
+            println!("Opening note: {}", note.title.cyan());
+            open_note_in_gui(&note.id)?;
+        }
+        Commands::Path {
+            target,
+            number,
+            all,
+        } => {
+            let items = if all {
+                let by_id = get_note_by_id(&conn, &target)
+                    .map_err(|e| CliError::database(format!("Failed to find item: {}", e)))?;
+                match by_id {
+                    Some(item) => vec![item],
+                    None => find_items_by_title(&conn, &target, false)
+                        .map_err(|e| CliError::database(format!("Failed to find item: {}", e)))?,
+                }
+            } else {
+                vec![resolve_item_ref(&conn, &target, number)?]
+            };
+            if items.is_empty() {
+                return Err(CliError::not_found(format!(
+                    "No item found with ID or title '{}'",
+                    target
+                )));
             }
 
-            if let Some(note) = select_note(&notes, number) {
-                println!("Opening note: {}", note.title.cyan());
-                open_note_in_gui(&note.id);
+            if cli.json {
+                let output: Vec<NoteJson> = items
+                    .iter()
+                    .map(|item| NoteJson::new(&conn, item, false))
+                    .collect();
+                return Ok(print_json(&output)?);
+            }
+            // Plain lines with no color, so the output pipes cleanly
+            for item in &items {
+                let path = get_item_path(&conn, &item.id)
+                    .map_err(|e| CliError::database(format!("Failed to build path: {}", e)))?;
+                println!("{}", path);
+            }
+        }
+        Commands::Tree {
+            counts,
+            book,
+            depth,
+            show_ids,
+            show_counts,
+        } => {
+            if depth == Some(0) {
+                return Err(CliError::invalid_input("--depth must be at least 1"));
+            }
+            let root = match book.as_deref() {
+                Some(book) => Some(resolve_book(&conn, book)?.id),
+                None => None,
+            };
+            let notes = get_all_notes_metadata(&conn, false)
+                .map_err(|e| CliError::database(format!("Failed to get items: {}", e)))?;
+            let children = group_children(&notes);
+            let note_counts = if counts {
+                Some(count_descendant_notes(&children))
+            } else {
+                None
+            };
+
+            if cli.json {
+                let tree = tree_json(&children, root.as_deref(), note_counts.as_ref(), depth);
+                return Ok(print_json(&tree)?);
+            }
+
+            struct TreeStyle<'a> {
+                note_counts: Option<&'a HashMap<String, usize>>,
+                depth: Option<usize>,
+                show_ids: bool,
+                show_counts: bool,
+            }
+
+            fn print_tree(
+                children: &ChildMap,
+                parent_id: Option<&str>,
+                indent: usize,
+                style: &TreeStyle,
+            ) {
+                if style.depth.is_some_and(|depth| indent >= depth) {
+                    return;
+                }
+                for note in children.get(&parent_id).map(Vec::as_slice).unwrap_or_default() {
+                    let icon = match note.item_type.as_str() {
+                        "book" => "📚",
+                        "section" => "📁",
+                        "note" => "📝",
+                        _ => "•",
+                    };
+                    let prefix = "  ".repeat(indent);
+                    let is_container = note.item_type != "note";
+                    let mut suffix = String::new();
+                    if let Some(counts) = style.note_counts.filter(|_| is_container) {
+                        suffix.push_str(&format!(
+                            " ({})",
+                            counts.get(&note.id).copied().unwrap_or(0)
+                        ));
+                    }
+                    if style.show_counts && is_container {
+                        let direct = children.get(&Some(note.id.as_str())).map_or(0, Vec::len);
+                        suffix.push_str(&format!(" [{}]", direct));
+                    }
+                    if style.show_ids {
+                        suffix.push_str(&format!(" ({})", note.id));
+                    }
+                    println!("{}{} {}{}", prefix, icon, note.title, suffix.dimmed());
+                    print_tree(children, Some(&note.id), indent + 1, style);
+                }
+            }
+
+            let style = TreeStyle {
+                note_counts: note_counts.as_ref(),
+                depth,
+                show_ids,
+                show_counts,
+            };
+            print_tree(&children, root.as_deref(), 0, &style);
+        }
+        Commands::Show {
+            title,
+            number,
+            raw,
+            markdown,
+            standalone,
+            links,
+            fuzzy,
+        } => {
+            let note = &resolve_note_by_title(&conn, &title, number, fuzzy)?;
+            // Databases from before tags existed simply have none
+            let tags = get_item_tags(&conn, &note.id).unwrap_or_default();
+            if cli.json {
+                let output = ShowJson {
+                    note: NoteJson::new(&conn, note, true),
+                    text: strip_html(&note.content),
+                    tags,
+                    links: links.then(|| links_json(&conn, &note.content)),
+                };
+                return Ok(print_json(&output)?);
+            }
+            if standalone {
+                // A full document is meant for redirecting to a file, so no banner
+                print!("{}", crate::export::html_document(&note.title, &note.content));
+                return Ok(());
+            }
+            println!("{}", "─".repeat(50).dimmed());
+            println!("{}", note.title.cyan().bold());
+            if !tags.is_empty() {
+                let tags: Vec<String> = tags.iter().map(|t| format!("#{}", t)).collect();
+                println!("{}", tags.join(" ").yellow());
+            }
+            println!("{}", "─".repeat(50).dimmed());
+            if raw {
+                println!("{}", note.content);
+            } else if markdown {
+                println!("{}", crate::markdown::html_to_markdown(&note.content));
             } else {
-                std::process::exit(1);
+                println!("{}", strip_html(&note.content));
+            }
+            if links {
+                print_links(&conn, &note.content);
             }
         }
-        Commands::Id { id } => {
-            let note =
-                get_note_by_id(&conn, &id).map_err(|e| format!("Failed to find note: {}", e))?;
+        Commands::Stats => {
+            let stats = collect_stats(&conn)
+                .map_err(|e| CliError::database(format!("Failed to read statistics: {}", e)))?;
+            if cli.json {
+                return Ok(print_json(&stats)?);
+            }
 
-            if let Some(note) = note {
-                println!("Opening note: {}", note.title.cyan());
-                open_note_in_gui(&note.id);
+            use crate::table::{self, Column};
+            let columns = [Column::left("Statistic"), Column::right("Value")];
+            let largest = match &stats.largest_note {
+                Some(note) => format!("{} ({} words)", note.path, note.words),
+                None => "-".to_string(),
+            };
+            let rows: Vec<Vec<String>> = [
+                ("Books", stats.books.to_string()),
+                ("Sections", stats.sections.to_string()),
+                ("Notes", stats.notes.to_string()),
+                ("Words", stats.words.to_string()),
+                ("Largest note", largest),
+                ("In trash", stats.deleted.to_string()),
+                ("Database size", format_size(stats.database_bytes)),
+            ]
+            .into_iter()
+            .map(|(label, value)| vec![label.to_string(), value])
+            .collect();
+            println!("{}", table::render(&columns, &rows, table::terminal_width()));
+        }
+        Commands::Optimize => {
+            let db_path = get_db_path();
+            let size_before = file_size(&db_path);
+
+            // Merge FTS segments in a transaction so a failure leaves the index untouched
+            let tx = conn
+                .unchecked_transaction()
+                .map_err(|e| CliError::database(format!("Failed to start transaction: {}", e)))?;
+            tx.execute("INSERT INTO items_fts(items_fts) VALUES('optimize')", [])
+                .map_err(|e| CliError::database(format!("Failed to optimize search index: {}", e)))?;
+            tx.commit()
+                .map_err(|e| CliError::database(format!("Failed to commit search index optimization: {}", e)))?;
+
+            conn.execute_batch("PRAGMA optimize;")
+                .map_err(|e| CliError::database(format!("PRAGMA optimize failed: {}", e)))?;
+
+            vacuum(&conn)?;
+
+            let size_after = file_size(&db_path);
+            println!(
+                "Optimized {}: {} → {} ({} reclaimed)",
+                db_path.display(),
+                format_size(size_before),
+                format_size(size_after),
+                format_size(size_before.saturating_sub(size_after))
+            );
+        }
+        Commands::Backup { out, keep } => {
+            if keep == Some(0) {
+                return Err(CliError::invalid_input("--keep must be at least 1"));
+            }
+            let dir = match out {
+                Some(dir) => dir,
+                None => irisnotes_paths::config_dir()?,
+            };
+
+            let path = backup_database(&conn, &dir)?;
+            println!("{}", path.display());
+
+            if let Some(keep) = keep {
+                for pruned in prune_backups(&dir, keep)? {
+                    eprintln!("Deleted old backup {}", pruned.display());
+                }
+            }
+        }
+        Commands::Reindex => {
+            let indexed = rebuild_search_index(&conn)?;
+            println!(
+                "Rebuilt the search index: {} item{} indexed",
+                indexed,
+                if indexed == 1 { "" } else { "s" }
+            );
+        }
+        Commands::Doctor { fix } => {
+            use crate::doctor::Severity;
+
+            let findings = crate::doctor::run(&conn, fix).map_err(CliError::database)?;
+            if cli.json {
+                print_json(&findings)?;
+            } else if findings.is_empty() {
+                println!("No problems found in {}", get_db_path().display());
+            } else {
+                for finding in &findings {
+                    let severity = match finding.severity {
+                        Severity::Error => "error".red().bold(),
+                        Severity::Warning => "warning".yellow().bold(),
+                    };
+                    let fixed = if finding.fixed {
+                        " (fixed)".green().to_string()
+                    } else {
+                        String::new()
+                    };
+                    println!("{} [{}] {}{}", severity, finding.check, finding.message, fixed);
+                }
+            }
+
+            let unfixed = findings.iter().filter(|finding| !finding.fixed).count();
+            if unfixed > 0 {
+                return Err(CliError::new(
+                    ErrorKind::Unhealthy,
+                    format!(
+                        "{} problem{} found{}",
+                        unfixed,
+                        if unfixed == 1 { "" } else { "s" },
+                        if fix { " that --fix cannot repair" } else { "" }
+                    ),
+                ));
+            }
+        }
+        Commands::TagSearch {
+            query,
+            tag,
+            remove,
+            dry_run,
+            yes,
+        } => {
+            let tag = normalize_tag(&tag);
+            if tag.is_empty() {
+                return Err(CliError::invalid_input("Tag name cannot be empty"));
+            }
+
+            crate::migrations::migrate(&conn).map_err(CliError::database)?;
+            let hits = search_notes(&conn, &query, SearchOptions::default())
+                .map_err(|e| CliError::database(format!("Search failed: {}", e)))?;
+            let targets: Vec<&Note> = hits
+                .iter()
+                .map(|(note, _)| note)
+                .filter(|n| n.item_type == "note")
+                .collect();
+            let action = if remove { "remove" } else { "add" };
+
+            if targets.is_empty() {
+                println!("No notes found matching '{}'", query);
+                return Ok(());
+            }
+
+            if dry_run {
+                println!(
+                    "Would {} tag '{}' on {} note{}:",
+                    action,
+                    tag.cyan(),
+                    targets.len(),
+                    if targets.len() == 1 { "" } else { "s" }
+                );
+                for note in &targets {
+                    let path =
+                        get_item_path(&conn, &note.id).unwrap_or_else(|_| note.title.clone());
+                    println!("  {}", path);
+                }
+                return Ok(());
+            }
+
+            if targets.len() > BULK_TAG_CONFIRM_THRESHOLD && !yes {
+                return Err(CliError::invalid_input(format!(
+                    "{} notes match '{}'. Rerun with --yes to {} tag '{}' on all of them (or --dry-run to review)",
+                    targets.len(),
+                    query,
+                    action,
+                    tag
+                )));
+            }
+
+            let ids: Vec<&str> = targets.iter().map(|n| n.id.as_str()).collect();
+            let tx = conn
+                .unchecked_transaction()
+                .map_err(|e| CliError::database(format!("Failed to start transaction: {}", e)))?;
+            let affected = if remove {
+                remove_tag_from_items(&tx, &tag, &ids)
+            } else {
+                add_tag_to_items(&tx, &tag, &ids)
+            }
+            .map_err(|e| CliError::database(format!("Failed to update tags: {}", e)))?;
+            tx.commit()
+                .map_err(|e| CliError::database(format!("Failed to commit tag changes: {}", e)))?;
+
+            if remove {
+                println!("Removed tag '{}' from {} of {} matching notes", tag.cyan(), affected, ids.len());
             } else {
-                eprintln!("{}: No note found with ID '{}'", "Error".red(), id);
-                std::process::exit(1);
-            }
-        }
-        Commands::Tree => {
-            let notes = get_all_notes(&conn).map_err(|e| format!("Failed to get items: {}", e))?;
-
-            fn print_tree(notes: &[Note], parent_id: Option<&str>, indent: usize) {
-                for note in notes {
-                    let note_parent = note.parent_id.as_deref();
-                    if note_parent == parent_id {
-                        let icon = match note.item_type.as_str() {
-                            "book" => "📚",
-                            "section" => "📁",
-                            "note" => "📝",
-                            _ => "•",
-                        };
-                        let prefix = "  ".repeat(indent);
-                        println!("{}{} {}", prefix, icon, note.title);
-                        print_tree(notes, Some(&note.id), indent + 1);
+                println!("Tagged {} of {} matching notes with '{}'", affected, ids.len(), tag.cyan());
+            }
+        }
+        Commands::Tags { action } => {
+            crate::migrations::migrate(&conn).map_err(CliError::database)?;
+            match action {
+                TagAction::Add {
+                    target,
+                    tags,
+                    number,
+                } => {
+                    let item = resolve_item_ref(&conn, &target, number)?;
+                    let tags: Vec<String> = tags.iter().map(|t| normalize_tag(t)).collect();
+                    if tags.iter().any(String::is_empty) {
+                        return Err(CliError::invalid_input("Tag name cannot be empty"));
+                    }
+
+                    let tx = conn.unchecked_transaction().map_err(|e| {
+                        CliError::database(format!("Failed to start transaction: {}", e))
+                    })?;
+                    let mut added = Vec::new();
+                    for tag in &tags {
+                        let affected = add_tag_to_items(&tx, tag, &[item.id.as_str()])
+                            .map_err(|e| CliError::database(format!("Failed to add tag: {}", e)))?;
+                        if affected > 0 {
+                            added.push(tag.as_str());
+                        }
+                    }
+                    tx.commit().map_err(|e| {
+                        CliError::database(format!("Failed to commit tag changes: {}", e))
+                    })?;
+
+                    if added.is_empty() {
+                        println!("'{}' already has every given tag", item.title);
+                    } else {
+                        println!("Tagged '{}' with {}", item.title, added.join(", "));
                     }
                 }
+                TagAction::Remove {
+                    target,
+                    tag,
+                    number,
+                } => {
+                    let item = resolve_item_ref(&conn, &target, number)?;
+                    let tag = normalize_tag(&tag);
+                    let removed = remove_tag_from_items(&conn, &tag, &[item.id.as_str()])
+                        .map_err(|e| CliError::database(format!("Failed to remove tag: {}", e)))?;
+                    if removed == 0 {
+                        return Err(CliError::not_found(format!(
+                            "'{}' has no tag '{}'",
+                            item.title, tag
+                        )));
+                    }
+                    println!("Removed tag '{}' from '{}'", tag, item.title);
+                }
+                TagAction::List => {
+                    let counts = get_tag_counts(&conn)
+                        .map_err(|e| CliError::database(format!("Failed to list tags: {}", e)))?;
+                    if cli.json {
+                        return Ok(print_json(&counts)?);
+                    }
+                    if counts.is_empty() {
+                        println!("No tags yet");
+                    }
+                    for tag in &counts {
+                        let name = format!("#{}", tag.name);
+                        println!("{} {}", name.yellow(), tag.count.to_string().dimmed());
+                    }
+                }
+            }
+        }
+        Commands::ConfigValidate => unreachable!("handled before opening the database"),
+        Commands::Config { .. } => unreachable!("handled before opening the database"),
+        Commands::Migrate => unreachable!("handled before opening the database"),
+        Commands::Encrypt | Commands::Decrypt => {
+            unreachable!("handled before opening the database")
+        }
+        Commands::Import { path, parent } => {
+            let summary = crate::import::import_paths(&conn, &[path], parent.as_deref())?;
+            for skipped in &summary.skipped {
+                eprintln!(
+                    "{}: skipped {} (not a Markdown file)",
+                    "Warning".yellow(),
+                    skipped.display()
+                );
             }
 
-            print_tree(&notes, None, 0);
+            let plural = |n: usize| if n == 1 { "" } else { "s" };
+            println!(
+                "Imported {} book{}, {} section{} and {} note{}",
+                summary.books,
+                plural(summary.books),
+                summary.sections,
+                plural(summary.sections),
+                summary.notes,
+                plural(summary.notes)
+            );
+            if !summary.skipped.is_empty() {
+                println!(
+                    "Skipped {} non-Markdown file{}",
+                    summary.skipped.len(),
+                    plural(summary.skipped.len())
+                );
+            }
         }
-        Commands::Show { title, number, raw } => {
-            let notes = find_notes_by_title(&conn, &title)
-                .map_err(|e| format!("Failed to find note: {}", e))?;
+        Commands::Export {
+            target,
+            to,
+            out,
+            number,
+            jobs,
+        } => {
+            let format = if cli.json { ExportFormat::Json } else { to };
+            let root = match target.as_deref() {
+                Some(target) => Some(resolve_item_ref(&conn, target, number)?),
+                None => None,
+            };
+
+            let Some(out) = out else {
+                // Without a directory only a single note can be written, to stdout
+                return match root.filter(|root| root.item_type == "note") {
+                    Some(note) => {
+                        print!("{}", crate::export::export_note(&conn, &note.id, format)?);
+                        Ok(())
+                    }
+                    None => Err(CliError::invalid_input(
+                        "Exporting a book, section or the whole database needs --out <dir>",
+                    )),
+                };
+            };
 
+            let summary = crate::export::export_tree(
+                &conn,
+                root.as_ref().map(|root| root.id.as_str()),
+                format,
+                &out,
+                jobs.map_or_else(crate::export::default_jobs, usize::from),
+            )?;
+
+            println!(
+                "Exported {} note{} in {} folder{} to {}",
+                summary.notes,
+                if summary.notes == 1 { "" } else { "s" },
+                summary.folders,
+                if summary.folders == 1 { "" } else { "s" },
+                out.display()
+            );
+        }
+        Commands::Edit { title, number, raw } => {
+            let notes = find_notes_by_title(&conn, &title)
+                .map_err(|e| CliError::database(format!("Failed to find note: {}", e)))?;
             if notes.is_empty() {
-                eprintln!("{}: No note found with title '{}'", "Error".red(), title);
-                std::process::exit(1);
+                return Err(CliError::not_found(format!(
+                    "No note found with title '{}'",
+                    title
+                )));
             }
+            let note = select_note(&notes, number)?;
 
-            if let Some(note) = select_note(&notes, number) {
-                println!("{}", "─".repeat(50).dimmed());
-                println!("{}", note.title.cyan().bold());
-                println!("{}", "─".repeat(50).dimmed());
-                if raw {
-                    println!("{}", note.content);
-                } else {
-                    println!("{}", strip_html(&note.content));
+            let (initial, extension) = if raw {
+                (note.content.clone(), "html")
+            } else {
+                (html_to_edit_buffer(&note.content), "txt")
+            };
+            let edited = edit_in_editor(&initial, extension)?;
+
+            if edited == initial {
+                println!("No changes to '{}'", note.title);
+                return Ok(());
+            }
+
+            let html = if raw {
+                edited
+            } else {
+                text_to_html(&edited)
+            };
+            crate::notes::set_content(&conn, &note.id, &html)?;
+            println!("Saved '{}'", note.title.cyan());
+        }
+        Commands::Move {
+            target,
+            parent,
+            to_root: _,
+            number,
+            before,
+            after,
+            recursive,
+        } => {
+            let item = resolve_item_ref(&conn, &target, number)?;
+            crate::hierarchy::require_recursive(&item.item_type, &item.title, recursive)
+                .map_err(CliError::invalid_input)?;
+
+            let parent = parent.as_deref();
+            crate::hierarchy::validate_parent(&conn, &item.item_type, parent)
+                .map_err(CliError::invalid_input)?;
+            crate::hierarchy::check_not_descendant(&conn, &item.id, parent)
+                .map_err(CliError::invalid_input)?;
+
+            let anchor = match before.as_deref().or(after.as_deref()) {
+                Some(sibling) => Some(
+                    crate::sort_order::find_sibling(&conn, parent, sibling)
+                        .map_err(CliError::invalid_input)?,
+                ),
+                None => None,
+            };
+            if anchor.as_deref() == Some(item.id.as_str()) {
+                return Err(CliError::invalid_input(
+                    "Cannot place an item relative to itself",
+                ));
+            }
+            let placement = match (&anchor, before.is_some()) {
+                (Some(id), true) => crate::sort_order::Placement::Before(id),
+                (Some(id), false) => crate::sort_order::Placement::After(id),
+                (None, _) => crate::sort_order::Placement::Append,
+            };
+
+            // Descendants follow through their parent_id, so only the item itself is updated
+            let tx = conn
+                .unchecked_transaction()
+                .map_err(|e| CliError::database(format!("Failed to start transaction: {}", e)))?;
+            let sort_order =
+                crate::sort_order::sort_order_for(&tx, parent, placement, Some(&item.id))?;
+            tx.execute(
+                "UPDATE items SET parent_id = ?1, sort_order = ?2 WHERE id = ?3",
+                rusqlite::params![parent, sort_order, item.id],
+            )
+            .map_err(|e| CliError::database(format!("Failed to move item: {}", e)))?;
+            tx.commit()
+                .map_err(|e| CliError::database(format!("Failed to commit move: {}", e)))?;
+
+            let path = get_item_path(&conn, &item.id).unwrap_or_else(|_| item.title.clone());
+            println!("Moved to {}", path.cyan());
+        }
+        Commands::Rename {
+            target,
+            new_title,
+            number,
+            force,
+        } => {
+            let item = resolve_item_ref(&conn, &target, number)?;
+            let new_title = new_title.trim();
+            if new_title.is_empty() {
+                return Err(CliError::invalid_input("Title cannot be empty"));
+            }
+
+            // Title lookups (open, show, delete) get ambiguous when siblings share a title
+            let collision: Option<String> = conn
+                .query_row(
+                    "SELECT id FROM items
+                     WHERE parent_id IS ?1 AND id != ?2 AND deleted_at IS NULL
+                       AND LOWER(title) = LOWER(?3)
+                     LIMIT 1",
+                    rusqlite::params![item.parent_id, item.id, new_title],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(|e| CliError::database(format!("Failed to check siblings: {}", e)))?;
+            if let Some(other_id) = collision {
+                if !force {
+                    return Err(CliError::invalid_input(format!(
+                        "A sibling is already titled '{}' (ID: {}); pass --force to rename anyway",
+                        new_title, other_id
+                    )));
+                }
+            }
+
+            // The items_fts_update trigger keeps the search index in step
+            conn.execute(
+                "UPDATE items SET title = ?1 WHERE id = ?2",
+                rusqlite::params![new_title, item.id],
+            )
+            .map_err(|e| CliError::database(format!("Failed to rename item: {}", e)))?;
+
+            println!("Renamed '{}' → '{}'", item.title, new_title.cyan());
+        }
+        Commands::Restore {
+            title,
+            id,
+            number,
+            with_parents,
+        } => {
+            let item = resolve_item(&conn, title.as_deref(), id.as_deref(), number, true)?;
+
+            let tx = conn
+                .unchecked_transaction()
+                .map_err(|e| CliError::database(format!("Failed to start transaction: {}", e)))?;
+            let mut restored = crate::hierarchy::restore_subtree(&tx, &item.id)?;
+            let trashed_ancestors = crate::hierarchy::deleted_ancestors(&tx, &item.id)?;
+            if with_parents {
+                for (ancestor_id, _) in &trashed_ancestors {
+                    restored += tx
+                        .execute(
+                            "UPDATE items SET deleted_at = NULL WHERE id = ?1",
+                            [ancestor_id],
+                        )
+                        .map_err(|e| {
+                            CliError::database(format!("Failed to restore parent: {}", e))
+                        })?;
+                }
+            }
+            tx.commit()
+                .map_err(|e| CliError::database(format!("Failed to commit restore: {}", e)))?;
+
+            println!(
+                "Restored '{}' ({} item{})",
+                item.title.cyan(),
+                restored,
+                if restored == 1 { "" } else { "s" }
+            );
+            if !with_parents && !trashed_ancestors.is_empty() {
+                let titles: Vec<String> = trashed_ancestors
+                    .iter()
+                    .map(|(_, title)| format!("'{}'", title))
+                    .collect();
+                eprintln!(
+                    "{}: {} still in the trash, so the item stays hidden from the tree. \
+                     Rerun with --with-parents to restore the parent chain too.",
+                    "Warning".yellow(),
+                    titles.join(", ")
+                );
+            }
+        }
+        Commands::Delete {
+            title,
+            id,
+            number,
+            hard,
+            recursive,
+        } => {
+            use std::io::IsTerminal;
+
+            let item = resolve_item(&conn, title.as_deref(), id.as_deref(), number, false)?;
+
+            if crate::hierarchy::is_container(&item.item_type) && !recursive {
+                // Interactive users confirm the cascade; scripts must opt in with --recursive
+                if !std::io::stdin().is_terminal() {
+                    crate::hierarchy::require_recursive(&item.item_type, &item.title, false)
+                        .map_err(CliError::invalid_input)?;
+                }
+                let descendants = crate::hierarchy::descendant_ids(&conn, &item.id)?;
+                let question = format!(
+                    "'{}' is a {} containing {} item{}. {} all of them?",
+                    item.title,
+                    item.item_type,
+                    descendants.len(),
+                    if descendants.len() == 1 { "" } else { "s" },
+                    if hard { "Permanently delete" } else { "Delete" }
+                );
+                if !confirm(&question)? {
+                    return Err(CliError::new(ErrorKind::Cancelled, "Delete cancelled"));
                 }
+            }
+
+            let tx = conn
+                .unchecked_transaction()
+                .map_err(|e| CliError::database(format!("Failed to start transaction: {}", e)))?;
+            let affected = if hard {
+                crate::hierarchy::purge_subtree(&tx, &item.id)?
             } else {
-                std::process::exit(1);
+                crate::hierarchy::soft_delete_subtree(&tx, &item.id)?
+            };
+            tx.commit()
+                .map_err(|e| CliError::database(format!("Failed to commit delete: {}", e)))?;
+
+            println!(
+                "{} '{}' ({} item{} affected)",
+                if hard { "Permanently deleted" } else { "Moved to trash:" },
+                item.title.cyan(),
+                affected,
+                if affected == 1 { "" } else { "s" }
+            );
+        }
+        Commands::Create {
+            title,
+            parent,
+            content,
+            before,
+            after,
+        } => {
+            use std::io::{IsTerminal, Read};
+
+            let title = title.trim();
+            if title.is_empty() {
+                return Err(CliError::invalid_input("Title cannot be empty"));
+            }
+
+            // Resolve the destination before blocking on stdin
+            let parent = parent.as_deref();
+            crate::hierarchy::validate_parent(&conn, "note", parent)
+                .map_err(CliError::invalid_input)?;
+            let anchor = match before.as_deref().or(after.as_deref()) {
+                Some(sibling) => Some(
+                    crate::sort_order::find_sibling(&conn, parent, sibling)
+                        .map_err(CliError::invalid_input)?,
+                ),
+                None => None,
+            };
+            let placement = match (&anchor, before.is_some()) {
+                (Some(id), true) => crate::sort_order::Placement::Before(id),
+                (Some(id), false) => crate::sort_order::Placement::After(id),
+                (None, _) => crate::sort_order::Placement::Append,
+            };
+
+            let body = match content {
+                Some(content) => content,
+                None if !std::io::stdin().is_terminal() => {
+                    let mut body = String::new();
+                    std::io::stdin()
+                        .read_to_string(&mut body)
+                        .map_err(|e| format!("Failed to read stdin: {}", e))?;
+                    body
+                }
+                None => String::new(),
+            };
+
+            let id = create_note(&conn, title, &text_to_html(&body), parent, placement)?;
+            println!("{}", id);
+        }
+        Commands::Tail { follow, lines } => {
+            // Databases that predate the changelog get its table and triggers here
+            crate::migrations::migrate(&conn).map_err(CliError::database)?;
+
+            // Read the high-water mark first so --follow resumes after it even with -n 0
+            let mut last_seq: i64 = conn
+                .query_row("SELECT COALESCE(MAX(seq), 0) FROM changelog", [], |row| {
+                    row.get(0)
+                })
+                .map_err(|e| CliError::database(format!("Failed to read change log: {}", e)))?;
+
+            let entries = get_changelog(&conn, 0, Some(lines))
+                .map_err(|e| CliError::database(format!("Failed to read change log: {}", e)))?;
+            for entry in &entries {
+                print_changelog_entry(entry);
+                last_seq = last_seq.max(entry.seq);
+            }
+
+            if follow {
+                follow_changelog(&conn, &get_db_path(), last_seq)?;
             }
         }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{with_env, TempDir};
+
+    #[test]
+    fn truncate_counts_characters() {
+        assert_eq!(truncate("short", 10), "short");
+        assert_eq!(truncate("abcdefghijkl", 10), "abcdefg...");
+        // Cutting inside a multi-byte character used to panic
+        assert_eq!(truncate("çàéèüñöäßø€", 10), "çàéèüñö...");
+        assert_eq!(truncate("日本語のテキスト", 8), "日本語のテキスト");
+    }
+
+    #[test]
+    fn search_notes_uses_the_fts_index() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(crate::DATABASE_SCHEMA).unwrap();
+        // Only the indexed plain text mentions the word, so a LIKE fallback
+        // over title and content cannot find it
+        conn.execute(
+            "INSERT INTO items (id, type, title, content, content_plaintext, sort_order)
+             VALUES ('n1', 'note', 'Recipe', '<p>see attachment</p>', 'zucchini bread', 'a')",
+            [],
+        )
+        .unwrap();
+
+        let notes = search_notes(&conn, "zucchini", SearchOptions::default()).unwrap();
+        assert_eq!(notes.iter().map(|(n, _)| n.id.as_str()).collect::<Vec<_>>(), ["n1"]);
+        // Only FTS matches carry a score
+        assert!(notes[0].1.is_some());
+    }
+
+    #[test]
+    fn rebuild_search_index_recomputes_stale_plain_text() {
+        let conn = crate::test_util::memory_db();
+        conn.execute(
+            "INSERT INTO items (id, type, title, content, content_plaintext, sort_order, updated_at)
+             VALUES ('n1', 'note', 'Recipe', '<p>zucchini <b>bread</b></p>', 'old text', 'a', '2020-01-01 00:00:00')",
+            [],
+        )
+        .unwrap();
+
+        assert_eq!(rebuild_search_index(&conn).unwrap(), 1);
+        let (plaintext, updated_at): (String, String) = conn
+            .query_row(
+                "SELECT content_plaintext, updated_at FROM items WHERE id = 'n1'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(plaintext, "zucchini bread");
+        assert_eq!(updated_at, "2020-01-01 00:00:00");
+
+        let notes = search_notes(&conn, "zucchini", SearchOptions::default()).unwrap();
+        assert_eq!(notes.iter().map(|(n, _)| n.id.as_str()).collect::<Vec<_>>(), ["n1"]);
+        assert!(search_notes(&conn, "old", SearchOptions::default()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn fuzzy_title_lookup_needs_a_few_characters() {
+        let conn = crate::test_util::memory_db();
+        crate::test_util::insert(&conn, "Meeting notes", "note", None);
+
+        let err = resolve_note_by_title(&conn, "mt", None, true).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidInput);
+        let note = resolve_note_by_title(&conn, "meetng", None, true).unwrap();
+        assert_eq!(note.title, "Meeting notes");
+    }
+
+    #[test]
+    fn search_notes_matches_like_wildcards_literally() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(crate::DATABASE_SCHEMA).unwrap();
+        let notes = [
+            ("sale", "50% off"),
+            ("order", "500 units"),
+            ("snake", "snake_case"),
+            ("other", "snakeXcase"),
+        ];
+        for (id, text) in notes {
+            conn.execute(
+                "INSERT INTO items (id, type, title, content, content_plaintext, sort_order)
+                 VALUES (?1, 'note', ?1, ?2, ?2, ?1)",
+                rusqlite::params![id, text],
+            )
+            .unwrap();
+        }
+
+        let ids = |query| {
+            search_notes(&conn, query, SearchOptions::default())
+                .unwrap()
+                .into_iter()
+                .map(|(note, _)| note.id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids("50%"), ["sale"]);
+        assert_eq!(ids("snake_case"), ["snake"]);
+        // Without a wildcard the index still prefix-matches
+        let mut prefix = ids("50");
+        prefix.sort();
+        assert_eq!(prefix, ["order", "sale"]);
+    }
+
+    #[test]
+    fn config_dir_env_holds_the_config_file() {
+        let dir = TempDir::new();
+        let dir_str = dir.0.to_str().unwrap();
+        with_env(&[(irisnotes_paths::CONFIG_DIR_ENV, Some(dir_str))], || {
+            // Neither file exists yet: config.toml is where one would be written
+            assert_eq!(get_config_file_path(), dir.0.join("config.toml"));
+            std::fs::write(dir.0.join("config.json"), "{}").unwrap();
+            assert_eq!(get_config_file_path(), dir.0.join("config.json"));
+            std::fs::write(dir.0.join("config.toml"), "").unwrap();
+            assert_eq!(get_config_file_path(), dir.0.join("config.toml"));
+        });
+    }
+
+    #[cfg(feature = "sqlcipher")]
+    #[test]
+    fn backup_of_an_encrypted_database_keeps_the_key() {
+        let dir = TempDir::new();
+        let conn = Connection::open(dir.0.join("notes.db")).unwrap();
+        conn.pragma_update(None, "key", "k1").unwrap();
+        conn.execute_batch("CREATE TABLE items (title TEXT); INSERT INTO items VALUES ('secret');")
+            .unwrap();
+
+        let backup = with_env(&[(irisnotes_paths::DATABASE_KEY_ENV, Some("k1"))], || {
+            backup_database(&conn, &dir.0.join("backups")).unwrap()
+        });
+
+        let read = |key: Option<&str>| {
+            let copy = Connection::open(&backup).unwrap();
+            if let Some(key) = key {
+                copy.pragma_update(None, "key", key).unwrap();
+            }
+            copy.query_row("SELECT title FROM items", [], |row| row.get::<_, String>(0))
+        };
+        assert_eq!(read(Some("k1")).unwrap(), "secret");
+        assert!(read(None).is_err());
+    }
+}
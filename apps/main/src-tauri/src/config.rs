@@ -0,0 +1,497 @@
+//! Typed schema for `config.toml` (or legacy `config.json`)
+//!
+//! Mirrors `AppConfig` in `apps/main/src/types/index.ts`, plus the `[quick]`
+//! table read by the quick search app. The app checks the config against this
+//! schema whenever it reads or writes it, and `irisnotes config-validate` and
+//! the `validate_config` command report the problems.
+//!
+//! Unknown keys are rejected rather than ignored (`deny_unknown_fields`): a
+//! misspelled key would otherwise be dropped without a word and the setting
+//! would silently keep its default. The cost is that a key added by a newer
+//! version fails validation in an older one, so every key the frontend writes
+//! must be declared here.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct AppConfig {
+    pub theme: Option<String>,
+    pub editor: EditorConfig,
+    pub debug: DebugConfig,
+    pub storage: StorageConfig,
+    pub sync: Option<SyncConfig>,
+    /// Hotkey overrides; their shape is owned by the frontend hotkey mapping
+    pub hotkeys: Option<toml::Table>,
+    pub layout: Option<LayoutConfig>,
+    pub development: DevelopmentConfig,
+    pub production: ProductionConfig,
+    pub quick: Option<QuickConfig>,
+    pub cli: Option<CliConfig>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct EditorConfig {
+    pub line_wrapping: bool,
+    pub toolbar_visible: bool,
+    pub title_bar_visible: bool,
+    pub metadata_bar_visible: bool,
+    pub paste_sanitizer: Option<SanitizerConfig>,
+}
+
+impl Default for EditorConfig {
+    fn default() -> Self {
+        Self {
+            line_wrapping: false,
+            toolbar_visible: true,
+            title_bar_visible: true,
+            metadata_bar_visible: true,
+            paste_sanitizer: None,
+        }
+    }
+}
+
+/// Allowlist adjustments for the HTML sanitizer (see `sanitize.rs`)
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SanitizerConfig {
+    pub keep: Vec<String>,
+    pub strip: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct DebugConfig {
+    pub enable_example_note: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    #[default]
+    Sqlite,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    pub backend: StorageBackend,
+    pub sqlite: Option<SqliteConfig>,
+    /// SQLCipher key; read by irisnotes-paths, which lets IRISNOTES_DB_KEY override it
+    pub encryption_key: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SqliteConfig {
+    pub database_path: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SyncConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub server_url: String,
+    #[serde(default)]
+    pub token: String,
+    pub interval_seconds: Option<u64>,
+    pub database_path: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct LayoutConfig {
+    pub sidebar_width: Option<u32>,
+    pub activity_bar_visible: Option<bool>,
+    pub sidebar_collapsed: Option<bool>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct DevelopmentConfig {
+    pub use_local_config: bool,
+    pub config_path: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct ProductionConfig {
+    pub custom_config_path: Option<String>,
+    pub custom_database_path: Option<String>,
+    pub custom_notes_path: Option<String>,
+}
+
+/// `[quick]` table, read by the quick search app
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QuickConfig {
+    pub start_visible: bool,
+    pub preserve_query: bool,
+    /// Maximum number of search results (30 when unset)
+    pub search_limit: Option<usize>,
+    /// Global shortcut toggling the quick window (Ctrl+Shift+Space when unset)
+    pub shortcut: Option<String>,
+}
+
+/// `[cli]` table, read by the `irisnotes` command-line interface
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CliConfig {
+    /// strftime-style format for displayed timestamps (ISO-8601 when unset)
+    pub date_format: Option<String>,
+}
+
+/// Sync interval bounds, in seconds
+const SYNC_INTERVAL_RANGE: std::ops::RangeInclusive<u64> = 5..=86_400;
+/// Sidebar width bounds, in pixels
+const SIDEBAR_WIDTH_RANGE: std::ops::RangeInclusive<u32> = 120..=1200;
+/// Quick search result limit bounds (kept in sync with the quick app)
+const QUICK_SEARCH_LIMIT_RANGE: std::ops::RangeInclusive<usize> = 1..=500;
+
+impl AppConfig {
+    /// The config a fresh install starts with, matching `DEFAULT_CONFIG` in
+    /// `apps/main/src/hooks/use-config.ts`. Unlike `Default`, which stands in
+    /// for keys missing from a file, this fills in every value the app sets.
+    pub fn defaults() -> Self {
+        Self {
+            theme: Some("default-dark".to_string()),
+            storage: StorageConfig {
+                backend: StorageBackend::Sqlite,
+                sqlite: Some(SqliteConfig {
+                    database_path: "notes.db".to_string(),
+                }),
+                encryption_key: None,
+            },
+            sync: Some(SyncConfig {
+                enabled: false,
+                server_url: "http://127.0.0.1:8787".to_string(),
+                token: String::new(),
+                interval_seconds: Some(30),
+                database_path: None,
+            }),
+            development: DevelopmentConfig {
+                use_local_config: false,
+                config_path: "./dev/".to_string(),
+            },
+            ..Self::default()
+        }
+    }
+
+    /// The config as TOML, as written to `config.toml`
+    pub fn to_toml(&self) -> Result<String, String> {
+        toml::to_string_pretty(self).map_err(|e| format!("Failed to serialize TOML: {}", e))
+    }
+
+    /// Check value ranges that the type system cannot express.
+    /// Returns one message per problem, keyed by the dotted config path.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if let Some(sync) = &self.sync {
+            if let Some(interval) = sync.interval_seconds {
+                if !SYNC_INTERVAL_RANGE.contains(&interval) {
+                    problems.push(format!(
+                        "sync.intervalSeconds: {} is out of range ({}-{})",
+                        interval,
+                        SYNC_INTERVAL_RANGE.start(),
+                        SYNC_INTERVAL_RANGE.end()
+                    ));
+                }
+            }
+            let has_scheme =
+                sync.server_url.starts_with("http://") || sync.server_url.starts_with("https://");
+            if sync.enabled && !has_scheme {
+                problems.push(format!(
+                    "sync.serverUrl: '{}' must start with http:// or https:// when sync is enabled",
+                    sync.server_url
+                ));
+            }
+        }
+
+        if let Some(width) = self.layout.as_ref().and_then(|l| l.sidebar_width) {
+            if !SIDEBAR_WIDTH_RANGE.contains(&width) {
+                problems.push(format!(
+                    "layout.sidebarWidth: {} is out of range ({}-{})",
+                    width,
+                    SIDEBAR_WIDTH_RANGE.start(),
+                    SIDEBAR_WIDTH_RANGE.end()
+                ));
+            }
+        }
+
+        if let Some(limit) = self.quick.as_ref().and_then(|q| q.search_limit) {
+            if !QUICK_SEARCH_LIMIT_RANGE.contains(&limit) {
+                problems.push(format!(
+                    "quick.search_limit: {} is out of range ({}-{})",
+                    limit,
+                    QUICK_SEARCH_LIMIT_RANGE.start(),
+                    QUICK_SEARCH_LIMIT_RANGE.end()
+                ));
+            }
+        }
+
+        if let Some(sanitizer) = &self.editor.paste_sanitizer {
+            let allowlist =
+                crate::sanitize::Allowlist::from_options(&sanitizer.keep, &sanitizer.strip);
+            if let Err(e) = allowlist {
+                problems.push(format!("editor.pasteSanitizer: {}", e));
+            }
+        }
+
+        if let Some(format) = self.cli.as_ref().and_then(|c| c.date_format.as_deref()) {
+            if let Err(e) = crate::timestamp::validate_date_format(format) {
+                problems.push(format!("cli.date_format: {}", e));
+            }
+        }
+
+        problems
+    }
+
+    /// The config as a tree of TOML values, keyed as in the file
+    fn to_value(&self) -> Result<toml::Value, String> {
+        toml::Value::try_from(self).map_err(|e| format!("Failed to serialize config: {}", e))
+    }
+
+    /// The value at a dotted key as written in the file (`editor.lineWrapping`,
+    /// `quick.search_limit`), or `None` when it is not set
+    pub fn get(&self, key: &str) -> Result<Option<toml::Value>, String> {
+        let parts = key_parts(key)?;
+        let root = self.to_value()?;
+        let mut value = &root;
+        for (i, part) in parts.iter().enumerate() {
+            let table = value
+                .as_table()
+                .ok_or_else(|| format!("{} is not a table", parts[..i].join(".")))?;
+            match table.get(*part) {
+                Some(child) => value = child,
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(value.clone()))
+    }
+
+    /// A copy with the dotted `key` set to `raw`, read as a TOML value (`true`,
+    /// `30`, `["b", "i"]`) or else taken as a string. The result goes back
+    /// through the schema, so a mistyped key or value is an error, as is a
+    /// range check that held before and fails now.
+    pub fn with_value(&self, key: &str, raw: &str) -> Result<AppConfig, String> {
+        let parts = key_parts(key)?;
+        let mut candidates = Vec::new();
+        if let Ok(mut table) = format!("value = {}", raw).parse::<toml::Table>() {
+            if table.len() == 1 {
+                candidates.extend(table.remove("value"));
+            }
+        }
+        // Bare words, and digits meant for a string field, fall back to a string
+        candidates.push(toml::Value::String(raw.to_string()));
+
+        let mut first_error = None;
+        for candidate in candidates {
+            let mut root = self.to_value()?;
+            insert_value(&mut root, &parts, candidate)?;
+            match root.try_into::<AppConfig>() {
+                Ok(config) => {
+                    let before = self.validate();
+                    let problems: Vec<String> = config
+                        .validate()
+                        .into_iter()
+                        .filter(|problem| !before.contains(problem))
+                        .collect();
+                    if !problems.is_empty() {
+                        return Err(problems.join("; "));
+                    }
+                    return Ok(config);
+                }
+                Err(e) => {
+                    first_error.get_or_insert_with(|| format!("{}: {}", key, e.message()));
+                }
+            }
+        }
+        Err(first_error.unwrap_or_else(|| format!("{}: invalid value", key)))
+    }
+}
+
+/// A dotted config key split into its parts
+fn key_parts(key: &str) -> Result<Vec<&str>, String> {
+    let parts: Vec<&str> = key.split('.').collect();
+    if parts.iter().any(|part| part.trim().is_empty()) {
+        return Err(format!("Invalid config key '{}'", key));
+    }
+    Ok(parts)
+}
+
+/// Set the value at `parts` in a tree of TOML values, creating missing tables
+fn insert_value(root: &mut toml::Value, parts: &[&str], value: toml::Value) -> Result<(), String> {
+    let (last, parents) = parts.split_last().ok_or("Empty config key")?;
+    let mut table = root.as_table_mut().ok_or("Config is not a table")?;
+    for (i, part) in parents.iter().enumerate() {
+        table = table
+            .entry(part.to_string())
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+            .as_table_mut()
+            .ok_or_else(|| format!("{} is not a table", parts[..=i].join(".")))?;
+    }
+    table.insert(last.to_string(), value);
+    Ok(())
+}
+
+/// 1-based line and column of a byte offset
+fn line_col(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let col = before.rsplit('\n').next().map_or(0, |l| l.chars().count()) + 1;
+    (line, col)
+}
+
+/// Parse a config file, reporting syntax and schema errors as `path:line:col: message`
+pub fn parse_config_file(path: &Path) -> Result<AppConfig, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    parse_config(&content, path)
+}
+
+/// Parse config text as the file at `path` (TOML, or JSON for a `.json` path)
+pub fn parse_config(content: &str, path: &Path) -> Result<AppConfig, String> {
+    let is_json = path.extension().is_some_and(|ext| ext == "json");
+    if is_json {
+        serde_json::from_str(content).map_err(|e| {
+            // serde_json appends the position to its message; report it up front instead
+            let message = e.to_string();
+            let suffix = format!(" at line {} column {}", e.line(), e.column());
+            let message = message.strip_suffix(&suffix).unwrap_or(&message);
+            format!("{}:{}:{}: {}", path.display(), e.line(), e.column(), message)
+        })
+    } else {
+        toml::from_str(content).map_err(|e| match e.span() {
+            Some(span) => {
+                let (line, col) = line_col(content, span.start);
+                format!("{}:{}:{}: {}", path.display(), line, col, e.message())
+            }
+            None => format!("{}: {}", path.display(), e.message()),
+        })
+    }
+}
+
+/// Every problem with config text: the parse error, or else each failed range
+/// check, prefixed with `path`. Empty when the config is valid.
+pub fn config_problems(content: &str, path: &Path) -> Vec<String> {
+    match parse_config(content, path) {
+        Ok(config) => config
+            .validate()
+            .into_iter()
+            .map(|problem| format!("{}: {}", path.display(), problem))
+            .collect(),
+        Err(e) => vec![e],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(toml: &str) -> AppConfig {
+        parse_config(toml, Path::new("config.toml")).unwrap()
+    }
+
+    #[test]
+    fn the_defaults_are_valid() {
+        let defaults = AppConfig::defaults();
+        assert!(defaults.validate().is_empty());
+        let text = defaults.to_toml().unwrap();
+        assert!(config_problems(&text, Path::new("config.toml")).is_empty());
+    }
+
+    #[test]
+    fn out_of_range_values_are_reported_by_dotted_key() {
+        let config = config(
+            "[sync]\nenabled = true\nserverUrl = \"example.com\"\nintervalSeconds = 1\n\
+             [layout]\nsidebarWidth = 5000\n[quick]\nsearch_limit = 0\n",
+        );
+        let problems = config.validate();
+        assert_eq!(problems.len(), 4, "{:?}", problems);
+        for key in ["sync.intervalSeconds", "sync.serverUrl", "layout.sidebarWidth", "quick.search_limit"] {
+            assert!(problems.iter().any(|p| p.starts_with(key)), "{} in {:?}", key, problems);
+        }
+    }
+
+    #[test]
+    fn config_problems_reports_parse_errors_with_a_position() {
+        let path = Path::new("config.toml");
+        let problems = config_problems("[editor]\nlineWrapping = \"yes\"\n", path);
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("config.toml:2:"), "{}", problems[0]);
+
+        let problems = config_problems("[editor]\nlineWraping = true\n", path);
+        assert!(problems[0].contains("lineWraping"), "{}", problems[0]);
+
+        let problems = config_problems("[layout]\nsidebarWidth = 10\n", path);
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("config.toml: layout.sidebarWidth"), "{}", problems[0]);
+
+        let problems = config_problems("{\"theme\": 1}", Path::new("config.json"));
+        assert!(problems[0].starts_with("config.json:1:"), "{}", problems[0]);
+    }
+
+    #[test]
+    fn get_reads_dotted_keys_as_written_in_the_file() {
+        let config = config("theme = \"light\"\n[quick]\nsearch_limit = 40\n");
+        assert_eq!(config.get("theme").unwrap(), Some(toml::Value::String("light".into())));
+        assert_eq!(config.get("quick.search_limit").unwrap(), Some(toml::Value::Integer(40)));
+        assert_eq!(config.get("editor.lineWrapping").unwrap(), Some(toml::Value::Boolean(false)));
+        assert_eq!(config.get("layout.sidebarWidth").unwrap(), None);
+        assert!(config.get("theme.name").unwrap_err().contains("theme is not a table"));
+    }
+
+    #[test]
+    fn key_parts_rejects_empty_parts() {
+        assert_eq!(key_parts("editor.lineWrapping").unwrap(), ["editor", "lineWrapping"]);
+        for key in ["", "editor.", ".theme", "editor..lineWrapping", "editor. "] {
+            assert!(key_parts(key).is_err(), "{:?}", key);
+        }
+    }
+
+    #[test]
+    fn with_value_parses_toml_and_falls_back_to_a_string() {
+        let config = AppConfig::defaults();
+        let updated = config.with_value("editor.lineWrapping", "true").unwrap();
+        assert!(updated.editor.line_wrapping);
+        let updated = config.with_value("quick.search_limit", "50").unwrap();
+        assert_eq!(updated.quick.unwrap().search_limit, Some(50));
+        let updated = config.with_value("editor.pasteSanitizer.keep", "[\"b\", \"i\"]").unwrap();
+        assert_eq!(updated.editor.paste_sanitizer.unwrap().keep, ["b", "i"]);
+
+        // Not TOML, or TOML of the wrong type for a string field
+        let updated = config.with_value("theme", "light").unwrap();
+        assert_eq!(updated.theme.as_deref(), Some("light"));
+        let updated = config.with_value("sync.token", "12345").unwrap();
+        assert_eq!(updated.sync.unwrap().token, "12345");
+    }
+
+    #[test]
+    fn with_value_rejects_unknown_keys_wrong_types_and_bad_ranges() {
+        let config = AppConfig::defaults();
+        let err = config.with_value("editor.lineWraping", "true").unwrap_err();
+        assert!(err.contains("lineWraping"), "{}", err);
+        let err = config.with_value("editor.lineWrapping", "yes").unwrap_err();
+        assert!(err.starts_with("editor.lineWrapping"), "{}", err);
+        let err = config.with_value("quick.search_limit", "0").unwrap_err();
+        assert!(err.starts_with("quick.search_limit: 0 is out of range"), "{}", err);
+        assert!(config.with_value("theme.name", "x").is_err());
+        assert!(config.with_value("editor..lineWrapping", "true").is_err());
+    }
+
+    #[test]
+    fn with_value_does_not_blame_a_problem_that_was_already_there() {
+        let config = config("[layout]\nsidebarWidth = 5\n");
+        assert_eq!(config.validate().len(), 1);
+
+        let updated = config.with_value("editor.lineWrapping", "true").unwrap();
+        assert!(updated.editor.line_wrapping);
+        let err = config.with_value("quick.search_limit", "0").unwrap_err();
+        assert!(!err.contains("sidebarWidth"), "{}", err);
+    }
+}
@@ -0,0 +1,248 @@
+//! Database health checks for `irisnotes doctor`
+//!
+//! Four checks, each producing `Finding`s:
+//! - `integrity`: SQLite's own `PRAGMA integrity_check`
+//! - `orphan`: live items whose `parent_id` names a missing or trashed row
+//! - `fts`: notes with no row in `items_fts`, so search never finds them
+//! - `cycle`: items that are their own ancestor, which hide from the tree
+//!
+//! With `fix`, orphaned notes move to the root and missing search rows are
+//! rebuilt, all in one transaction. Orphaned sections need a book and cycles
+//! need a decision about where each item belongs, so those are only reported.
+
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Data is damaged or unreachable
+    Error,
+    /// Data is intact but misplaced or unsearchable
+    Warning,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Finding {
+    pub severity: Severity,
+    /// The check that reported it: integrity, orphan, fts or cycle
+    pub check: &'static str,
+    pub message: String,
+    /// Repaired by `fix`
+    pub fixed: bool,
+}
+
+impl Finding {
+    fn new(severity: Severity, check: &'static str, message: String) -> Self {
+        Self {
+            severity,
+            check,
+            message,
+            fixed: false,
+        }
+    }
+}
+
+/// `PRAGMA integrity_check` rows other than the lone `ok` of a healthy database
+fn integrity_findings(conn: &Connection) -> Result<Vec<Finding>, String> {
+    let mut stmt = conn
+        .prepare("PRAGMA integrity_check")
+        .map_err(|e| format!("Failed to run integrity check: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| format!("Failed to run integrity check: {}", e))?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| format!("Failed to run integrity check: {}", e))?;
+
+    Ok(rows
+        .into_iter()
+        .filter(|row| row != "ok")
+        .map(|row| Finding::new(Severity::Error, "integrity", row))
+        .collect())
+}
+
+/// Live items under a parent that is missing or in the trash. Notes are moved
+/// to the root when `fix` is set.
+fn orphan_findings(conn: &Connection, fix: bool) -> Result<Vec<Finding>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT c.id, c.type, c.title, c.parent_id, p.title
+             FROM items c LEFT JOIN items p ON p.id = c.parent_id
+             WHERE c.deleted_at IS NULL AND c.parent_id IS NOT NULL
+               AND (p.id IS NULL OR p.deleted_at IS NOT NULL)
+             ORDER BY c.title",
+        )
+        .map_err(|e| format!("Failed to find orphaned items: {}", e))?;
+    let orphans = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, Option<String>>(4)?,
+            ))
+        })
+        .map_err(|e| format!("Failed to find orphaned items: {}", e))?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| format!("Failed to find orphaned items: {}", e))?;
+
+    let mut findings = Vec::new();
+    for (id, item_type, title, parent_id, parent_title) in orphans {
+        let parent = match parent_title {
+            Some(parent_title) => format!("'{}', which is in the trash", parent_title),
+            None => format!("missing item {}", parent_id),
+        };
+        let mut finding = Finding::new(
+            Severity::Warning,
+            "orphan",
+            format!("{} '{}' ({}) is under {}", item_type, title, id, parent),
+        );
+
+        if item_type == "note" {
+            if fix {
+                let sort_order = crate::sort_order::sort_order_for(
+                    conn,
+                    None,
+                    crate::sort_order::Placement::Append,
+                    Some(&id),
+                )?;
+                conn.execute(
+                    "UPDATE items SET parent_id = NULL, sort_order = ?1 WHERE id = ?2",
+                    params![sort_order, id],
+                )
+                .map_err(|e| format!("Failed to move '{}' to the root: {}", title, e))?;
+                finding.fixed = true;
+            }
+        } else {
+            finding
+                .message
+                .push_str("; move it into a book with `irisnotes move`");
+        }
+        findings.push(finding);
+    }
+    Ok(findings)
+}
+
+/// Notes without a search index row, rebuilt from the stored plain text when `fix` is set
+fn fts_findings(conn: &Connection, fix: bool) -> Result<Vec<Finding>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, title FROM items
+             WHERE type = 'note' AND id NOT IN (SELECT item_id FROM items_fts)
+             ORDER BY title",
+        )
+        .map_err(|e| format!("Failed to check the search index: {}", e))?;
+    let missing = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })
+        .map_err(|e| format!("Failed to check the search index: {}", e))?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| format!("Failed to check the search index: {}", e))?;
+
+    let mut findings = Vec::new();
+    for (id, title) in missing {
+        let mut finding = Finding::new(
+            Severity::Warning,
+            "fts",
+            format!("note '{}' ({}) is missing from the search index", title, id),
+        );
+        if fix {
+            conn.execute(
+                "INSERT INTO items_fts(item_id, title, content_plaintext)
+                 SELECT id, title, COALESCE(content_plaintext, '') FROM items WHERE id = ?1",
+                [&id],
+            )
+            .map_err(|e| format!("Failed to reindex '{}': {}", title, e))?;
+            finding.fixed = true;
+        }
+        findings.push(finding);
+    }
+    Ok(findings)
+}
+
+/// Parent chains that loop back on themselves, one finding per loop
+fn cycle_findings(conn: &Connection) -> Result<Vec<Finding>, String> {
+    let mut stmt = conn
+        .prepare("SELECT id, title, parent_id FROM items ORDER BY id")
+        .map_err(|e| format!("Failed to read hierarchy: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?,
+            ))
+        })
+        .map_err(|e| format!("Failed to read hierarchy: {}", e))?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| format!("Failed to read hierarchy: {}", e))?;
+
+    let items: HashMap<&str, (&str, Option<&str>)> = rows
+        .iter()
+        .map(|(id, title, parent_id)| (id.as_str(), (title.as_str(), parent_id.as_deref())))
+        .collect();
+
+    // Walk up from every item; reaching an item already on the current walk
+    // closes a loop, reaching one finished by an earlier walk does not
+    #[derive(PartialEq)]
+    enum Visit {
+        OnPath,
+        Done,
+    }
+    let mut visits: HashMap<&str, Visit> = HashMap::new();
+    let mut findings = Vec::new();
+    for (start, _, _) in &rows {
+        let mut path: Vec<&str> = Vec::new();
+        let mut current = Some(start.as_str());
+        while let Some(id) = current {
+            match visits.get(id) {
+                Some(Visit::OnPath) => {
+                    let begin = path.iter().position(|&p| p == id).unwrap_or(0);
+                    let titles: Vec<String> = path[begin..]
+                        .iter()
+                        .chain(std::iter::once(&id))
+                        .map(|id| format!("'{}'", items[id].0))
+                        .collect();
+                    findings.push(Finding::new(
+                        Severity::Error,
+                        "cycle",
+                        format!("parent chain loops: {}", titles.join(" → ")),
+                    ));
+                    break;
+                }
+                Some(Visit::Done) => break,
+                None => {}
+            }
+            let Some(&(_, parent_id)) = items.get(id) else {
+                break;
+            };
+            visits.insert(id, Visit::OnPath);
+            path.push(id);
+            current = parent_id;
+        }
+        for id in path {
+            visits.insert(id, Visit::Done);
+        }
+    }
+    Ok(findings)
+}
+
+/// Run every check, applying fixes when `fix` is set. Fixes share one
+/// transaction, so a failure leaves the database untouched.
+pub fn run(conn: &Connection, fix: bool) -> Result<Vec<Finding>, String> {
+    let mut findings = integrity_findings(conn)?;
+
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+    findings.extend(orphan_findings(&tx, fix)?);
+    findings.extend(fts_findings(&tx, fix)?);
+    findings.extend(cycle_findings(&tx)?);
+    tx.commit()
+        .map_err(|e| format!("Failed to commit fixes: {}", e))?;
+
+    Ok(findings)
+}
@@ -0,0 +1,237 @@
+//! SQLCipher encryption of the notes database
+//!
+//! When a key is configured (`IRISNOTES_DB_KEY`, else `[storage]
+//! encryption_key`; see `irisnotes_paths::resolve_database_key`), every
+//! connection must issue `PRAGMA key` before it touches the file.
+//! `irisnotes encrypt` and `irisnotes decrypt` convert an existing database
+//! with `sqlcipher_export`. All of this needs a build with the `sqlcipher`
+//! feature, which swaps the bundled SQLite for SQLCipher; without it a
+//! configured key is an error rather than silently ignored.
+//!
+//! The frontend's tauri_plugin_sql pool has no way to set the key, so the
+//! GUI's own queries cannot read an encrypted database yet; the CLI, the quick
+//! app and the backend commands can. `convert` therefore refuses to run while
+//! any app, the GUI included, has the database open.
+
+use rusqlite::{Connection, DatabaseName, ErrorCode};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Fail unless this build links SQLCipher
+fn require_sqlcipher() -> Result<(), String> {
+    if cfg!(feature = "sqlcipher") {
+        Ok(())
+    } else {
+        Err("Database encryption needs a build with SQLCipher support \
+             (`--features sqlcipher`)"
+            .to_string())
+    }
+}
+
+/// Read the schema, which is where a wrong key (or a plaintext file opened
+/// with one, or an encrypted file opened without) first shows up
+fn check_readable(conn: &Connection, keyed: bool) -> Result<(), String> {
+    match conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| row.get::<_, i64>(0)) {
+        Ok(_) => Ok(()),
+        Err(e) if e.sqlite_error_code() == Some(ErrorCode::NotADatabase) => Err(if keyed {
+            "The database could not be decrypted: the encryption key is wrong, \
+             or the file is not encrypted (see `irisnotes encrypt`)"
+                .to_string()
+        } else {
+            "The database is encrypted, or not a database at all; \
+             set IRISNOTES_DB_KEY or [storage] encryption_key"
+                .to_string()
+        }),
+        Err(e) => Err(format!("Failed to read database: {}", e)),
+    }
+}
+
+/// Set `conn`'s key without reading anything yet
+fn set_key(conn: &Connection, key: &str) -> Result<(), String> {
+    require_sqlcipher()?;
+    conn.pragma_update(None, "key", key)
+        .map_err(|e| format!("Failed to set encryption key: {}", e))
+}
+
+/// Unlock `conn` with `key`; must run before any other statement
+fn apply_key(conn: &Connection, key: &str) -> Result<(), String> {
+    set_key(conn, key)?;
+    check_readable(conn, true)
+}
+
+/// Take an exclusive lock on the database and keep it until `conn` closes,
+/// failing at once if any other connection has the file open. Must run before
+/// `conn` first reads: from then on a WAL connection only takes the write lock,
+/// which idle readers (such as the GUI's pool) do not hold.
+fn lock_exclusively(conn: &Connection) -> Result<(), String> {
+    conn.busy_timeout(Duration::ZERO)
+        .and_then(|()| conn.pragma_update(None, "locking_mode", "EXCLUSIVE"))
+        .map_err(|e| format!("Failed to lock database: {}", e))?;
+    match conn.execute_batch("BEGIN EXCLUSIVE; COMMIT") {
+        Err(e) if e.sqlite_error_code() == Some(ErrorCode::DatabaseBusy) => {
+            Err("The database is in use; close IrisNotes and the quick app first".to_string())
+        }
+        // Anything else (a wrong key, say) is check_readable's to report
+        _ => Ok(()),
+    }
+}
+
+/// Unlock a freshly opened connection with the configured key, if any.
+/// Without one, an encrypted file is reported as such rather than as "file is
+/// not a database".
+pub fn unlock(conn: &Connection) -> Result<(), String> {
+    match irisnotes_paths::resolve_database_key()? {
+        Some(key) => apply_key(conn, &key),
+        None => check_readable(conn, false),
+    }
+}
+
+/// `path` with `suffix` appended to its file name, e.g. `notes.db-wal`
+fn sibling_file(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Copy everything `conn` holds into a new database at `temp_path`, keyed
+/// with `to_key` (plain SQLite when `None`)
+fn export_to(
+    conn: &Connection,
+    temp_path: &Path,
+    to_key: Option<&str>,
+    version: i64,
+) -> rusqlite::Result<()> {
+    // An empty key attaches a plaintext database
+    conn.execute(
+        "ATTACH DATABASE ?1 AS converted KEY ?2",
+        [temp_path.to_string_lossy().as_ref(), to_key.unwrap_or("")],
+    )?;
+    // One transaction, so the copy is a snapshot even while another app writes
+    conn.execute_batch("BEGIN")?;
+    conn.query_row("SELECT sqlcipher_export('converted')", [], |_| Ok(()))?;
+    // sqlcipher_export copies the schema and rows, not the header fields
+    conn.pragma_update(Some(DatabaseName::Attached("converted")), "user_version", version)?;
+    conn.execute_batch("COMMIT")?;
+    conn.execute("DETACH DATABASE converted", [])?;
+    Ok(())
+}
+
+/// Write a copy of the encrypted database behind `conn` to a new file at
+/// `path`, encrypted with the same `key`. SQLite's online backup API cannot
+/// copy between SQLCipher databases, so this goes through `sqlcipher_export`.
+pub fn export_copy(conn: &Connection, path: &Path, key: &str) -> Result<(), String> {
+    require_sqlcipher()?;
+    let version: i64 = conn
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(|e| format!("Failed to read schema version: {}", e))?;
+    export_to(conn, path, Some(key), version).map_err(|e| format!("Failed to copy database: {}", e))
+}
+
+/// Rewrite the database at `path` from `from_key` to `to_key` (`None` being
+/// plain SQLite). The copy is made with `sqlcipher_export` into a temporary
+/// file that then replaces the original, so a failure leaves it untouched.
+/// Every other connection must be closed, and none can open the file until
+/// this returns: the WAL is checkpointed and removed along with the original.
+pub fn convert(path: &Path, from_key: Option<&str>, to_key: Option<&str>) -> Result<(), String> {
+    require_sqlcipher()?;
+    if !path.exists() {
+        return Err(format!("{} does not exist", path.display()));
+    }
+
+    let conn = Connection::open(path).map_err(|e| format!("Failed to open database: {}", e))?;
+    if let Some(key) = from_key {
+        set_key(&conn, key)?;
+    }
+    lock_exclusively(&conn)?;
+    match from_key {
+        Some(_) => check_readable(&conn, true)?,
+        None => check_readable(&conn, false)
+            .map_err(|_| format!("{} is already encrypted, or not a database", path.display()))?,
+    }
+
+    // Fold the WAL into the file
+    conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| row.get::<_, i64>(0))
+        .map_err(|e| format!("Failed to checkpoint database: {}", e))?;
+    let version: i64 = conn
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(|e| format!("Failed to read schema version: {}", e))?;
+
+    let temp_path = sibling_file(path, ".converting");
+    if temp_path.exists() {
+        std::fs::remove_file(&temp_path)
+            .map_err(|e| format!("Failed to remove {}: {}", temp_path.display(), e))?;
+    }
+    let result = export_to(&conn, &temp_path, to_key, version);
+    drop(conn);
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(format!("Failed to convert database: {}", e));
+    }
+
+    std::fs::rename(&temp_path, path)
+        .map_err(|e| format!("Failed to replace {}: {}", path.display(), e))?;
+    // Left behind, the old database's WAL would be replayed into the new file
+    for suffix in ["-wal", "-shm"] {
+        let stale = sibling_file(path, suffix);
+        match std::fs::remove_file(&stale) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("Failed to remove {}: {}", stale.display(), e)),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "sqlcipher"))]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    /// A plaintext WAL database at `path` holding one note
+    fn create_plain(path: &Path) {
+        let conn = Connection::open(path).unwrap();
+        conn.query_row("PRAGMA journal_mode = WAL", [], |row| row.get::<_, String>(0)).unwrap();
+        conn.execute_batch("CREATE TABLE items (title TEXT); INSERT INTO items VALUES ('secret');")
+            .unwrap();
+    }
+
+    fn read_title(conn: &Connection) -> rusqlite::Result<String> {
+        conn.query_row("SELECT title FROM items", [], |row| row.get(0))
+    }
+
+    #[test]
+    fn convert_encrypts_and_decrypts() {
+        let dir = TempDir::new();
+        let path = dir.0.join("notes.db");
+        create_plain(&path);
+
+        convert(&path, None, Some("k1")).unwrap();
+        let conn = Connection::open(&path).unwrap();
+        assert!(read_title(&conn).is_err());
+        drop(conn);
+        let conn = Connection::open(&path).unwrap();
+        apply_key(&conn, "k1").unwrap();
+        assert_eq!(read_title(&conn).unwrap(), "secret");
+        drop(conn);
+
+        convert(&path, Some("k1"), None).unwrap();
+        assert_eq!(read_title(&Connection::open(&path).unwrap()).unwrap(), "secret");
+    }
+
+    #[test]
+    fn convert_refuses_while_another_connection_is_open() {
+        let dir = TempDir::new();
+        let path = dir.0.join("notes.db");
+        create_plain(&path);
+        // An idle connection, like the GUI's pool between queries
+        let other = Connection::open(&path).unwrap();
+        read_title(&other).unwrap();
+
+        let error = convert(&path, None, Some("k1")).unwrap_err();
+        assert!(error.contains("in use"), "{}", error);
+        assert_eq!(read_title(&other).unwrap(), "secret");
+
+        drop(other);
+        convert(&path, None, Some("k1")).unwrap();
+    }
+}
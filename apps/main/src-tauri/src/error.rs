@@ -0,0 +1,123 @@
+//! Errors returned by the Tauri commands
+//!
+//! A command error reaches the frontend as `{ "kind": "not_found", "message": "..." }`,
+//! so callers can branch on `kind` instead of matching message text (see
+//! `AppError` in `apps/main/src/utils/app-error.ts`). Helpers that still
+//! report plain strings surface with kind `other`.
+
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
+#[derive(Debug)]
+pub enum AppError {
+    /// Reading or writing a file, or running an external program, failed
+    Io(String),
+    /// Opening, reading or writing the database failed
+    Db(String),
+    /// The requested item, file or external tool does not exist
+    NotFound(String),
+    /// The config is unreadable or fails validation
+    Config(String),
+    /// JSON, TOML or text the frontend passed in could not be parsed
+    Parse(String),
+    /// An argument was rejected before anything was touched
+    InvalidInput(String),
+    /// Talking to a remote server failed
+    Network(String),
+    /// Anything else
+    Other(String),
+}
+
+impl AppError {
+    /// Machine-readable category, serialized as `kind`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Io(_) => "io",
+            Self::Db(_) => "db",
+            Self::NotFound(_) => "not_found",
+            Self::Config(_) => "config",
+            Self::Parse(_) => "parse",
+            Self::InvalidInput(_) => "invalid_input",
+            Self::Network(_) => "network",
+            Self::Other(_) => "other",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            Self::Io(message)
+            | Self::Db(message)
+            | Self::NotFound(message)
+            | Self::Config(message)
+            | Self::Parse(message)
+            | Self::InvalidInput(message)
+            | Self::Network(message)
+            | Self::Other(message) => message,
+        }
+    }
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AppError", 2)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", self.message())?;
+        state.end()
+    }
+}
+
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        Self::Other(message)
+    }
+}
+
+impl From<&str> for AppError {
+    fn from(message: &str) -> Self {
+        Self::Other(message.to_string())
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::NotFound => Self::NotFound(e.to_string()),
+            _ => Self::Io(e.to_string()),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(e: rusqlite::Error) -> Self {
+        match e {
+            rusqlite::Error::QueryReturnedNoRows => Self::NotFound("No matching item".to_string()),
+            e => Self::Db(format!("Database error: {}", e)),
+        }
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Parse(format!("Invalid JSON: {}", e))
+    }
+}
+
+impl From<toml::de::Error> for AppError {
+    fn from(e: toml::de::Error) -> Self {
+        Self::Parse(format!("Invalid TOML: {}", e))
+    }
+}
+
+impl From<toml::ser::Error> for AppError {
+    fn from(e: toml::ser::Error) -> Self {
+        Self::Other(format!("Failed to serialize TOML: {}", e))
+    }
+}
@@ -0,0 +1,491 @@
+//! Exporting notes as Markdown, JSON or HTML files
+//!
+//! Shared by `irisnotes export` and the main app's `export_note` and
+//! `export_tree` commands. A tree export mirrors books and sections as
+//! folders holding their children, with root-level notes in `_root/`, the
+//! same layout as the GUI's export-import.ts so its importer reads the files
+//! back. Markdown goes through `markdown::html_to_markdown`; HTML notes
+//! become standalone documents headed by their title.
+//!
+//! A tree export runs in two phases. Everything that touches the database or
+//! decides a name happens first, on the calling thread: the rows are read in
+//! one snapshot, folders are created and every note is given its file name.
+//! Converting and writing the notes then runs on a pool of worker threads,
+//! which share nothing but the loaded rows and a queue index.
+
+use crate::error::AppError;
+use crate::notes::get_item_path;
+use clap::ValueEnum;
+use rusqlite::{Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// File format for `export`
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// Markdown with YAML frontmatter (the GUI's export layout)
+    Markdown,
+    /// One JSON object per note
+    Json,
+    /// Self-contained HTML documents
+    Html,
+}
+
+impl ExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "md",
+            ExportFormat::Json => "json",
+            ExportFormat::Html => "html",
+        }
+    }
+}
+
+/// A live item as loaded for `export`
+#[derive(Debug)]
+struct ExportItem {
+    id: String,
+    item_type: String,
+    title: String,
+    content: String,
+    parent_id: Option<String>,
+    created_at: String,
+    updated_at: String,
+}
+
+impl ExportItem {
+    const COLUMNS: &'static str = "id, type, title, content, parent_id, created_at, updated_at";
+
+    fn from_row(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        Ok(ExportItem {
+            id: row.get(0)?,
+            item_type: row.get(1)?,
+            title: row.get(2)?,
+            content: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+            parent_id: row.get(4)?,
+            created_at: row.get(5)?,
+            updated_at: row.get(6)?,
+        })
+    }
+}
+
+/// JSON form of one exported note
+#[derive(Serialize)]
+struct ExportNoteJson<'a> {
+    id: &'a str,
+    title: &'a str,
+    parent_id: Option<&'a str>,
+    path: String,
+    created_at: &'a str,
+    updated_at: &'a str,
+    content: &'a str,
+}
+
+/// All live items in sibling order, read up front so the export sees one snapshot
+fn get_export_items(conn: &Connection) -> Result<Vec<ExportItem>, AppError> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM items
+             WHERE deleted_at IS NULL
+             ORDER BY sort_order, id",
+            ExportItem::COLUMNS
+        ))
+        .map_err(|e| AppError::Db(format!("Failed to read notes: {}", e)))?;
+
+    let items = stmt
+        .query_map([], ExportItem::from_row)
+        .map_err(|e| AppError::Db(format!("Failed to read notes: {}", e)))?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| AppError::Db(format!("Failed to read notes: {}", e)))?;
+
+    Ok(items)
+}
+
+/// Same rules as `sanitizeFilename` in the GUI's export-import.ts
+fn sanitize_filename(name: &str) -> String {
+    let replaced: String = name
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let normalized = replaced.split_whitespace().collect::<Vec<_>>().join(" ");
+    let truncated: String = normalized.chars().take(100).collect();
+    match truncated.trim() {
+        "" | "." | ".." => "Untitled".to_string(),
+        name => name.to_string(),
+    }
+}
+
+/// `name`, or `name (2)`, `name (3)`, ... if already taken in this directory
+fn unique_name(used: &mut HashSet<String>, name: &str, extension: &str) -> String {
+    let mut candidate = name.to_string();
+    let mut n = 2;
+    while !used.insert(format!("{}{}", candidate, extension).to_lowercase()) {
+        candidate = format!("{} ({})", name, n);
+        n += 1;
+    }
+    format!("{}{}", candidate, extension)
+}
+
+const STANDALONE_CSS: &str = "body { max-width: 48rem; margin: 2rem auto; padding: 0 1rem; \
+font-family: system-ui, sans-serif; line-height: 1.6; color: #222; }
+pre, code { font-family: ui-monospace, monospace; background: #f4f4f4; }
+pre { padding: 0.75rem; overflow-x: auto; }
+blockquote { border-left: 3px solid #ccc; margin-left: 0; padding-left: 1rem; color: #555; }
+table { border-collapse: collapse; }
+td, th { border: 1px solid #ccc; padding: 0.25rem 0.5rem; }
+img { max-width: 100%; }";
+
+/// Wrap a stored HTML body in a minimal, self-contained HTML document
+pub fn html_document(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n{}\n</style>\n</head>\n<body>\n{}\n</body>\n</html>\n",
+        crate::markdown::escape_html(title),
+        STANDALONE_CSS,
+        body
+    )
+}
+
+/// The `path` field of a JSON export (`get_item_path`); unused by other formats
+fn json_path(conn: &Connection, item: &ExportItem, format: ExportFormat) -> String {
+    if format != ExportFormat::Json {
+        return String::new();
+    }
+    get_item_path(conn, &item.id).unwrap_or_else(|_| item.title.clone())
+}
+
+/// Render one note in the export format; `path` is its `json_path`
+fn render_note(item: &ExportItem, format: ExportFormat, path: &str) -> Result<String, AppError> {
+    match format {
+        ExportFormat::Markdown => {
+            // Frontmatter mirrors the GUI exporter so its importer reads these files back.
+            // The title is a double-quoted string (JSON escapes are valid YAML) so
+            // colons, quotes, `#` and line breaks in it survive
+            let title = serde_json::to_string(&item.title)
+                .map_err(|e| AppError::Other(format!("Failed to serialize title: {}", e)))?;
+            Ok(format!(
+                "---\nid: {}\ntitle: {}\ncreated: {}\nupdated: {}\ncontent_type: markdown\n---\n\n{}\n",
+                item.id,
+                title,
+                item.created_at,
+                item.updated_at,
+                crate::markdown::html_to_markdown(&item.content)
+            ))
+        }
+        ExportFormat::Html => {
+            let heading = format!("<h1>{}</h1>", crate::markdown::escape_html(&item.title));
+            Ok(html_document(&item.title, &format!("{}\n{}", heading, item.content)))
+        }
+        ExportFormat::Json => {
+            let note = ExportNoteJson {
+                id: &item.id,
+                title: &item.title,
+                parent_id: item.parent_id.as_deref(),
+                path: path.to_string(),
+                created_at: &item.created_at,
+                updated_at: &item.updated_at,
+                content: &item.content,
+            };
+            let json = serde_json::to_string_pretty(&note)
+                .map_err(|e| AppError::Other(format!("Failed to serialize JSON: {}", e)))?;
+            Ok(format!("{}\n", json))
+        }
+    }
+}
+
+/// The live note `id` rendered in `format`
+pub fn export_note(conn: &Connection, id: &str, format: ExportFormat) -> Result<String, AppError> {
+    let item = conn
+        .query_row(
+            &format!(
+                "SELECT {} FROM items WHERE id = ?1 AND type = 'note' AND deleted_at IS NULL",
+                ExportItem::COLUMNS
+            ),
+            [id],
+            ExportItem::from_row,
+        )
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("Note '{}' not found", id)))?;
+    render_note(&item, format, &json_path(conn, &item, format))
+}
+
+/// What a tree export wrote
+#[derive(Debug, Default)]
+pub struct ExportSummary {
+    pub notes: usize,
+    pub folders: usize,
+    /// Every file and folder created, parents before their contents
+    pub paths: Vec<PathBuf>,
+}
+
+/// A note file to write, decided before any worker starts
+struct NoteJob {
+    index: usize,
+    path: PathBuf,
+    json_path: String,
+}
+
+/// Lays out an export: creates the folders and names every note file
+struct Exporter<'a> {
+    conn: &'a Connection,
+    items: &'a [ExportItem],
+    /// Item indices grouped by parent, each group in sibling order
+    children: HashMap<Option<&'a str>, Vec<usize>>,
+    format: ExportFormat,
+    jobs: Vec<NoteJob>,
+    summary: ExportSummary,
+}
+
+impl<'a> Exporter<'a> {
+    fn new(conn: &'a Connection, items: &'a [ExportItem], format: ExportFormat) -> Self {
+        let mut children: HashMap<Option<&str>, Vec<usize>> = HashMap::new();
+        for (index, item) in items.iter().enumerate() {
+            children.entry(item.parent_id.as_deref()).or_default().push(index);
+        }
+        Self {
+            conn,
+            items,
+            children,
+            format,
+            jobs: Vec::new(),
+            summary: ExportSummary::default(),
+        }
+    }
+
+    /// Lay out one item in `dir`: a note as a file to write, a book or section
+    /// as a folder holding its children. `used` tracks the names already taken
+    /// in `dir`.
+    fn plan_item(
+        &mut self,
+        index: usize,
+        dir: &Path,
+        used: &mut HashSet<String>,
+    ) -> Result<(), AppError> {
+        let items = self.items;
+        let item = &items[index];
+        let name = sanitize_filename(&item.title);
+        std::fs::create_dir_all(dir)
+            .map_err(|e| AppError::Io(format!("Failed to create {}: {}", dir.display(), e)))?;
+
+        if item.item_type == "note" {
+            let extension = format!(".{}", self.format.extension());
+            let path = dir.join(unique_name(used, &name, &extension));
+            self.summary.paths.push(path.clone());
+            self.jobs.push(NoteJob {
+                index,
+                path,
+                json_path: json_path(self.conn, item, self.format),
+            });
+            return Ok(());
+        }
+
+        let folder = dir.join(unique_name(used, &name, ""));
+        std::fs::create_dir_all(&folder)
+            .map_err(|e| AppError::Io(format!("Failed to create {}: {}", folder.display(), e)))?;
+        self.summary.folders += 1;
+        self.summary.paths.push(folder.clone());
+
+        let children = self
+            .children
+            .get(&Some(item.id.as_str()))
+            .cloned()
+            .unwrap_or_default();
+        let mut folder_used = HashSet::new();
+        for child in children {
+            self.plan_item(child, &folder, &mut folder_used)?;
+        }
+        Ok(())
+    }
+
+    /// Lay out every root item; root-level notes go in `_root/` like the GUI exporter
+    fn plan_all(&mut self, out: &Path) -> Result<(), AppError> {
+        let roots = self.children.get(&None).cloned().unwrap_or_default();
+        let root_notes = out.join("_root");
+        let mut used = HashSet::new();
+        let mut root_used = HashSet::new();
+        for index in roots {
+            if self.items[index].item_type == "note" {
+                if root_used.is_empty() {
+                    self.summary.paths.push(root_notes.clone());
+                }
+                self.plan_item(index, &root_notes, &mut root_used)?;
+            } else {
+                self.plan_item(index, out, &mut used)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Render and write one planned note
+fn write_note(items: &[ExportItem], job: &NoteJob, format: ExportFormat) -> Result<(), AppError> {
+    let content = render_note(&items[job.index], format, &job.json_path)?;
+    std::fs::write(&job.path, content)
+        .map_err(|e| AppError::Io(format!("Failed to write {}: {}", job.path.display(), e)))
+}
+
+/// Write every planned note on `workers` threads pulling from a shared queue.
+/// The first failure (in tree order) is returned once the workers stop; no new
+/// note is started after any failure.
+fn write_notes(
+    items: &[ExportItem],
+    jobs: &[NoteJob],
+    format: ExportFormat,
+    workers: usize,
+) -> Result<(), AppError> {
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let worker = || -> Option<(usize, AppError)> {
+        while !failed.load(Ordering::Relaxed) {
+            let n = next.fetch_add(1, Ordering::Relaxed);
+            let job = jobs.get(n)?;
+            if let Err(e) = write_note(items, job, format) {
+                failed.store(true, Ordering::Relaxed);
+                return Some((n, e));
+            }
+        }
+        None
+    };
+
+    let workers = workers.clamp(1, jobs.len().max(1));
+    let mut errors: Vec<(usize, AppError)> = if workers == 1 {
+        worker().into_iter().collect()
+    } else {
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..workers).map(|_| scope.spawn(worker)).collect();
+            handles
+                .into_iter()
+                .filter_map(|handle| handle.join().expect("export worker panicked"))
+                .collect()
+        })
+    };
+    errors.sort_by_key(|(n, _)| *n);
+    match errors.into_iter().next() {
+        Some((_, e)) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// Worker threads for `export` when `--jobs` is not given: one per CPU
+pub fn default_jobs() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Write the live item `root_id` (everything when `None`) below `out`,
+/// converting and writing notes on `jobs` threads
+pub fn export_tree(
+    conn: &Connection,
+    root_id: Option<&str>,
+    format: ExportFormat,
+    out: &Path,
+    jobs: usize,
+) -> Result<ExportSummary, AppError> {
+    let items = get_export_items(conn)?;
+    let mut exporter = Exporter::new(conn, &items, format);
+    match root_id {
+        Some(root_id) => {
+            let index = items
+                .iter()
+                .position(|i| i.id == root_id)
+                .ok_or_else(|| AppError::NotFound(format!("Item '{}' not found", root_id)))?;
+            exporter.plan_item(index, out, &mut HashSet::new())?;
+        }
+        None => exporter.plan_all(out)?,
+    }
+
+    let Exporter {
+        jobs: notes,
+        mut summary,
+        ..
+    } = exporter;
+    write_notes(&items, &notes, format, jobs)?;
+    summary.notes = notes.len();
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{insert, memory_db, TempDir};
+
+    /// Every file below `dir` with its contents, relative to `dir`
+    fn read_tree(dir: &Path) -> Vec<(PathBuf, Option<String>)> {
+        let mut entries = Vec::new();
+        let mut pending = vec![dir.to_path_buf()];
+        while let Some(current) = pending.pop() {
+            for entry in std::fs::read_dir(&current).unwrap() {
+                let path = entry.unwrap().path();
+                let relative = path.strip_prefix(dir).unwrap().to_path_buf();
+                if path.is_dir() {
+                    entries.push((relative, None));
+                    pending.push(path);
+                } else {
+                    entries.push((relative, Some(std::fs::read_to_string(&path).unwrap())));
+                }
+            }
+        }
+        entries.sort();
+        entries
+    }
+
+    fn sample_db() -> Connection {
+        let conn = memory_db();
+        insert(&conn, "Book", "book", None);
+        insert(&conn, "Section", "section", Some("Book"));
+        for n in 0..20 {
+            let id = format!("n{}", n);
+            insert(&conn, &id, "note", Some("Section"));
+            conn.execute(
+                "UPDATE items SET title = ?1 WHERE id = ?2",
+                [format!("Note {}", n % 5), id],
+            )
+            .unwrap();
+        }
+        insert(&conn, "Loose", "note", None);
+        conn.execute("UPDATE items SET content = '<p>Body of <strong>' || id || '</strong></p>'", [])
+            .unwrap();
+        conn
+    }
+
+    #[test]
+    fn parallel_export_matches_serial() {
+        let conn = sample_db();
+        for format in [ExportFormat::Markdown, ExportFormat::Json, ExportFormat::Html] {
+            let serial = TempDir::new();
+            let parallel = TempDir::new();
+            let one = export_tree(&conn, None, format, &serial.0, 1).unwrap();
+            let many = export_tree(&conn, None, format, &parallel.0, 8).unwrap();
+
+            assert_eq!((one.notes, one.folders), (21, 2));
+            assert_eq!((many.notes, many.folders), (21, 2));
+            assert_eq!(read_tree(&serial.0), read_tree(&parallel.0));
+        }
+    }
+
+    #[test]
+    fn duplicate_titles_get_numbered_names() {
+        let conn = sample_db();
+        let out = TempDir::new();
+        export_tree(&conn, Some("Section"), ExportFormat::Markdown, &out.0, 4).unwrap();
+        let section = out.0.join("Section");
+        assert!(section.join("Note 0.md").is_file());
+        assert!(section.join("Note 0 (4).md").is_file());
+        assert!(!section.join("Note 0 (5).md").exists());
+    }
+
+    #[test]
+    fn a_failed_write_is_reported() {
+        let conn = sample_db();
+        let out = TempDir::new();
+        // A folder squatting a note's file name makes its write fail
+        std::fs::create_dir_all(out.0.join("Section").join("Note 3.md")).unwrap();
+        let err = export_tree(&conn, Some("Section"), ExportFormat::Markdown, &out.0, 4).unwrap_err();
+        assert!(err.to_string().contains("Note 3.md"), "{}", err);
+    }
+}
@@ -0,0 +1,497 @@
+//! Hierarchy validation for the unified items table
+//!
+//! Rust counterpart of `apps/main/src/storage/hierarchy.ts`, enforcing the
+//! rules from `schema/base.sql` before anything is written:
+//! - books live at the root only
+//! - sections live under a book
+//! - notes live at the root, under a book, or under a section (never under a note)
+//!
+//! It also walks subtrees for recursive delete, restore and move of containers.
+//! A recursive soft delete stamps the container and every live descendant with
+//! the same `deleted_at`, unique to that delete, which is how a restore finds
+//! the items deleted with it.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+
+/// Valid parent types for an item type (`None` means the root level)
+pub fn valid_parent_types(item_type: &str) -> &'static [Option<&'static str>] {
+    match item_type {
+        "book" => &[None],
+        "section" => &[Some("book")],
+        "note" => &[None, Some("book"), Some("section")],
+        _ => &[],
+    }
+}
+
+/// Check if an item type can be placed under a parent type (`None` for root)
+pub fn can_be_child_of(item_type: &str, parent_type: Option<&str>) -> bool {
+    valid_parent_types(item_type).contains(&parent_type)
+}
+
+/// Human-readable form of the rule an item type must follow
+fn rule_for(item_type: &str) -> &'static str {
+    match item_type {
+        "book" => "books must be at the root level",
+        "section" => "sections must be placed under a book",
+        "note" => "notes must be at the root or under a book or section",
+        _ => "unknown item type",
+    }
+}
+
+/// Validate a parent type, returning an error naming the violated rule
+pub fn check_parent_type(item_type: &str, parent_type: Option<&str>) -> Result<(), String> {
+    if valid_parent_types(item_type).is_empty() {
+        return Err(format!("Unknown item type '{}'", item_type));
+    }
+    if can_be_child_of(item_type, parent_type) {
+        return Ok(());
+    }
+
+    let parent_name = match parent_type {
+        Some(parent) => format!("a {}", parent),
+        None => "the root".to_string(),
+    };
+    Err(format!(
+        "Cannot place a {} under {}: {}",
+        item_type,
+        parent_name,
+        rule_for(item_type)
+    ))
+}
+
+/// Resolve a proposed parent and validate the hierarchy against it.
+/// Fails if the parent does not exist or is soft-deleted.
+pub fn validate_parent(
+    conn: &Connection,
+    item_type: &str,
+    parent_id: Option<&str>,
+) -> Result<(), String> {
+    let Some(parent_id) = parent_id else {
+        return check_parent_type(item_type, None);
+    };
+
+    let parent_type: Option<String> = conn
+        .query_row(
+            "SELECT type FROM items WHERE id = ?1 AND deleted_at IS NULL",
+            [parent_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to look up parent: {}", e))?;
+
+    match parent_type {
+        Some(parent_type) => check_parent_type(item_type, Some(&parent_type)),
+        None => Err(format!("Parent item '{}' not found", parent_id)),
+    }
+}
+
+/// Books and sections hold other items; notes never do
+pub fn is_container(item_type: &str) -> bool {
+    matches!(item_type, "book" | "section")
+}
+
+/// Refuse to delete or move a container unless `--recursive` was given
+pub fn require_recursive(item_type: &str, title: &str, recursive: bool) -> Result<(), String> {
+    if is_container(item_type) && !recursive {
+        return Err(format!(
+            "'{}' is a {} and its contents would be affected; pass --recursive",
+            title, item_type
+        ));
+    }
+    Ok(())
+}
+
+/// IDs of every item below `root_id` (deleted or not), parents before children.
+/// Built from a single parent -> children adjacency map rather than a query per level.
+pub fn descendant_ids(conn: &Connection, root_id: &str) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare("SELECT id, parent_id FROM items WHERE parent_id IS NOT NULL ORDER BY sort_order")
+        .map_err(|e| format!("Failed to read hierarchy: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })
+        .map_err(|e| format!("Failed to read hierarchy: {}", e))?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| format!("Failed to read hierarchy: {}", e))?;
+
+    let mut children: HashMap<String, Vec<String>> = HashMap::new();
+    for (id, parent_id) in rows {
+        children.entry(parent_id).or_default().push(id);
+    }
+
+    let mut descendants = Vec::new();
+    let mut queue = vec![root_id.to_string()];
+    while let Some(id) = queue.pop() {
+        if let Some(kids) = children.remove(&id) {
+            queue.extend(kids.iter().rev().cloned());
+            descendants.extend(kids);
+        }
+    }
+    Ok(descendants)
+}
+
+/// Fail if `new_parent_id` is `item_id` or lies inside its subtree.
+/// Walks up from the new parent, so only its ancestors are read.
+pub fn check_not_descendant(
+    conn: &Connection,
+    item_id: &str,
+    new_parent_id: Option<&str>,
+) -> Result<(), String> {
+    let Some(new_parent_id) = new_parent_id else {
+        return Ok(());
+    };
+    // UNION (not UNION ALL) drops revisited rows, so a looping chain ends
+    let inside: bool = conn
+        .query_row(
+            "WITH RECURSIVE ancestors(id, parent_id) AS (
+                SELECT id, parent_id FROM items WHERE id = ?1
+                UNION
+                SELECT items.id, items.parent_id
+                FROM items JOIN ancestors ON items.id = ancestors.parent_id
+            )
+            SELECT EXISTS(SELECT 1 FROM ancestors WHERE id = ?2)",
+            [new_parent_id, item_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to read hierarchy: {}", e))?;
+    if inside {
+        return Err("Cannot move an item into itself or one of its descendants".to_string());
+    }
+    Ok(())
+}
+
+/// A `deleted_at` for a new trash batch: the current time to the millisecond,
+/// moved on a millisecond at a time until no trashed item carries it, so two
+/// deletes never share a stamp and a restore only brings back its own batch
+fn trash_stamp(conn: &Connection) -> Result<String, String> {
+    let now: String = conn
+        .query_row("SELECT strftime('%Y-%m-%d %H:%M:%f', 'now')", [], |row| row.get(0))
+        .map_err(|e| format!("Failed to read clock: {}", e))?;
+    first_free_stamp(conn, now)
+}
+
+/// `stamp`, or the first millisecond after it that no trashed item carries
+fn first_free_stamp(conn: &Connection, mut stamp: String) -> Result<String, String> {
+    loop {
+        let taken: bool = conn
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM items WHERE deleted_at = ?1)",
+                [&stamp],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("Failed to read trash: {}", e))?;
+        if !taken {
+            return Ok(stamp);
+        }
+        stamp = conn
+            .query_row(
+                "SELECT strftime('%Y-%m-%d %H:%M:%f', ?1, '+0.001 seconds')",
+                [&stamp],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("Failed to read clock: {}", e))?;
+    }
+}
+
+/// Soft-delete `root_id` and its live descendants with one shared timestamp,
+/// unique to this delete (see `trash_stamp`). Items already in the trash keep
+/// their own timestamp. Returns the number deleted.
+/// Call inside the caller's transaction.
+pub fn soft_delete_subtree(conn: &Connection, root_id: &str) -> Result<usize, String> {
+    let deleted_at = trash_stamp(conn)?;
+
+    let mut ids = vec![root_id.to_string()];
+    ids.extend(descendant_ids(conn, root_id)?);
+
+    let mut count = 0;
+    for id in &ids {
+        count += conn
+            .execute(
+                "UPDATE items SET deleted_at = ?1 WHERE id = ?2 AND deleted_at IS NULL",
+                params![deleted_at, id],
+            )
+            .map_err(|e| format!("Failed to delete item: {}", e))?;
+    }
+    Ok(count)
+}
+
+/// Restore `root_id` and the descendants deleted together with it.
+/// Items that were trashed separately stay in the trash. Returns the number restored.
+/// Call inside the caller's transaction.
+pub fn restore_subtree(conn: &Connection, root_id: &str) -> Result<usize, String> {
+    let deleted_at: Option<String> = conn
+        .query_row(
+            "SELECT deleted_at FROM items WHERE id = ?1",
+            [root_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to look up item: {}", e))?
+        .ok_or_else(|| format!("Item '{}' not found", root_id))?;
+    let Some(deleted_at) = deleted_at else {
+        return Err(format!("Item '{}' is not deleted", root_id));
+    };
+
+    let mut ids = vec![root_id.to_string()];
+    ids.extend(descendant_ids(conn, root_id)?);
+
+    let mut count = 0;
+    for id in &ids {
+        count += conn
+            .execute(
+                "UPDATE items SET deleted_at = NULL WHERE id = ?1 AND deleted_at = ?2",
+                params![id, deleted_at],
+            )
+            .map_err(|e| format!("Failed to restore item: {}", e))?;
+    }
+    Ok(count)
+}
+
+/// Permanently delete `root_id` and everything below it, children first.
+/// Tags and versions are removed explicitly since foreign keys are not enforced
+/// on every connection; the FTS and changelog triggers fire per row.
+/// Returns the number of items removed. Call inside the caller's transaction.
+pub fn purge_subtree(conn: &Connection, root_id: &str) -> Result<usize, String> {
+    let mut ids = vec![root_id.to_string()];
+    ids.extend(descendant_ids(conn, root_id)?);
+
+    let mut count = 0;
+    for id in ids.iter().rev() {
+        conn.execute("DELETE FROM item_tags WHERE item_id = ?1", [id])
+            .map_err(|e| format!("Failed to remove tags: {}", e))?;
+        conn.execute("DELETE FROM note_versions WHERE note_id = ?1", [id])
+            .map_err(|e| format!("Failed to remove versions: {}", e))?;
+        count += conn
+            .execute("DELETE FROM items WHERE id = ?1", [id])
+            .map_err(|e| format!("Failed to delete item: {}", e))?;
+    }
+    Ok(count)
+}
+
+/// Ancestors of `id` that are in the trash, as (id, title), nearest first
+pub fn deleted_ancestors(conn: &Connection, id: &str) -> Result<Vec<(String, String)>, String> {
+    let mut ancestors = Vec::new();
+    let mut current = id.to_string();
+
+    // Walk up the parent chain like `get_item_path`; the depth cap guards against cycles
+    for _ in 0..irisnotes_search::MAX_PATH_DEPTH {
+        let parent: Option<(String, String, bool)> = conn
+            .query_row(
+                "SELECT p.id, p.title, p.deleted_at IS NOT NULL
+                 FROM items c JOIN items p ON p.id = c.parent_id
+                 WHERE c.id = ?1",
+                [&current],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()
+            .map_err(|e| format!("Failed to look up parent: {}", e))?;
+
+        let Some((parent_id, title, deleted)) = parent else {
+            break;
+        };
+        if deleted {
+            ancestors.push((parent_id.clone(), title));
+        }
+        current = parent_id;
+    }
+    Ok(ancestors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{insert, memory_db};
+
+    #[test]
+    fn allows_the_documented_parents() {
+        assert!(check_parent_type("book", None).is_ok());
+        assert!(check_parent_type("section", Some("book")).is_ok());
+        assert!(check_parent_type("note", None).is_ok());
+        assert!(check_parent_type("note", Some("book")).is_ok());
+        assert!(check_parent_type("note", Some("section")).is_ok());
+    }
+
+    #[test]
+    fn rejects_a_book_under_anything() {
+        for parent in ["book", "section", "note"] {
+            let err = check_parent_type("book", Some(parent)).unwrap_err();
+            assert!(err.contains("books must be at the root level"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn rejects_a_section_at_the_root() {
+        assert!(check_parent_type("section", None).is_err());
+    }
+
+    #[test]
+    fn rejects_a_section_under_a_note() {
+        assert!(check_parent_type("section", Some("note")).is_err());
+    }
+
+    #[test]
+    fn rejects_a_section_under_a_section() {
+        let err = check_parent_type("section", Some("section")).unwrap_err();
+        assert_eq!(
+            err,
+            "Cannot place a section under a section: sections must be placed under a book"
+        );
+    }
+
+    #[test]
+    fn rejects_anything_under_a_note() {
+        for item_type in ["book", "section", "note"] {
+            assert!(check_parent_type(item_type, Some("note")).is_err(), "{}", item_type);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_types() {
+        assert_eq!(
+            check_parent_type("folder", None).unwrap_err(),
+            "Unknown item type 'folder'"
+        );
+    }
+
+    #[test]
+    fn validate_parent_reads_the_parent_type() {
+        let conn = memory_db();
+        insert(&conn, "book", "book", None);
+        insert(&conn, "section", "section", Some("book"));
+        insert(&conn, "note", "note", Some("section"));
+
+        assert!(validate_parent(&conn, "note", Some("section")).is_ok());
+        assert!(validate_parent(&conn, "section", Some("section")).is_err());
+        assert!(validate_parent(&conn, "note", Some("note")).is_err());
+        assert_eq!(
+            validate_parent(&conn, "note", Some("missing")).unwrap_err(),
+            "Parent item 'missing' not found"
+        );
+    }
+
+    #[test]
+    fn check_not_descendant_rejects_cycles() {
+        let conn = memory_db();
+        insert(&conn, "book", "book", None);
+        insert(&conn, "section", "section", Some("book"));
+        insert(&conn, "note", "note", Some("section"));
+
+        // Into itself, its child, or a deeper descendant
+        assert!(check_not_descendant(&conn, "book", Some("book")).is_err());
+        assert!(check_not_descendant(&conn, "book", Some("section")).is_err());
+        assert!(check_not_descendant(&conn, "book", Some("note")).is_err());
+        assert!(check_not_descendant(&conn, "section", Some("note")).is_err());
+
+        // Up the tree, sideways, or to the root
+        assert!(check_not_descendant(&conn, "note", Some("book")).is_ok());
+        assert!(check_not_descendant(&conn, "section", Some("book")).is_ok());
+        assert!(check_not_descendant(&conn, "book", None).is_ok());
+    }
+
+    #[test]
+    fn check_not_descendant_ends_on_a_looping_chain() {
+        let conn = memory_db();
+        // Two sections parented by each other, as `irisnotes doctor` reports;
+        // the schema does not stop this, only the checks above do
+        insert(&conn, "book", "book", None);
+        insert(&conn, "a", "section", Some("book"));
+        insert(&conn, "b", "section", Some("book"));
+        conn.execute_batch(
+            "UPDATE items SET parent_id = 'b' WHERE id = 'a';
+             UPDATE items SET parent_id = 'a' WHERE id = 'b';",
+        )
+        .unwrap();
+
+        assert!(check_not_descendant(&conn, "a", Some("b")).is_err());
+        assert!(check_not_descendant(&conn, "book", Some("a")).is_ok());
+    }
+
+    /// `deleted_at` of every item, keyed by ID
+    fn deleted_at(conn: &Connection) -> std::collections::HashMap<String, Option<String>> {
+        let mut stmt = conn.prepare("SELECT id, deleted_at FROM items").unwrap();
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        rows
+    }
+
+    #[test]
+    fn deleting_and_restoring_a_book_round_trips_its_subtree() {
+        let conn = memory_db();
+        insert(&conn, "book", "book", None);
+        insert(&conn, "s1", "section", Some("book"));
+        insert(&conn, "s2", "section", Some("book"));
+        insert(&conn, "n1", "note", Some("s1"));
+        insert(&conn, "n2", "note", Some("s1"));
+        insert(&conn, "n3", "note", Some("s2"));
+        insert(&conn, "n4", "note", Some("book"));
+        insert(&conn, "other", "book", None);
+        // Trashed on its own before the book
+        conn.execute(
+            "UPDATE items SET deleted_at = '2020-01-01 00:00:00' WHERE id = 'n2'",
+            [],
+        )
+        .unwrap();
+
+        assert_eq!(soft_delete_subtree(&conn, "book").unwrap(), 6);
+        let deleted = deleted_at(&conn);
+        let stamp = deleted["book"].clone().unwrap();
+        for id in ["s1", "s2", "n1", "n3", "n4"] {
+            assert_eq!(deleted[id].as_deref(), Some(stamp.as_str()), "{}", id);
+        }
+        assert_eq!(deleted["n2"].as_deref(), Some("2020-01-01 00:00:00"));
+        assert_eq!(deleted["other"], None);
+
+        assert_eq!(restore_subtree(&conn, "book").unwrap(), 6);
+        let restored = deleted_at(&conn);
+        for id in ["book", "s1", "s2", "n1", "n3", "n4", "other"] {
+            assert_eq!(restored[id], None, "{}", id);
+        }
+        assert_eq!(restored["n2"].as_deref(), Some("2020-01-01 00:00:00"));
+
+        // The separately trashed note comes back on its own
+        assert_eq!(restore_subtree(&conn, "n2").unwrap(), 1);
+        assert!(restore_subtree(&conn, "n2").unwrap_err().contains("is not deleted"));
+        assert!(restore_subtree(&conn, "missing").unwrap_err().contains("not found"));
+    }
+
+    #[test]
+    fn deletes_in_quick_succession_restore_separately() {
+        let conn = memory_db();
+        insert(&conn, "book", "book", None);
+        insert(&conn, "n1", "note", Some("book"));
+        insert(&conn, "n2", "note", Some("book"));
+
+        // Well within one second: only the stamps' milliseconds can tell them apart
+        assert_eq!(soft_delete_subtree(&conn, "n1").unwrap(), 1);
+        assert_eq!(soft_delete_subtree(&conn, "book").unwrap(), 2);
+        let deleted = deleted_at(&conn);
+        assert_ne!(deleted["n1"], deleted["book"]);
+
+        assert_eq!(restore_subtree(&conn, "book").unwrap(), 2);
+        let restored = deleted_at(&conn);
+        assert_eq!(restored["n2"], None);
+        assert!(restored["n1"].is_some());
+    }
+
+    #[test]
+    fn a_taken_trash_stamp_moves_on_a_millisecond() {
+        let conn = memory_db();
+        insert(&conn, "a", "note", None);
+        insert(&conn, "b", "note", None);
+        conn.execute_batch(
+            "UPDATE items SET deleted_at = '2024-01-15 10:30:59.998' WHERE id = 'a';
+             UPDATE items SET deleted_at = '2024-01-15 10:30:59.999' WHERE id = 'b';",
+        )
+        .unwrap();
+
+        let free = |stamp: &str| first_free_stamp(&conn, stamp.to_string()).unwrap();
+        assert_eq!(free("2024-01-15 10:30:59.997"), "2024-01-15 10:30:59.997");
+        assert_eq!(free("2024-01-15 10:30:59.998"), "2024-01-15 10:31:00.000");
+        assert_eq!(trash_stamp(&conn).unwrap().len(), "2024-01-15 10:30:00.123".len());
+    }
+}
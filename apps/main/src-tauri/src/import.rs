@@ -0,0 +1,268 @@
+//! Importing Markdown files and folders as notes
+//!
+//! Shared by `irisnotes import` and the main app's `import_markdown` command.
+//! A folder's contents are imported in name order, sub-folders becoming books
+//! at the root and sections inside books, so a tree written by `export` reads
+//! back as it was (its `_root/` folder holds the root-level notes). Each file
+//! becomes a note: Markdown goes through `markdown::markdown_to_html`, files
+//! exported as HTML are sanitized instead, and frontmatter `title` overrides
+//! the file name. The FTS index is filled by the insert trigger.
+
+use crate::error::AppError;
+use crate::sort_order::Placement;
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Split `---` frontmatter (as written by `export` and the GUI exporter) from
+/// a Markdown file, returning its `key: value` pairs and the body
+fn split_frontmatter(text: &str) -> (HashMap<String, String>, &str) {
+    let mut fields = HashMap::new();
+    let Some(rest) = text
+        .strip_prefix("---\n")
+        .or_else(|| text.strip_prefix("---\r\n"))
+    else {
+        return (fields, text);
+    };
+    let Some(end) = rest.find("\n---") else {
+        return (fields, text);
+    };
+
+    for line in rest[..end].lines() {
+        if let Some((key, value)) = line.split_once(':') {
+            let key = key.trim();
+            if !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                fields.insert(key.to_string(), unquote(value.trim()));
+            }
+        }
+    }
+    let body = &rest[end + 4..];
+    let body = body.split_once('\n').map_or("", |(_, body)| body);
+    (fields, body)
+}
+
+/// A frontmatter value without its YAML quotes: `"..."` with JSON-style
+/// escapes (as `export` writes titles) or `'...'` with `''` for a quote.
+/// Anything else, including a malformed quoted string, is kept as written.
+fn unquote(value: &str) -> String {
+    if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        if let Ok(unquoted) = serde_json::from_str::<String>(value) {
+            return unquoted;
+        }
+    } else if value.len() >= 2 && value.starts_with('\'') && value.ends_with('\'') {
+        return value[1..value.len() - 1].replace("''", "'");
+    }
+    value.to_string()
+}
+
+fn is_markdown_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("md") || ext.eq_ignore_ascii_case("markdown"))
+}
+
+/// What an import created
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportSummary {
+    pub books: usize,
+    pub sections: usize,
+    pub notes: usize,
+    /// IDs of every item created, parents before their children
+    pub item_ids: Vec<String>,
+    /// Files inside imported folders that were not Markdown
+    pub skipped: Vec<PathBuf>,
+}
+
+/// Creates items from Markdown files and folders, inside the caller's transaction
+struct Importer<'a> {
+    conn: &'a Connection,
+    summary: ImportSummary,
+}
+
+impl<'a> Importer<'a> {
+    fn new(conn: &'a Connection) -> Self {
+        Self {
+            conn,
+            summary: ImportSummary::default(),
+        }
+    }
+
+    /// Create one item after its siblings and count it
+    fn insert(
+        &mut self,
+        item_type: &str,
+        title: &str,
+        html: &str,
+        parent: Option<&str>,
+    ) -> Result<String, AppError> {
+        let id =
+            crate::notes::insert_item(self.conn, item_type, title, html, parent, Placement::Append)?;
+        match item_type {
+            "book" => self.summary.books += 1,
+            "section" => self.summary.sections += 1,
+            _ => self.summary.notes += 1,
+        }
+        self.summary.item_ids.push(id.clone());
+        Ok(id)
+    }
+
+    /// Whether `id` is a section, whose sub-folders cannot become sections
+    fn is_section(&self, id: &str) -> Result<bool, AppError> {
+        let item_type: String = self
+            .conn
+            .query_row("SELECT type FROM items WHERE id = ?1", [id], |row| row.get(0))?;
+        Ok(item_type == "section")
+    }
+
+    /// Import a Markdown file or the contents of a folder under `parent`,
+    /// which must already be checked to hold notes
+    fn import_path(&mut self, path: &Path, parent: Option<&str>) -> Result<(), AppError> {
+        if !path.exists() {
+            return Err(AppError::NotFound(format!("{} does not exist", path.display())));
+        }
+        if path.is_dir() {
+            self.import_dir(path, parent, true)
+        } else if is_markdown_file(path) {
+            self.import_file(path, parent)
+        } else {
+            Err(AppError::InvalidInput(format!(
+                "{} is not a Markdown file",
+                path.display()
+            )))
+        }
+    }
+
+    /// Create a note from a Markdown file. Frontmatter `title` overrides the
+    /// file name; bodies exported as HTML are sanitized instead of converted.
+    fn import_file(&mut self, path: &Path, parent: Option<&str>) -> Result<(), AppError> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| AppError::Io(format!("Failed to read {}: {}", path.display(), e)))?;
+        let (fields, body) = split_frontmatter(&text);
+
+        let stem = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let title = fields
+            .get("title")
+            .map(|t| t.trim())
+            .filter(|t| !t.is_empty())
+            .unwrap_or(stem.trim());
+        let title = if title.is_empty() { "Untitled" } else { title };
+
+        let html = if fields.get("content_type").is_some_and(|t| t == "html") {
+            crate::sanitize::sanitize_html(body, &crate::sanitize::Allowlist::default())
+        } else {
+            crate::markdown::markdown_to_html(body)
+        };
+
+        self.insert("note", title, &html, parent)?;
+        Ok(())
+    }
+
+    /// Import the contents of `dir` under `parent`. Folders become books at
+    /// the root and sections inside books; inside a section their contents
+    /// are added to that section. At the top level, a `_root` folder (as
+    /// written by `export`) holds notes that belong directly under `parent`.
+    fn import_dir(
+        &mut self,
+        dir: &Path,
+        parent: Option<&str>,
+        top_level: bool,
+    ) -> Result<(), AppError> {
+        let mut entries = std::fs::read_dir(dir)
+            .map_err(|e| AppError::Io(format!("Failed to read {}: {}", dir.display(), e)))?
+            .collect::<std::io::Result<Vec<_>>>()
+            .map_err(|e| AppError::Io(format!("Failed to read {}: {}", dir.display(), e)))?;
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with('.') {
+                continue;
+            }
+
+            if path.is_dir() {
+                if top_level && name == "_root" {
+                    self.import_dir(&path, parent, false)?;
+                    continue;
+                }
+                // Sections cannot nest, so a folder inside one adds its notes to it
+                let id = match parent {
+                    None => self.insert("book", &name, "", None)?,
+                    Some(parent_id) if self.is_section(parent_id)? => parent_id.to_string(),
+                    Some(parent_id) => self.insert("section", &name, "", Some(parent_id))?,
+                };
+                self.import_dir(&path, Some(&id), false)?;
+            } else if is_markdown_file(&path) {
+                self.import_file(&path, parent)?;
+            } else {
+                self.summary.skipped.push(path);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Import each of `paths` under `parent_id` (the root when `None`) in one
+/// transaction, so a failure part way leaves nothing half-imported
+pub fn import_paths(
+    conn: &Connection,
+    paths: &[PathBuf],
+    parent_id: Option<&str>,
+) -> Result<ImportSummary, AppError> {
+    crate::hierarchy::validate_parent(conn, "note", parent_id).map_err(AppError::InvalidInput)?;
+
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| AppError::Db(format!("Failed to start transaction: {}", e)))?;
+    let mut importer = Importer::new(&tx);
+    for path in paths {
+        importer.import_path(path, parent_id)?;
+    }
+    let summary = importer.summary;
+    tx.commit()
+        .map_err(|e| AppError::Db(format!("Failed to commit import: {}", e)))?;
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::{export_note, ExportFormat};
+    use crate::test_util::{insert, memory_db, TempDir};
+
+    #[test]
+    fn frontmatter_values_are_unquoted() {
+        let (fields, body) = split_frontmatter(
+            "---\ntitle: \"A \\\"quoted\\\" title\"\nid: 'it''s'\ncreated: 2024-01-01\n---\n\nBody\n",
+        );
+        assert_eq!(fields["title"], "A \"quoted\" title");
+        assert_eq!(fields["id"], "it's");
+        assert_eq!(fields["created"], "2024-01-01");
+        assert_eq!(body, "\nBody\n");
+    }
+
+    #[test]
+    fn an_exported_title_reads_back_unchanged() {
+        let conn = memory_db();
+        let title = "Re: \"draft\" #2\nsecond line";
+        insert(&conn, "n", "note", None);
+        conn.execute("UPDATE items SET title = ?1 WHERE id = 'n'", [title]).unwrap();
+
+        let dir = TempDir::new();
+        let file = dir.0.join("note.md");
+        std::fs::write(&file, export_note(&conn, "n", ExportFormat::Markdown).unwrap()).unwrap();
+        let summary = import_paths(&conn, &[file], None).unwrap();
+
+        let imported: String = conn
+            .query_row("SELECT title FROM items WHERE id = ?1", [&summary.item_ids[0]], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(imported, title);
+    }
+}
@@ -1,8 +1,27 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 pub mod cli;
-
+pub mod config;
+pub mod doctor;
+pub mod encryption;
+pub mod error;
+pub mod export;
+pub mod hierarchy;
+pub mod import;
+pub mod markdown;
+pub mod migrations;
+pub mod notes;
+pub mod sanitize;
+pub mod sort_order;
+pub mod table;
+#[cfg(test)]
+mod test_util;
+pub mod timestamp;
+
+use error::AppError;
 use notify::{Config, Event, RecommendedWatcher, RecursiveMode, Watcher};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc;
 use std::thread;
 use std::time::{Duration, Instant};
@@ -13,106 +32,109 @@ use tauri::{AppHandle, Emitter, Manager, PhysicalPosition, PhysicalSize, Webview
 const DATABASE_SCHEMA: &str = include_str!("../../../../schema/base.sql");
 const ALLOWED_IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp", "svg", "bmp", "ico"];
 const MAX_IMAGE_ASSET_BYTES: u64 = 20 * 1024 * 1024;
+// URL scheme for links that open a note (irisnotes://note/<id>), see tauri.conf.json
+const DEEP_LINK_SCHEME: &str = "irisnotes";
 
 // Helper function to determine if we're in development mode
 fn is_development_mode() -> bool {
-    cfg!(debug_assertions) || std::env::var("TAURI_ENV").as_deref() == Ok("dev")
-}
-
-// Helper function to get the appropriate config directory
-fn get_config_dir(_app_handle: &AppHandle) -> Result<PathBuf, String> {
-    if is_development_mode() {
-        // In development mode, use ./dev/config relative to project root
-        let exe_path = std::env::current_exe()
-            .map_err(|e| format!("Failed to get exe path: {}", e))?;
-        let mut project_root = exe_path.parent()
-            .ok_or("Failed to get exe parent")?
-            .to_path_buf();
-
-        // Navigate up to find monorepo root (where pnpm-workspace.yaml exists)
-        // We use pnpm-workspace.yaml instead of package.json because apps/main 
-        // also has a package.json
-        loop {
-            if project_root.join("pnpm-workspace.yaml").exists() {
-                break;
-            }
-            if !project_root.pop() {
-                // Fallback: use current directory
-                project_root = std::env::current_dir()
-                    .map_err(|e| format!("Failed to get current dir: {}", e))?;
-                break;
-            }
-        }
-
-        let dev_config = project_root.join("dev");
-
-        // Create the directory if it doesn't exist
-        std::fs::create_dir_all(&dev_config)
-            .map_err(|e| format!("Failed to create dev config directory: {}", e))?;
-
-        Ok(dev_config)
-    } else {
-        // In production mode, use ~/.config/irisnotes/ (Linux) or platform equivalent
-        let config_dir = dirs::config_dir()
-            .ok_or("Failed to get system config directory")?
-            .join("irisnotes");
-
-        std::fs::create_dir_all(&config_dir)
-            .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    irisnotes_paths::is_development_mode()
+}
 
-        Ok(config_dir)
-    }
+// Helper function to get the appropriate config directory, creating it if
+// needed. See irisnotes_paths for how it is resolved; the quick app and the
+// CLI use the same rules.
+fn get_config_dir(_app_handle: &AppHandle) -> Result<PathBuf, AppError> {
+    let config_dir = irisnotes_paths::config_dir().map_err(AppError::Config)?;
+    std::fs::create_dir_all(&config_dir)
+        .map_err(|e| AppError::Io(format!("Failed to create config directory: {}", e)))?;
+    Ok(config_dir)
 }
 
 // Helper function to get the appropriate data directory for databases
 // NOTE: Currently using the same directory as config (~/.config/irisnotes/) to keep
 // everything in one place, matching the dev layout. If we want to follow XDG standards
-// in the future, change dirs::config_dir() to dirs::data_dir() which would put the
-// database in ~/.local/share/irisnotes/ on Linux instead.
-fn get_data_dir(_app_handle: &AppHandle) -> Result<PathBuf, String> {
-    if is_development_mode() {
-        // In development mode, use ./dev relative to project root
-        let exe_path = std::env::current_exe()
-            .map_err(|e| format!("Failed to get exe path: {}", e))?;
-        let mut project_root = exe_path.parent()
-            .ok_or("Failed to get exe parent")?
-            .to_path_buf();
-
-        // Navigate up to find monorepo root (where pnpm-workspace.yaml exists)
-        // We use pnpm-workspace.yaml instead of package.json because apps/main 
-        // also has a package.json
-        loop {
-            if project_root.join("pnpm-workspace.yaml").exists() {
-                break;
-            }
-            if !project_root.pop() {
-                // Fallback: use current directory
-                project_root = std::env::current_dir()
-                    .map_err(|e| format!("Failed to get current dir: {}", e))?;
-                break;
-            }
-        }
-
-        let dev_data = project_root.join("dev");
+// in the future, change dirs::config_dir() to dirs::data_dir() in irisnotes_paths,
+// which would put the database in ~/.local/share/irisnotes/ on Linux instead.
+fn get_data_dir(_app_handle: &AppHandle) -> Result<PathBuf, AppError> {
+    let data_dir = irisnotes_paths::data_dir().map_err(AppError::Config)?;
+    std::fs::create_dir_all(&data_dir)
+        .map_err(|e| AppError::Io(format!("Failed to create data directory: {}", e)))?;
+    Ok(data_dir)
+}
 
-        // Create the directory if it doesn't exist
-        std::fs::create_dir_all(&dev_data)
-            .map_err(|e| format!("Failed to create dev data directory: {}", e))?;
+// The database file, resolved the same way by the quick app and the CLI
+fn get_database_file(_app_handle: &AppHandle) -> Result<PathBuf, AppError> {
+    let db_path = irisnotes_paths::resolve_database_path().map_err(AppError::Config)?;
+    if let Some(parent) = db_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| AppError::Io(format!("Failed to create database directory: {}", e)))?;
+    }
+    Ok(db_path)
+}
 
-        Ok(dev_data)
-    } else {
-        // In production mode, use ~/.config/irisnotes/ (same as config dir for simplicity)
-        let data_dir = dirs::config_dir()
-            .ok_or("Failed to get system config directory")?
-            .join("irisnotes");
+/// Payload of `open-note-from-quick`, built from `--open-note <id>` and an
+/// optional `--highlight <term>` passed by the quick app, or from an
+/// `irisnotes://note/<id>?highlight=<term>` deep link
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OpenNoteRequest {
+    note_id: String,
+    highlight: Option<String>,
+}
 
-        std::fs::create_dir_all(&data_dir)
-            .map_err(|e| format!("Failed to create data directory: {}", e))?;
+impl OpenNoteRequest {
+    /// Parse `irisnotes://note/<id>`; other URLs are ignored
+    fn from_url(url: &str) -> Option<Self> {
+        let url = tauri::Url::parse(url).ok()?;
+        if url.scheme() != DEEP_LINK_SCHEME || url.host_str() != Some("note") {
+            return None;
+        }
+        let note_id = url.path().trim_matches('/');
+        if note_id.is_empty() || note_id.contains('/') {
+            return None;
+        }
+        let highlight = url
+            .query_pairs()
+            .find(|(key, _)| key == "highlight")
+            .map(|(_, term)| term.into_owned())
+            .filter(|term| !term.is_empty());
+        Some(Self {
+            note_id: note_id.to_string(),
+            highlight,
+        })
+    }
 
-        Ok(data_dir)
+    /// Read a request from command-line arguments. On Linux and Windows a deep
+    /// link reaches us as an argument too, so URLs are accepted alongside flags.
+    fn from_args(args: &[String]) -> Option<Self> {
+        if let Some(request) = args.iter().find_map(|arg| Self::from_url(arg)) {
+            return Some(request);
+        }
+        let note_id = flag_value(args, "--open-note").filter(|id| !id.is_empty())?;
+        let highlight = flag_value(args, "--highlight")
+            .filter(|term| !term.is_empty())
+            .map(str::to_string);
+        Some(Self {
+            note_id: note_id.to_string(),
+            highlight,
+        })
     }
 }
 
+/// Value of `--flag=value` or `--flag value` in command-line arguments
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter().enumerate().find_map(|(i, arg)| {
+        let rest = arg.strip_prefix(flag)?;
+        if let Some(value) = rest.strip_prefix('=') {
+            Some(value)
+        } else if rest.is_empty() {
+            args.get(i + 1).map(String::as_str).filter(|value| !value.starts_with("--"))
+        } else {
+            None
+        }
+    })
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct SavedWindowState {
     width: u32,
@@ -130,7 +152,7 @@ struct SavedWindowStates {
 
 const WINDOW_STATE_KEY: &str = "main";
 
-fn window_state_file_path(app_handle: &AppHandle) -> Result<PathBuf, String> {
+fn window_state_file_path(app_handle: &AppHandle) -> Result<PathBuf, AppError> {
     Ok(get_config_dir(app_handle)?.join(".window-state.json"))
 }
 
@@ -200,42 +222,40 @@ fn save_window_state(window: &WebviewWindow, path: &PathBuf) -> Result<(), Strin
         .map_err(|e| format!("Failed to write window state: {}", e))
 }
 
-/// Initialize the database if it doesn't exist
-/// Creates the schema from base.sql embedded at compile time
-fn init_database(app_handle: &AppHandle) -> Result<(), String> {
-    use rusqlite::Connection;
+/// How long a connection waits for another app's write lock before failing
+/// with "database is locked"
+const BUSY_TIMEOUT: Duration = Duration::from_millis(5000);
+
+/// Open the notes database for the backend and the CLI. WAL mode lets the
+/// quick app and the CLI read while one connection writes; it is stored in
+/// the database file, so setting it again is a no-op. The frontend's
+/// tauri_plugin_sql (sqlx) pool already opens its connections in WAL mode
+/// with the same 5 s busy timeout. An encrypted database is unlocked first
+/// (see encryption.rs).
+pub(crate) fn open_database(path: &std::path::Path) -> Result<rusqlite::Connection, String> {
+    let conn = rusqlite::Connection::open(path).map_err(|e| e.to_string())?;
+    encryption::unlock(&conn)?;
+    conn.busy_timeout(BUSY_TIMEOUT).map_err(|e| e.to_string())?;
+    // journal_mode reports the resulting mode as a row
+    conn.query_row("PRAGMA journal_mode = WAL", [], |row| row.get::<_, String>(0))
+        .map_err(|e| e.to_string())?;
+    Ok(conn)
+}
 
-    let data_dir = get_data_dir(app_handle)?;
-    let db_path = data_dir.join("notes.db");
-
-    // Check if the database file already exists
-    if db_path.exists() {
-        // Database exists - check if it has tables
-        let conn = Connection::open(&db_path)
-            .map_err(|e| format!("Failed to open database: {}", e))?;
-
-        let table_count: i32 = conn
-            .query_row(
-                "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='items'",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0);
-
-        if table_count > 0 {
-            // Database is already initialized
-            return Ok(());
-        }
+/// Create or upgrade the database schema (see migrations.rs)
+fn init_database(app_handle: &AppHandle) -> Result<(), AppError> {
+    let db_path = get_database_file(app_handle)?;
+    let conn = open_database(&db_path)
+        .map_err(|e| AppError::Db(format!("Failed to open database: {}", e)))?;
+
+    for migration in migrations::migrate(&conn).map_err(AppError::Db)? {
+        println!(
+            "Applied database migration {} ({}) to {}",
+            migration.version,
+            migration.description,
+            db_path.display()
+        );
     }
-
-    // Create new database with schema
-    let conn = Connection::open(&db_path)
-        .map_err(|e| format!("Failed to create database: {}", e))?;
-
-    conn.execute_batch(DATABASE_SCHEMA)
-        .map_err(|e| format!("Failed to initialize database schema: {}", e))?;
-
-    println!("Database initialized at: {}", db_path.display());
     Ok(())
 }
 
@@ -249,10 +269,26 @@ fn is_wayland() -> bool {
     std::env::var("WAYLAND_DISPLAY").is_ok() || std::env::var("XDG_SESSION_TYPE").as_deref() == Ok("wayland")
 }
 
+/// Sanitize HTML against the default allowlist, adjusted by `keep`/`strip` entries
+/// (`tag` or `tag[attr ...]`)
+#[tauri::command]
+fn sanitize_html(
+    html: String,
+    keep: Option<Vec<String>>,
+    strip: Option<Vec<String>>,
+) -> Result<String, AppError> {
+    let allowlist = sanitize::Allowlist::from_options(
+        keep.as_deref().unwrap_or_default(),
+        strip.as_deref().unwrap_or_default(),
+    )
+    .map_err(AppError::InvalidInput)?;
+    Ok(sanitize::sanitize_html(&html, &allowlist))
+}
+
 /// Read clipboard content with a specific MIME type/target
 /// Uses wl-paste on Wayland, xclip on X11
 #[tauri::command]
-fn read_clipboard_target(target: String) -> Result<String, String> {
+fn read_clipboard_target(target: String) -> Result<String, AppError> {
     use std::process::Command;
 
     let output = if is_wayland() {
@@ -260,60 +296,66 @@ fn read_clipboard_target(target: String) -> Result<String, String> {
         Command::new("wl-paste")
             .args(["--no-newline", "-t", &target])
             .output()
-            .map_err(|e| format!("Failed to run wl-paste: {}. Is wl-clipboard installed?", e))?
+            .map_err(|e| {
+                AppError::Io(format!("Failed to run wl-paste: {}. Is wl-clipboard installed?", e))
+            })?
     } else {
         // X11: use xclip
         Command::new("xclip")
             .args(["-selection", "clipboard", "-target", &target, "-o"])
             .output()
-            .map_err(|e| format!("Failed to run xclip: {}. Is xclip installed?", e))?
+            .map_err(|e| AppError::Io(format!("Failed to run xclip: {}. Is xclip installed?", e)))?
     };
 
     if output.status.success() {
         String::from_utf8(output.stdout)
-            .map_err(|e| format!("Clipboard content is not valid UTF-8: {}", e))
+            .map_err(|e| AppError::Parse(format!("Clipboard content is not valid UTF-8: {}", e)))
     } else {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        Err(format!("Clipboard read failed: {}", stderr))
+        Err(AppError::Io(format!("Clipboard read failed: {}", stderr)))
     }
 }
 
 /// Read binary clipboard content with a specific MIME type/target.
 /// Used as a fallback for image paste when the webview does not expose a File.
 #[tauri::command]
-fn read_clipboard_binary_target(target: String) -> Result<Vec<u8>, String> {
+fn read_clipboard_binary_target(target: String) -> Result<Vec<u8>, AppError> {
     use std::process::Command;
 
     if !target.to_ascii_lowercase().starts_with("image/") {
-        return Err(format!("Not an image clipboard target: {}", target));
+        return Err(AppError::InvalidInput(format!("Not an image clipboard target: {}", target)));
     }
 
     let output = if is_wayland() {
         Command::new("wl-paste")
             .args(["-t", &target])
             .output()
-            .map_err(|e| format!("Failed to run wl-paste: {}. Is wl-clipboard installed?", e))?
+            .map_err(|e| {
+                AppError::Io(format!("Failed to run wl-paste: {}. Is wl-clipboard installed?", e))
+            })?
     } else {
         Command::new("xclip")
             .args(["-selection", "clipboard", "-target", &target, "-o"])
             .output()
-            .map_err(|e| format!("Failed to run xclip: {}. Is xclip installed?", e))?
+            .map_err(|e| AppError::Io(format!("Failed to run xclip: {}. Is xclip installed?", e)))?
     };
 
     if output.status.success() {
         if output.stdout.len() as u64 > MAX_IMAGE_ASSET_BYTES {
-            return Err("Clipboard image is larger than the 20 MB import limit".to_string());
+            return Err(AppError::InvalidInput(
+                "Clipboard image is larger than the 20 MB import limit".into(),
+            ));
         }
         Ok(output.stdout)
     } else {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        Err(format!("Clipboard read failed: {}", stderr))
+        Err(AppError::Io(format!("Clipboard read failed: {}", stderr)))
     }
 }
 
 /// List all available clipboard targets (MIME types)
 #[tauri::command]
-fn list_clipboard_targets() -> Result<Vec<String>, String> {
+fn list_clipboard_targets() -> Result<Vec<String>, AppError> {
     use std::process::Command;
 
     let output = if is_wayland() {
@@ -321,13 +363,15 @@ fn list_clipboard_targets() -> Result<Vec<String>, String> {
         Command::new("wl-paste")
             .args(["--list-types"])
             .output()
-            .map_err(|e| format!("Failed to run wl-paste: {}. Is wl-clipboard installed?", e))?
+            .map_err(|e| {
+                AppError::Io(format!("Failed to run wl-paste: {}. Is wl-clipboard installed?", e))
+            })?
     } else {
         // X11: use xclip with TARGETS
         Command::new("xclip")
             .args(["-selection", "clipboard", "-target", "TARGETS", "-o"])
             .output()
-            .map_err(|e| format!("Failed to run xclip: {}. Is xclip installed?", e))?
+            .map_err(|e| AppError::Io(format!("Failed to run xclip: {}. Is xclip installed?", e)))?
     };
 
     if output.status.success() {
@@ -335,14 +379,14 @@ fn list_clipboard_targets() -> Result<Vec<String>, String> {
         Ok(content.lines().map(|s| s.to_string()).collect())
     } else {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        Err(format!("Clipboard list failed: {}", stderr))
+        Err(AppError::Io(format!("Clipboard list failed: {}", stderr)))
     }
 }
 
 /// Read VS Code editor data from clipboard
 /// Handles both direct vscode-editor-data (X11) and embedded in chromium/x-web-custom-data (Wayland)
 #[tauri::command]
-fn read_vscode_editor_data() -> Result<String, String> {
+fn read_vscode_editor_data() -> Result<String, AppError> {
     use std::process::Command;
 
     // First, try to read vscode-editor-data directly
@@ -358,12 +402,12 @@ fn read_vscode_editor_data() -> Result<String, String> {
             Command::new("wl-paste")
                 .args(["--no-newline", "-t", "chromium/x-web-custom-data"])
                 .output()
-                .map_err(|e| format!("Failed to run wl-paste: {}", e))?
+                .map_err(|e| AppError::Io(format!("Failed to run wl-paste: {}", e)))?
         } else {
             Command::new("xclip")
                 .args(["-selection", "clipboard", "-target", "chromium/x-web-custom-data", "-o"])
                 .output()
-                .map_err(|e| format!("Failed to run xclip: {}", e))?
+                .map_err(|e| AppError::Io(format!("Failed to run xclip: {}", e)))?
         };
 
         if output.status.success() {
@@ -405,7 +449,7 @@ fn read_vscode_editor_data() -> Result<String, String> {
         }
     }
 
-    Err("vscode-editor-data not found in clipboard".to_string())
+    Err(AppError::NotFound("vscode-editor-data not found in clipboard".into()))
 }
 
 /// Find the end of a JSON object by counting braces
@@ -437,7 +481,7 @@ fn find_json_object_end(s: &str) -> Option<usize> {
 }
 
 #[tauri::command]
-async fn open_app_config_folder(app_handle: tauri::AppHandle) -> Result<(), String> {
+async fn open_app_config_folder(app_handle: tauri::AppHandle) -> Result<(), AppError> {
     use tauri_plugin_opener::OpenerExt;
 
     let app_config_dir = get_config_dir(&app_handle)?;
@@ -461,7 +505,7 @@ async fn open_app_config_folder(app_handle: tauri::AppHandle) -> Result<(), Stri
         app_handle
             .opener()
             .open_path(&path_str, None::<&str>)
-            .map_err(|e| format!("Failed to open app config folder: {}", e))?;
+            .map_err(|e| AppError::Io(format!("Failed to open app config folder: {}", e)))?;
     }
 
     #[cfg(not(target_os = "linux"))]
@@ -469,14 +513,14 @@ async fn open_app_config_folder(app_handle: tauri::AppHandle) -> Result<(), Stri
         app_handle
             .opener()
             .open_path(app_config_dir.to_string_lossy().to_string(), None::<&str>)
-            .map_err(|e| format!("Failed to open app config folder: {}", e))?;
+            .map_err(|e| AppError::Io(format!("Failed to open app config folder: {}", e)))?;
     }
 
     Ok(())
 }
 
 #[tauri::command]
-async fn read_config(app_handle: tauri::AppHandle, filename: String) -> Result<String, String> {
+async fn read_config(app_handle: tauri::AppHandle, filename: String) -> Result<String, AppError> {
     let app_config_dir = get_config_dir(&app_handle)?;
 
     // Determine the base name (without extension) and try TOML first, then JSON
@@ -491,17 +535,22 @@ async fn read_config(app_handle: tauri::AppHandle, filename: String) -> Result<S
     if toml_path.exists() {
         // Read TOML and convert to JSON for frontend
         let toml_content = std::fs::read_to_string(&toml_path)
-            .map_err(|e| format!("Failed to read {}.toml: {}", base_name, e))?;
-        let value: toml::Value = toml::from_str(&toml_content)
-            .map_err(|e| format!("Failed to parse TOML: {}", e))?;
-        serde_json::to_string(&value)
-            .map_err(|e| format!("Failed to convert TOML to JSON: {}", e))
+            .map_err(|e| AppError::Io(format!("Failed to read {}.toml: {}", base_name, e)))?;
+        let value: toml::Value = toml::from_str(&toml_content)?;
+        if base_name == "config" {
+            log_config_problems(&toml_content, &toml_path);
+        }
+        Ok(serde_json::to_string(&value)?)
     } else if json_path.exists() {
         // Fall back to JSON for backward compatibility
-        std::fs::read_to_string(&json_path)
-            .map_err(|e| format!("Failed to read config file: {}", e))
+        let json_content = std::fs::read_to_string(&json_path)
+            .map_err(|e| AppError::Io(format!("Failed to read config file: {}", e)))?;
+        if base_name == "config" {
+            log_config_problems(&json_content, &json_path);
+        }
+        Ok(json_content)
     } else {
-        Err(format!("Config file {}.toml does not exist", base_name))
+        Err(AppError::NotFound(format!("Config file {}.toml does not exist", base_name)))
     }
 }
 
@@ -510,7 +559,7 @@ async fn write_config(
     app_handle: tauri::AppHandle,
     filename: String,
     content: String,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     let app_config_dir = get_config_dir(&app_handle)?;
 
     // Determine the base name and always write as TOML
@@ -522,21 +571,213 @@ async fn write_config(
     let toml_path = app_config_dir.join(format!("{}.toml", base_name));
 
     // Parse JSON from frontend and convert to TOML
-    let json_value: serde_json::Value = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse JSON: {}", e))?;
-    let toml_value: toml::Value = serde_json::from_value(
-        serde_json::to_value(&json_value).unwrap()
-    ).map_err(|e| format!("Failed to convert to TOML value: {}", e))?;
-    let toml_string = toml::to_string_pretty(&toml_value)
-        .map_err(|e| format!("Failed to serialize TOML: {}", e))?;
-    std::fs::write(&toml_path, toml_string)
-        .map_err(|e| format!("Failed to write {}.toml: {}", base_name, e))
+    let toml_string = json_to_toml(&content)?;
+
+    // Reject an invalid app config before it replaces the file on disk
+    if base_name == "config" {
+        let problems = config::config_problems(&toml_string, &toml_path);
+        if !problems.is_empty() {
+            return Err(AppError::Config(format!("Invalid config: {}", problems.join("; "))));
+        }
+    }
+
+    write_atomic(&toml_path, &toml_string)
+        .map_err(|e| AppError::Io(format!("Failed to write {}.toml: {}", base_name, e)))
 }
 
+// Replace a config file with its defaults, keeping the current file as
+// <name>.toml.bak. Only the app config has defaults on the Rust side.
 #[tauri::command]
-async fn setup_config_watcher(app_handle: AppHandle) -> Result<(), String> {
+async fn reset_config(app_handle: tauri::AppHandle, filename: String) -> Result<(), AppError> {
     let app_config_dir = get_config_dir(&app_handle)?;
 
+    let base_name = filename
+        .strip_suffix(".json")
+        .or_else(|| filename.strip_suffix(".toml"))
+        .unwrap_or(&filename);
+    if base_name != "config" {
+        return Err(AppError::InvalidInput(format!("No defaults for {}.toml", base_name)));
+    }
+
+    let toml_path = app_config_dir.join(format!("{}.toml", base_name));
+    let toml_string = config::AppConfig::defaults().to_toml()?;
+
+    // Copy rather than rename, so the config is never missing mid-reset
+    if toml_path.exists() {
+        let backup_path = app_config_dir.join(format!("{}.toml.bak", base_name));
+        std::fs::copy(&toml_path, &backup_path)
+            .map_err(|e| AppError::Io(format!("Failed to back up {}.toml: {}", base_name, e)))?;
+    }
+    write_atomic(&toml_path, &toml_string)
+        .map_err(|e| AppError::Io(format!("Failed to write {}.toml: {}", base_name, e)))
+}
+
+// The default app config as JSON, in the shape read_config returns
+#[tauri::command]
+async fn get_default_config() -> Result<String, AppError> {
+    let toml_string = config::AppConfig::defaults().to_toml()?;
+    let value: toml::Value = toml::from_str(&toml_string)?;
+    Ok(serde_json::to_string(&value)?)
+}
+
+// A config that fails validation is still handed to the frontend: refusing it
+// would make the app fall back to defaults and overwrite the file on the next
+// save. The problems are logged here and reported by validate_config.
+fn log_config_problems(content: &str, path: &std::path::Path) {
+    for problem in config::config_problems(content, path) {
+        eprintln!("Config problem: {}", problem);
+    }
+}
+
+// Check the app config against its schema. Returns one message per problem,
+// naming the offending field; an empty list means the config is valid (or
+// there is no config file yet).
+#[tauri::command]
+async fn validate_config(app_handle: tauri::AppHandle) -> Result<Vec<String>, AppError> {
+    let app_config_dir = get_config_dir(&app_handle)?;
+    let Some(path) = ["config.toml", "config.json"]
+        .iter()
+        .map(|name| app_config_dir.join(name))
+        .find(|path| path.exists())
+    else {
+        return Ok(Vec::new());
+    };
+
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| AppError::Io(format!("Failed to read {}: {}", path.display(), e)))?;
+    Ok(config::config_problems(&content, &path))
+}
+
+// Replace a file's contents all at once: write a temporary file next to it,
+// then rename it over the original. A crash mid-write leaves the old file
+// intact, and concurrent writers each land a complete file. std::fs::rename
+// replaces an existing destination on Windows as well as Unix.
+pub(crate) fn write_atomic(path: &std::path::Path, contents: &str) -> std::io::Result<()> {
+    use std::io::Write;
+    use std::sync::atomic::AtomicU64;
+
+    // Unique per process and per write, so racing writers never share a temp file
+    static WRITE_COUNTER: AtomicU64 = AtomicU64::new(0);
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let tmp_path = path.with_file_name(format!(
+        ".{}.{}.{}.tmp",
+        file_name,
+        std::process::id(),
+        WRITE_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let result = std::fs::File::create(&tmp_path)
+        .and_then(|mut file| {
+            file.write_all(contents.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|()| std::fs::rename(&tmp_path, path));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+// Convert a JSON config document to TOML. TOML has no null, so null
+// entries are dropped, which reads back the same as an unset key.
+fn json_to_toml(content: &str) -> Result<String, AppError> {
+    fn drop_nulls(value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::Object(map) => {
+                map.retain(|_, v| !v.is_null());
+                map.values_mut().for_each(drop_nulls);
+            }
+            serde_json::Value::Array(items) => items.iter_mut().for_each(drop_nulls),
+            _ => {}
+        }
+    }
+
+    let mut json_value: serde_json::Value = serde_json::from_str(content)?;
+    drop_nulls(&mut json_value);
+    let toml_value: toml::Value = serde_json::from_value(json_value)
+        .map_err(|e| AppError::Parse(format!("Failed to convert to TOML value: {}", e)))?;
+    Ok(toml::to_string_pretty(&toml_value)?)
+}
+
+// One-time migration of a legacy config.json: write the equivalent
+// config.toml and keep the old file as config.json.bak, so it is not edited
+// by mistake after read_config stops looking at it. Does nothing once a
+// config.toml exists. Returns whether a migration happened.
+fn migrate_json_config(config_dir: &std::path::Path) -> Result<bool, AppError> {
+    let toml_path = config_dir.join("config.toml");
+    let json_path = config_dir.join("config.json");
+    if toml_path.exists() || !json_path.exists() {
+        return Ok(false);
+    }
+
+    let content = std::fs::read_to_string(&json_path)
+        .map_err(|e| AppError::Io(format!("Failed to read config.json: {}", e)))?;
+    let toml_string = json_to_toml(&content)?;
+    write_atomic(&toml_path, &toml_string)
+        .map_err(|e| AppError::Io(format!("Failed to write config.toml: {}", e)))?;
+    std::fs::rename(&json_path, config_dir.join("config.json.bak"))
+        .map_err(|e| AppError::Io(format!("Failed to rename config.json: {}", e)))?;
+    Ok(true)
+}
+
+// Files setup_config_watcher reports when the caller names none: the app
+// config plus the per-feature files the frontend reads through read_config
+const DEFAULT_WATCHED_FILES: &[&str] = &[
+    "config.toml",
+    "config.json",
+    "hotkeys.toml",
+    "hotkeys.json",
+    "autocorrect.toml",
+    "ascii-art.toml",
+];
+
+// Match a path relative to the config dir (with `/` separators) against a
+// watch pattern. `*` matches any run of characters and `?` any single
+// character, neither crossing a `/`, so `themes/*.toml` only matches files
+// directly inside themes/.
+fn watch_pattern_matches(pattern: &str, path: &str) -> bool {
+    fn matches(pattern: &[char], path: &[char]) -> bool {
+        match pattern.split_first() {
+            None => path.is_empty(),
+            Some(('*', rest)) => {
+                (0..=path.len())
+                    .take_while(|&skip| skip == 0 || path[skip - 1] != '/')
+                    .any(|skip| matches(rest, &path[skip..]))
+            }
+            Some(('?', rest)) => {
+                path.first().is_some_and(|&c| c != '/') && matches(rest, &path[1..])
+            }
+            Some((c, rest)) => path.first() == Some(c) && matches(rest, &path[1..]),
+        }
+    }
+
+    let pattern: Vec<char> = pattern.chars().collect();
+    let path: Vec<char> = path.chars().collect();
+    matches(&pattern, &path)
+}
+
+// Watch the config dir and emit `config-file-changed` with the changed file's
+// path relative to it. `files` lists names or globs (see
+// watch_pattern_matches) and defaults to DEFAULT_WATCHED_FILES; a pattern
+// inside a subfolder such as `themes/*.toml` makes the watch recursive.
+#[tauri::command]
+async fn setup_config_watcher(
+    app_handle: AppHandle,
+    files: Option<Vec<String>>,
+) -> Result<(), AppError> {
+    let app_config_dir = get_config_dir(&app_handle)?;
+    // Event paths are canonical on some platforms (e.g. /private/var on macOS)
+    let app_config_dir = app_config_dir.canonicalize().unwrap_or(app_config_dir);
+
+    let patterns: Vec<String> = files.unwrap_or_else(|| {
+        DEFAULT_WATCHED_FILES.iter().map(|name| name.to_string()).collect()
+    });
+    let recursive_mode = if patterns.iter().any(|pattern| pattern.contains('/')) {
+        RecursiveMode::Recursive
+    } else {
+        RecursiveMode::NonRecursive
+    };
+
     // Create a channel to receive the events
     let (tx, rx) = mpsc::channel();
 
@@ -552,12 +793,12 @@ async fn setup_config_watcher(app_handle: AppHandle) -> Result<(), String> {
         },
         Config::default(),
     )
-    .map_err(|e| format!("Failed to create file watcher: {}", e))?;
+    .map_err(|e| AppError::Io(format!("Failed to create file watcher: {}", e)))?;
 
     // Watch the config directory
     watcher
-        .watch(&app_config_dir, RecursiveMode::NonRecursive)
-        .map_err(|e| format!("Failed to watch config directory: {}", e))?;
+        .watch(&app_config_dir, recursive_mode)
+        .map_err(|e| AppError::Io(format!("Failed to watch config directory: {}", e)))?;
 
     // Spawn a thread to handle file events
     let app_handle_clone = app_handle.clone();
@@ -565,58 +806,389 @@ async fn setup_config_watcher(app_handle: AppHandle) -> Result<(), String> {
         // Keep the watcher alive
         let _watcher = watcher;
 
-        let mut last_config_event = Instant::now();
+        // Each file debounces on its own: it is reported once it has been quiet
+        // for the debounce window, so a burst of writes to one file emits a
+        // single event, and a change to one file never hides another's.
         let debounce_duration = Duration::from_millis(100); // 100ms debounce
+        let mut last_event_times: HashMap<String, Instant> = HashMap::new();
 
-        for event in rx {
-            if let Some(path) = event.paths.first() {
-                let file_name = path.file_name();
-                let is_config_file = file_name == Some(std::ffi::OsStr::new("config.json"))
-                    || file_name == Some(std::ffi::OsStr::new("config.toml"));
+        loop {
+            let next_due = last_event_times
+                .values()
+                .map(|last| (*last + debounce_duration).saturating_duration_since(Instant::now()))
+                .min();
+            let received = match next_due {
+                Some(timeout) => rx.recv_timeout(timeout),
+                None => rx.recv().map_err(|_| mpsc::RecvTimeoutError::Disconnected),
+            };
 
-                if is_config_file {
+            match received {
+                Ok(event) => {
+                    // write_config renames a temp file into place, so a watched
+                    // file may be the rename target rather than the first path
                     let now = Instant::now();
-                    if now.duration_since(last_config_event) > debounce_duration {
-                        last_config_event = now;
-                        if let Err(e) = app_handle_clone.emit("config-file-changed", ()) {
-                            eprintln!("Failed to emit config change event: {}", e);
+                    for path in &event.paths {
+                        let Ok(relative) = path.strip_prefix(&app_config_dir) else {
+                            continue;
+                        };
+                        let relative = relative.to_string_lossy().replace('\\', "/");
+                        if patterns
+                            .iter()
+                            .any(|pattern| watch_pattern_matches(pattern, &relative))
+                        {
+                            last_event_times.insert(relative, now);
                         }
                     }
                 }
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
             }
+
+            let now = Instant::now();
+            last_event_times.retain(|path, last| {
+                if now.duration_since(*last) < debounce_duration {
+                    return true;
+                }
+                let payload = serde_json::json!({ "filename": path });
+                if let Err(e) = app_handle_clone.emit("config-file-changed", payload) {
+                    eprintln!("Failed to emit config change event: {}", e);
+                }
+                false
+            });
         }
     });
 
     Ok(())
 }
 
+// Quiet time after the last write to notes.db before `database-changed` fires;
+// one CLI command can touch the WAL many times
+const DATABASE_DEBOUNCE: Duration = Duration::from_millis(300);
+
+// Set once setup_database_watcher has started, so a second call (e.g. a
+// remounted component) does not add a second watcher
+static DATABASE_WATCHER_STARTED: AtomicBool = AtomicBool::new(false);
+
+// Modification times of the database and its write-ahead log. In WAL mode a
+// write lands in notes.db-wal and only reaches notes.db at a checkpoint.
+fn database_mtimes(db_path: &std::path::Path) -> [Option<std::time::SystemTime>; 2] {
+    let mut wal_path = db_path.as_os_str().to_owned();
+    wal_path.push("-wal");
+    [db_path, std::path::Path::new(&wal_path)]
+        .map(|path| std::fs::metadata(path).and_then(|meta| meta.modified()).ok())
+}
+
+// Watch notes.db and notes.db-wal and emit `database-changed` with the database
+// path once their mtimes change and then stay put for DATABASE_DEBOUNCE, so
+// the GUI can reload after the CLI or another process writes. The GUI's own
+// writes are reported too.
 #[tauri::command]
-async fn get_database_path(app_handle: tauri::AppHandle) -> Result<String, String> {
-    let data_dir = get_data_dir(&app_handle)?;
-    let db_path = data_dir.join("notes.db");
+async fn setup_database_watcher(app_handle: AppHandle) -> Result<(), AppError> {
+    // Claim the watcher before building it, so concurrent calls cannot both start one
+    if DATABASE_WATCHER_STARTED.swap(true, Ordering::SeqCst) {
+        return Ok(());
+    }
+    let result = start_database_watcher(app_handle);
+    if result.is_err() {
+        // Let a later call try again
+        DATABASE_WATCHER_STARTED.store(false, Ordering::SeqCst);
+    }
+    result
+}
+
+// Body of setup_database_watcher: watch the database directory on a thread
+// for the life of the app
+fn start_database_watcher(app_handle: AppHandle) -> Result<(), AppError> {
+    let db_path = get_database_file(&app_handle)?;
+    let db_path = db_path.canonicalize().unwrap_or(db_path);
+    let Some(db_dir) = db_path.parent().map(PathBuf::from) else {
+        return Err(AppError::Config(format!(
+            "Database path has no parent directory: {}",
+            db_path.display()
+        )));
+    };
+    let db_name = db_path.file_name().unwrap_or_default().to_os_string();
+    let mut wal_name = db_name.clone();
+    wal_name.push("-wal");
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = RecommendedWatcher::new(
+        move |res: Result<Event, notify::Error>| match res {
+            Ok(event) => {
+                if let Err(e) = tx.send(event) {
+                    eprintln!("Failed to send file event: {}", e);
+                }
+            }
+            Err(e) => eprintln!("File watch error: {:?}", e),
+        },
+        Config::default(),
+    )
+    .map_err(|e| AppError::Io(format!("Failed to create file watcher: {}", e)))?;
+
+    // The directory, not the files: the WAL comes and goes with checkpoints
+    watcher
+        .watch(&db_dir, RecursiveMode::NonRecursive)
+        .map_err(|e| AppError::Io(format!("Failed to watch database directory: {}", e)))?;
+
+    thread::spawn(move || {
+        // Keep the watcher alive
+        let _watcher = watcher;
+
+        let mut seen = database_mtimes(&db_path);
+        let mut changed_at: Option<Instant> = None;
+        loop {
+            let received = match changed_at {
+                Some(last) => {
+                    rx.recv_timeout((last + DATABASE_DEBOUNCE).saturating_duration_since(Instant::now()))
+                }
+                None => rx.recv().map_err(|_| mpsc::RecvTimeoutError::Disconnected),
+            };
+
+            match received {
+                Ok(event) => {
+                    let touches_database = event.paths.iter().any(|path| {
+                        path.file_name()
+                            .is_some_and(|name| name == db_name || name == wal_name)
+                    });
+                    // Reads and lock traffic raise events too; only a new mtime is a write
+                    let mtimes = database_mtimes(&db_path);
+                    if touches_database && mtimes != seen {
+                        seen = mtimes;
+                        changed_at = Some(Instant::now());
+                    }
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+
+            if changed_at.is_some_and(|last| last.elapsed() >= DATABASE_DEBOUNCE) {
+                changed_at = None;
+                let payload = serde_json::json!({ "path": db_path.to_string_lossy() });
+                if let Err(e) = app_handle.emit("database-changed", payload) {
+                    eprintln!("Failed to emit database change event: {}", e);
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_database_path(app_handle: tauri::AppHandle) -> Result<String, AppError> {
+    let db_path = get_database_file(&app_handle)?;
     Ok(db_path.to_string_lossy().to_string())
 }
 
 #[tauri::command]
-async fn get_app_info(app_handle: tauri::AppHandle) -> Result<serde_json::Value, String> {
+async fn get_app_info(app_handle: tauri::AppHandle) -> Result<serde_json::Value, AppError> {
     let config_dir = get_config_dir(&app_handle)?;
     let data_dir = get_data_dir(&app_handle)?;
+    let db_path = get_database_file(&app_handle)?;
     let is_dev = is_development_mode();
 
     Ok(serde_json::json!({
         "development_mode": is_dev,
         "config_dir": config_dir.to_string_lossy(),
         "data_dir": data_dir.to_string_lossy(),
-        "database_path": data_dir.join("notes.db").to_string_lossy()
+        "database_path": db_path.to_string_lossy()
     }))
 }
 
-fn normalize_image_extension(extension: &str) -> Result<String, String> {
+// Open the notes database for a note command, resolved like everywhere else
+fn open_notes_database(app_handle: &AppHandle) -> Result<rusqlite::Connection, AppError> {
+    let db_path = get_database_file(app_handle)?;
+    open_database(&db_path).map_err(|e| AppError::Db(format!("Failed to open database: {}", e)))
+}
+
+/// Create a note under `parent_id` (the root when unset), after its siblings.
+/// Returns the new note's ID.
+#[tauri::command]
+async fn create_note(
+    app_handle: tauri::AppHandle,
+    title: String,
+    parent_id: Option<String>,
+    content: Option<String>,
+) -> Result<String, AppError> {
+    let conn = open_notes_database(&app_handle)?;
+    notes::create_note(
+        &conn,
+        &title,
+        parent_id.as_deref(),
+        content.as_deref().unwrap_or_default(),
+    )
+}
+
+/// Payload of `note-changed`, emitted to every window after a note command
+/// writes. The quick app runs in its own process and does not receive it.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NoteChanged<'a> {
+    note_id: &'a str,
+    /// "updated", "trashed" or "deleted"
+    change: &'static str,
+}
+
+fn emit_note_changed(app_handle: &AppHandle, note_id: &str, change: &'static str) {
+    if let Err(e) = app_handle.emit("note-changed", NoteChanged { note_id, change }) {
+        eprintln!("Failed to emit note change event: {}", e);
+    }
+}
+
+/// Change a note's title and/or content; omitted fields are left as they are.
+/// Returns the note's ID.
+#[tauri::command]
+async fn update_note(
+    app_handle: tauri::AppHandle,
+    id: String,
+    title: Option<String>,
+    content: Option<String>,
+) -> Result<String, AppError> {
+    let conn = open_notes_database(&app_handle)?;
+    notes::update_note(&conn, &id, title.as_deref(), content.as_deref())?;
+    emit_note_changed(&app_handle, &id, "updated");
+    Ok(id)
+}
+
+/// Move a note to the trash, or delete it permanently with `hard`.
+/// Returns the note's ID.
+#[tauri::command]
+async fn delete_note(
+    app_handle: tauri::AppHandle,
+    id: String,
+    hard: Option<bool>,
+) -> Result<String, AppError> {
+    let hard = hard.unwrap_or(false);
+    let conn = open_notes_database(&app_handle)?;
+    notes::delete_note(&conn, &id, hard)?;
+    emit_note_changed(&app_handle, &id, if hard { "deleted" } else { "trashed" });
+    Ok(id)
+}
+
+/// The sidebar hierarchy as nested `{ id, title, type, children }` objects.
+/// Trashed items are left out unless `include_deleted` is set.
+#[tauri::command]
+async fn get_tree(
+    app_handle: tauri::AppHandle,
+    include_deleted: Option<bool>,
+) -> Result<serde_json::Value, AppError> {
+    let conn = open_notes_database(&app_handle)?;
+    let tree = notes::tree(&conn, include_deleted.unwrap_or(false))?;
+    Ok(serde_json::to_value(tree)?)
+}
+
+/// Move an item under `new_parent_id` (the root when `None`) at `position`
+/// among its new siblings, or last when `position` is omitted. `position`
+/// leaves the moved item out of the count, as `notes::move_item` explains.
+/// Returns the new siblings' IDs and sort keys in order.
+#[tauri::command]
+async fn move_item(
+    app_handle: tauri::AppHandle,
+    id: String,
+    new_parent_id: Option<String>,
+    position: Option<usize>,
+) -> Result<Vec<notes::SiblingOrder>, AppError> {
+    let conn = open_notes_database(&app_handle)?;
+    notes::move_item(&conn, &id, new_parent_id.as_deref(), position)
+}
+
+/// Search notes with the quick app's query syntax and ranking. Results carry
+/// each note's full path; `max_per_book` caps hits from any one book.
+#[tauri::command]
+async fn search_notes(
+    app_handle: tauri::AppHandle,
+    query: String,
+    limit: Option<usize>,
+    max_per_book: Option<usize>,
+) -> Result<Vec<irisnotes_search::SearchResult>, AppError> {
+    let conn = open_notes_database(&app_handle)?;
+    notes::search(&conn, &query, limit, max_per_book)
+}
+
+/// Notes to jump back into, most recently modified first (30 unless `limit` is set)
+#[tauri::command]
+async fn recent_notes(
+    app_handle: tauri::AppHandle,
+    limit: Option<usize>,
+) -> Result<Vec<irisnotes_search::SearchResult>, AppError> {
+    let conn = open_notes_database(&app_handle)?;
+    notes::recent(&conn, limit)
+}
+
+/// A note rendered as "markdown", "html" or "json", for the frontend to save
+/// or copy
+#[tauri::command]
+async fn export_note(
+    app_handle: tauri::AppHandle,
+    id: String,
+    format: export::ExportFormat,
+) -> Result<String, AppError> {
+    let conn = open_notes_database(&app_handle)?;
+    export::export_note(&conn, &id, format)
+}
+
+/// Write an item (everything when `root_id` is omitted) below `out_dir` as
+/// folders of note files. Returns every path written, parents first.
+#[tauri::command]
+async fn export_tree(
+    app_handle: tauri::AppHandle,
+    root_id: Option<String>,
+    format: export::ExportFormat,
+    out_dir: String,
+) -> Result<Vec<String>, AppError> {
+    if out_dir.trim().is_empty() {
+        return Err(AppError::InvalidInput("Choose a folder to export to".into()));
+    }
+    let conn = open_notes_database(&app_handle)?;
+    let summary = export::export_tree(
+        &conn,
+        root_id.as_deref(),
+        format,
+        std::path::Path::new(&out_dir),
+        export::default_jobs(),
+    )?;
+    Ok(summary
+        .paths
+        .iter()
+        .map(|path| path.to_string_lossy().to_string())
+        .collect())
+}
+
+/// Import Markdown files, and the contents of folders, picked in the file
+/// dialog under `parent_id` (the root when omitted). Emits one `tree-changed`
+/// event with the summary once everything is committed.
+#[tauri::command]
+async fn import_markdown(
+    app_handle: tauri::AppHandle,
+    paths: Vec<String>,
+    parent_id: Option<String>,
+) -> Result<import::ImportSummary, AppError> {
+    if paths.is_empty() {
+        return Err(AppError::InvalidInput("No files to import".into()));
+    }
+    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+    let conn = open_notes_database(&app_handle)?;
+    let summary = import::import_paths(&conn, &paths, parent_id.as_deref())?;
+    if let Err(e) = app_handle.emit("tree-changed", &summary) {
+        eprintln!("Failed to emit tree change event: {}", e);
+    }
+    Ok(summary)
+}
+
+/// One live note by ID with its path and tags, for opening it from
+/// `--open-note` or a deep link
+#[tauri::command]
+async fn get_note(app_handle: tauri::AppHandle, id: String) -> Result<notes::NoteDto, AppError> {
+    let conn = open_notes_database(&app_handle)?;
+    notes::get_note(&conn, &id)
+}
+
+fn normalize_image_extension(extension: &str) -> Result<String, AppError> {
     let ext = extension.trim().trim_start_matches('.').to_lowercase();
     if ALLOWED_IMAGE_EXTENSIONS.contains(&ext.as_str()) {
         Ok(ext)
     } else {
-        Err(format!("Unsupported image extension: {}", ext))
+        Err(AppError::InvalidInput(format!("Unsupported image extension: {}", ext)))
     }
 }
 
@@ -699,12 +1271,12 @@ fn write_image_asset(
     app_handle: &AppHandle,
     data: &[u8],
     extension: &str,
-) -> Result<String, String> {
+) -> Result<String, AppError> {
     let ext = normalize_image_extension(extension)?;
     let data_dir = get_data_dir(app_handle)?;
     let assets_dir = data_dir.join("assets");
     std::fs::create_dir_all(&assets_dir)
-        .map_err(|e| format!("Failed to create assets directory: {}", e))?;
+        .map_err(|e| AppError::Io(format!("Failed to create assets directory: {}", e)))?;
 
     let filename = format!(
         "{:x}.{}",
@@ -716,18 +1288,19 @@ fn write_image_asset(
     );
     let file_path = assets_dir.join(&filename);
 
-    std::fs::write(&file_path, data).map_err(|e| format!("Failed to write image: {}", e))?;
+    std::fs::write(&file_path, data)
+        .map_err(|e| AppError::Io(format!("Failed to write image: {}", e)))?;
 
     Ok(filename)
 }
 
 /// Get the assets directory path
 #[tauri::command]
-async fn get_assets_dir(app_handle: tauri::AppHandle) -> Result<String, String> {
+async fn get_assets_dir(app_handle: tauri::AppHandle) -> Result<String, AppError> {
     let data_dir = get_data_dir(&app_handle)?;
     let assets_dir = data_dir.join("assets");
     std::fs::create_dir_all(&assets_dir)
-        .map_err(|e| format!("Failed to create assets directory: {}", e))?;
+        .map_err(|e| AppError::Io(format!("Failed to create assets directory: {}", e)))?;
     Ok(assets_dir.to_string_lossy().to_string())
 }
 
@@ -738,7 +1311,7 @@ async fn save_image_asset(
     app_handle: tauri::AppHandle,
     data: Vec<u8>,
     extension: String,
-) -> Result<String, String> {
+) -> Result<String, AppError> {
     write_image_asset(&app_handle, &data, &extension)
 }
 
@@ -747,34 +1320,38 @@ async fn save_image_asset(
 async fn import_remote_image_asset(
     app_handle: tauri::AppHandle,
     url: String,
-) -> Result<String, String> {
+) -> Result<String, AppError> {
     let lower_url = url.to_ascii_lowercase();
     if !lower_url.starts_with("https://") && !lower_url.starts_with("http://") {
-        return Err("Only http and https image URLs can be imported".to_string());
+        return Err(AppError::InvalidInput("Only http and https image URLs can be imported".into()));
     }
 
     let app_handle_for_task = app_handle.clone();
-    tauri::async_runtime::spawn_blocking(move || -> Result<String, String> {
+    tauri::async_runtime::spawn_blocking(move || -> Result<String, AppError> {
         let client = reqwest::blocking::Client::builder()
             .timeout(Duration::from_secs(30))
             .redirect(reqwest::redirect::Policy::limited(5))
             .user_agent("IrisNotes/1.0")
             .build()
-            .map_err(|err| format!("Failed to create image download client: {}", err))?;
+            .map_err(|err| {
+                AppError::Network(format!("Failed to create image download client: {}", err))
+            })?;
 
         let response = client
             .get(&url)
             .send()
-            .map_err(|err| format!("Failed to download image: {}", err))?;
+            .map_err(|err| AppError::Network(format!("Failed to download image: {}", err)))?;
 
         let status = response.status();
         if !status.is_success() {
-            return Err(format!("Image download failed with status {}", status));
+            return Err(AppError::Network(format!("Image download failed with status {}", status)));
         }
 
         if let Some(content_length) = response.content_length() {
             if content_length > MAX_IMAGE_ASSET_BYTES {
-                return Err("Image is larger than the 20 MB import limit".to_string());
+                return Err(AppError::InvalidInput(
+                    "Image is larger than the 20 MB import limit".into(),
+                ));
             }
         }
 
@@ -797,38 +1374,40 @@ async fn import_remote_image_asset(
             && content_type_base != "application/octet-stream"
             && content_type_base != "binary/octet-stream"
         {
-            return Err(format!(
+            return Err(AppError::InvalidInput(format!(
                 "Remote URL did not return an image: {}",
                 content_type_base
-            ));
+            )));
         }
 
         let ext = image_extension_from_content_type(&content_type)
             .map(str::to_string)
             .or_else(|| image_extension_from_path_like(&url))
-            .ok_or_else(|| "Remote image type is not supported".to_string())?;
+            .ok_or_else(|| AppError::InvalidInput("Remote image type is not supported".into()))?;
 
         let bytes = response
             .bytes()
-            .map_err(|err| format!("Failed to read downloaded image: {}", err))?;
+            .map_err(|err| AppError::Network(format!("Failed to read downloaded image: {}", err)))?;
 
         if bytes.len() as u64 > MAX_IMAGE_ASSET_BYTES {
-            return Err("Image is larger than the 20 MB import limit".to_string());
+            return Err(AppError::InvalidInput(
+                "Image is larger than the 20 MB import limit".into(),
+            ));
         }
 
         write_image_asset(&app_handle_for_task, bytes.as_ref(), &ext)
     })
     .await
-    .map_err(|err| format!("Image import task failed: {}", err))?
+    .map_err(|err| AppError::Other(format!("Image import task failed: {}", err)))?
 }
 
 /// Open the assets directory in the system file manager.
 /// On macOS, reveals the specific file. On Linux/Windows, opens the directory.
 #[tauri::command]
-async fn reveal_asset(app_handle: tauri::AppHandle, filename: String) -> Result<(), String> {
+async fn reveal_asset(app_handle: tauri::AppHandle, filename: String) -> Result<(), AppError> {
     // Sanitise: reject path traversal
     if filename.contains("..") || filename.contains('/') || filename.contains('\\') {
-        return Err("Invalid filename".to_string());
+        return Err(AppError::InvalidInput("Invalid filename".into()));
     }
     let data_dir = get_data_dir(&app_handle)?;
     let assets_dir = data_dir.join("assets");
@@ -855,7 +1434,7 @@ async fn reveal_asset(app_handle: tauri::AppHandle, filename: String) -> Result<
         std::process::Command::new("xdg-open")
             .arg(&assets_dir)
             .spawn()
-            .map_err(|e| format!("Failed to open directory: {}", e))?;
+            .map_err(|e| AppError::Io(format!("Failed to open directory: {}", e)))?;
     }
 
     #[cfg(target_os = "macos")]
@@ -864,7 +1443,7 @@ async fn reveal_asset(app_handle: tauri::AppHandle, filename: String) -> Result<
             .arg("-R")
             .arg(&file_path)
             .spawn()
-            .map_err(|e| format!("Failed to reveal file: {}", e))?;
+            .map_err(|e| AppError::Io(format!("Failed to reveal file: {}", e)))?;
     }
 
     #[cfg(target_os = "windows")]
@@ -873,7 +1452,7 @@ async fn reveal_asset(app_handle: tauri::AppHandle, filename: String) -> Result<
             .arg("/select,")
             .arg(&file_path)
             .spawn()
-            .map_err(|e| format!("Failed to reveal file: {}", e))?;
+            .map_err(|e| AppError::Io(format!("Failed to reveal file: {}", e)))?;
     }
 
     Ok(())
@@ -882,7 +1461,7 @@ async fn reveal_asset(app_handle: tauri::AppHandle, filename: String) -> Result<
 /// Read an image file from an arbitrary path and return its bytes.
 /// Only allows known image extensions.
 #[tauri::command]
-async fn read_image_file(path: String) -> Result<Vec<u8>, String> {
+async fn read_image_file(path: String) -> Result<Vec<u8>, AppError> {
     let p = std::path::Path::new(&path);
     let ext = p.extension()
         .and_then(|e| e.to_str())
@@ -890,21 +1469,20 @@ async fn read_image_file(path: String) -> Result<Vec<u8>, String> {
         .unwrap_or_default();
     let allowed = ["png", "jpg", "jpeg", "gif", "webp", "svg", "bmp", "ico"];
     if !allowed.contains(&ext.as_str()) {
-        return Err(format!("Not a supported image type: {}", ext));
+        return Err(AppError::InvalidInput(format!("Not a supported image type: {}", ext)));
     }
     std::fs::read(&path)
-        .map_err(|e| format!("Failed to read image file: {}", e))
+        .map_err(|e| AppError::Io(format!("Failed to read image file: {}", e)))
 }
 
 /// Remove asset files not referenced by any note's content.
 /// Returns the number of deleted files.
 #[tauri::command]
-async fn cleanup_orphaned_assets(app_handle: tauri::AppHandle) -> Result<u32, String> {
-    use rusqlite::Connection;
+async fn cleanup_orphaned_assets(app_handle: tauri::AppHandle) -> Result<u32, AppError> {
     use std::collections::HashSet;
 
     let data_dir = get_data_dir(&app_handle)?;
-    let db_path = data_dir.join("notes.db");
+    let db_path = get_database_file(&app_handle)?;
     let assets_dir = data_dir.join("assets");
 
     if !assets_dir.exists() {
@@ -912,17 +1490,17 @@ async fn cleanup_orphaned_assets(app_handle: tauri::AppHandle) -> Result<u32, St
     }
 
     // 1. Collect all asset filenames referenced in any note's content
-    let conn = Connection::open(&db_path)
-        .map_err(|e| format!("Failed to open database: {}", e))?;
+    let conn = open_database(&db_path)
+        .map_err(|e| AppError::Db(format!("Failed to open database: {}", e)))?;
 
     let mut stmt = conn
         .prepare("SELECT content FROM items WHERE type = 'note' AND content IS NOT NULL")
-        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+        .map_err(|e| AppError::Db(format!("Failed to prepare query: {}", e)))?;
 
     let mut referenced: HashSet<String> = HashSet::new();
     let rows = stmt
         .query_map([], |row| row.get::<_, String>(0))
-        .map_err(|e| format!("Failed to query notes: {}", e))?;
+        .map_err(|e| AppError::Db(format!("Failed to query notes: {}", e)))?;
 
     for row in rows {
         if let Ok(content) = row {
@@ -948,10 +1526,11 @@ async fn cleanup_orphaned_assets(app_handle: tauri::AppHandle) -> Result<u32, St
     // 2. Walk the assets directory and delete files not in the referenced set
     let mut deleted = 0u32;
     let entries = std::fs::read_dir(&assets_dir)
-        .map_err(|e| format!("Failed to read assets directory: {}", e))?;
+        .map_err(|e| AppError::Io(format!("Failed to read assets directory: {}", e)))?;
 
     for entry in entries {
-        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+        let entry = entry
+            .map_err(|e| AppError::Io(format!("Failed to read directory entry: {}", e)))?;
         let path = entry.path();
         if !path.is_file() {
             continue;
@@ -973,18 +1552,22 @@ async fn cleanup_orphaned_assets(app_handle: tauri::AppHandle) -> Result<u32, St
 /// and installs them to ~/.local/share/icons/hicolor/.
 /// The `icon_name` is typically "irisnotes" or "irisnotes-dev".
 #[tauri::command]
-async fn install_icon_to_hicolor(svg_content: String, icon_name: String) -> Result<String, String> {
+async fn install_icon_to_hicolor(svg_content: String, icon_name: String) -> Result<String, AppError> {
     use std::process::Command;
 
     // Validate icon_name (only allow safe characters)
     if !icon_name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
-        return Err("Invalid icon name: only alphanumeric, dash, underscore allowed".to_string());
+        return Err(AppError::InvalidInput(
+            "Invalid icon name: only alphanumeric, dash, underscore allowed".into(),
+        ));
     }
 
     // Check rsvg-convert is available
     let has_rsvg = Command::new("rsvg-convert").arg("--version").output().is_ok();
     if !has_rsvg {
-        return Err("rsvg-convert not found. Install librsvg (pacman -S librsvg) for icon generation.".to_string());
+        return Err(AppError::NotFound(
+            "rsvg-convert not found. Install librsvg (pacman -S librsvg) for icon generation.".into(),
+        ));
     }
 
     let home = dirs::home_dir().ok_or("Failed to get home directory")?;
@@ -996,13 +1579,13 @@ async fn install_icon_to_hicolor(svg_content: String, icon_name: String) -> Resu
     // Write SVG to a temp file for rsvg-convert
     let tmp_svg = std::env::temp_dir().join(format!("{}_custom.svg", icon_name));
     std::fs::write(&tmp_svg, &svg_content)
-        .map_err(|e| format!("Failed to write temp SVG: {}", e))?;
+        .map_err(|e| AppError::Io(format!("Failed to write temp SVG: {}", e)))?;
 
     // Generate and install PNGs at each size
     for &size in sizes {
         let dir = hicolor.join(format!("{}x{}/apps", size, size));
         std::fs::create_dir_all(&dir)
-            .map_err(|e| format!("Failed to create dir {}: {}", dir.display(), e))?;
+            .map_err(|e| AppError::Io(format!("Failed to create dir {}: {}", dir.display(), e)))?;
         let out_path = dir.join(format!("{}.png", icon_name));
 
         let output = Command::new("rsvg-convert")
@@ -1014,11 +1597,11 @@ async fn install_icon_to_hicolor(svg_content: String, icon_name: String) -> Resu
                 "-o", &out_path.to_string_lossy().to_string(),
             ])
             .output()
-            .map_err(|e| format!("rsvg-convert failed for {}px: {}", size, e))?;
+            .map_err(|e| AppError::Io(format!("rsvg-convert failed for {}px: {}", size, e)))?;
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(format!("rsvg-convert failed for {}px: {}", size, stderr));
+            return Err(AppError::Io(format!("rsvg-convert failed for {}px: {}", size, stderr)));
         }
         installed.push(format!("{}x{}", size, size));
     }
@@ -1026,9 +1609,9 @@ async fn install_icon_to_hicolor(svg_content: String, icon_name: String) -> Resu
     // Install SVG to scalable/
     let scalable_dir = hicolor.join("scalable/apps");
     std::fs::create_dir_all(&scalable_dir)
-        .map_err(|e| format!("Failed to create scalable dir: {}", e))?;
+        .map_err(|e| AppError::Io(format!("Failed to create scalable dir: {}", e)))?;
     std::fs::copy(&tmp_svg, scalable_dir.join(format!("{}.svg", icon_name)))
-        .map_err(|e| format!("Failed to copy SVG: {}", e))?;
+        .map_err(|e| AppError::Io(format!("Failed to copy SVG: {}", e)))?;
     installed.push("scalable".to_string());
 
     // Create index.theme if missing (required for gtk-update-icon-cache)
@@ -1049,11 +1632,11 @@ async fn install_icon_to_hicolor(svg_content: String, icon_name: String) -> Resu
 /// Save a custom SVG icon for use as the systray icon (quick app).
 /// Writes SVG to the config/dev directory where the quick app reads it from.
 #[tauri::command]
-async fn save_custom_tray_svg(app_handle: tauri::AppHandle, svg_content: String) -> Result<String, String> {
+async fn save_custom_tray_svg(app_handle: tauri::AppHandle, svg_content: String) -> Result<String, AppError> {
     let config_dir = get_config_dir(&app_handle)?;
     let svg_path = config_dir.join("quick-tray-icon.svg");
     std::fs::write(&svg_path, &svg_content)
-        .map_err(|e| format!("Failed to write tray SVG: {}", e))?;
+        .map_err(|e| AppError::Io(format!("Failed to write tray SVG: {}", e)))?;
 
     // Also generate PNG version via rsvg-convert (tray icons often need raster)
     let png_path = config_dir.join("quick-tray-icon.png");
@@ -1069,14 +1652,14 @@ async fn save_custom_tray_svg(app_handle: tauri::AppHandle, svg_content: String)
 /// Save a custom SVG as the in-app logo asset.
 /// Writes to the app's assets dir as custom-logo.svg.
 #[tauri::command]
-async fn save_custom_app_logo(app_handle: tauri::AppHandle, svg_content: String) -> Result<String, String> {
+async fn save_custom_app_logo(app_handle: tauri::AppHandle, svg_content: String) -> Result<String, AppError> {
     let data_dir = get_data_dir(&app_handle)?;
     let assets_dir = data_dir.join("assets");
     std::fs::create_dir_all(&assets_dir)
-        .map_err(|e| format!("Failed to create assets dir: {}", e))?;
+        .map_err(|e| AppError::Io(format!("Failed to create assets dir: {}", e)))?;
     let svg_path = assets_dir.join("custom-logo.svg");
     std::fs::write(&svg_path, &svg_content)
-        .map_err(|e| format!("Failed to write logo SVG: {}", e))?;
+        .map_err(|e| AppError::Io(format!("Failed to write logo SVG: {}", e)))?;
     Ok("custom-logo.svg".to_string())
 }
 
@@ -1096,22 +1679,22 @@ pub fn run() {
 
     tauri::Builder::default()
         .plugin(tauri_plugin_single_instance::init(|app, args, _cwd| {
-            // Another instance tried to start - check for --open-note argument
-            for arg in &args {
-                if let Some(note_id) = arg.strip_prefix("--open-note=") {
-                    // Emit to the specific window
-                    if let Some(window) = app.get_webview_window("main") {
-                        let _ = window.emit("open-note-from-quick", note_id.to_string());
-                    }
+            // Another instance tried to start - check for --open-note or a deep link
+            if let Some(request) = OpenNoteRequest::from_args(&args) {
+                // Emit to the specific window
+                if let Some(window) = app.get_webview_window("main") {
+                    let _ = window.emit("open-note-from-quick", request);
                 }
             }
 
-            // Focus our window
+            // Focus our window, even if it was minimized or hidden to the tray
             if let Some(window) = app.get_webview_window("main") {
+                let _ = window.unminimize();
                 let _ = window.show();
                 let _ = window.set_focus();
             }
         }))
+        .plugin(tauri_plugin_deep_link::init())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_sql::Builder::default().build())
@@ -1134,17 +1717,7 @@ pub fn run() {
             }
 
             // Resolve assets dir
-            let data_dir = if is_development_mode() {
-                let exe_path = std::env::current_exe().unwrap_or_default();
-                let mut root = exe_path.parent().unwrap_or(std::path::Path::new(".")).to_path_buf();
-                loop {
-                    if root.join("pnpm-workspace.yaml").exists() { break; }
-                    if !root.pop() { root = std::env::current_dir().unwrap_or_default(); break; }
-                }
-                root.join("dev")
-            } else {
-                dirs::config_dir().unwrap_or_default().join("irisnotes")
-            };
+            let data_dir = irisnotes_paths::data_dir().unwrap_or_default();
             let file_path = data_dir.join("assets").join(path_str);
 
             match std::fs::read(&file_path) {
@@ -1172,6 +1745,12 @@ pub fn run() {
                 eprintln!("Warning: Failed to initialize database: {}", e);
             }
 
+            match get_config_dir(app.handle()).and_then(|dir| migrate_json_config(&dir)) {
+                Ok(true) => println!("Migrated config.json to config.toml (old file kept as config.json.bak)"),
+                Ok(false) => {}
+                Err(e) => eprintln!("Warning: Failed to migrate config.json: {}", e),
+            }
+
             if let Some(window) = app.get_webview_window("main") {
                 if let Ok(state_path) = window_state_file_path(app.handle()) {
                     if let Some(state) = load_saved_window_state(&state_path) {
@@ -1190,20 +1769,43 @@ pub fn run() {
                 }
             }
 
-            // Check for --open-note argument on startup (from quick app launching us)
+            // Installed bundles register the URL scheme; Linux and dev builds must do it at runtime
+            #[cfg(any(target_os = "linux", all(debug_assertions, windows)))]
+            {
+                use tauri_plugin_deep_link::DeepLinkExt;
+                if let Err(e) = app.deep_link().register_all() {
+                    eprintln!("Failed to register {}:// links: {}", DEEP_LINK_SCHEME, e);
+                }
+            }
+
+            // macOS delivers deep links to the running app as events rather than arguments
+            #[cfg(target_os = "macos")]
+            {
+                use tauri_plugin_deep_link::DeepLinkExt;
+                let app_handle = app.handle().clone();
+                app.deep_link().on_open_url(move |event| {
+                    let request = event
+                        .urls()
+                        .iter()
+                        .find_map(|url| OpenNoteRequest::from_url(url.as_str()));
+                    if let (Some(request), Some(window)) = (request, app_handle.get_webview_window("main")) {
+                        let _ = window.emit("open-note-from-quick", request);
+                        let _ = window.show();
+                        let _ = window.set_focus();
+                    }
+                });
+            }
+
+            // Check for --open-note or a deep link on startup (from the quick app or a link)
             let args: Vec<String> = std::env::args().collect();
 
-            for arg in &args {
-                if let Some(note_id) = arg.strip_prefix("--open-note=") {
-                    let note_id = note_id.to_string();
-                    let app_handle = app.handle().clone();
-                    // Emit event after a short delay to let frontend initialize
-                    std::thread::spawn(move || {
-                        std::thread::sleep(std::time::Duration::from_millis(500));
-                        let _ = app_handle.emit("open-note-from-quick", note_id);
-                    });
-                    break;
-                }
+            if let Some(request) = OpenNoteRequest::from_args(&args) {
+                let app_handle = app.handle().clone();
+                // Emit event after a short delay to let frontend initialize
+                std::thread::spawn(move || {
+                    std::thread::sleep(std::time::Duration::from_millis(500));
+                    let _ = app_handle.emit("open-note-from-quick", request);
+                });
             }
             Ok(())
         })
@@ -1211,10 +1813,25 @@ pub fn run() {
             greet,
             read_config,
             write_config,
+            validate_config,
+            reset_config,
+            get_default_config,
             setup_config_watcher,
+            setup_database_watcher,
             open_app_config_folder,
             get_database_path,
             get_app_info,
+            get_note,
+            create_note,
+            update_note,
+            delete_note,
+            get_tree,
+            move_item,
+            search_notes,
+            recent_notes,
+            export_note,
+            export_tree,
+            import_markdown,
             get_assets_dir,
             save_image_asset,
             import_remote_image_asset,
@@ -1224,6 +1841,7 @@ pub fn run() {
             read_clipboard_target,
             read_clipboard_binary_target,
             list_clipboard_targets,
+            sanitize_html,
             read_vscode_editor_data,
             install_icon_to_hicolor,
             save_custom_tray_svg,
@@ -1243,3 +1861,203 @@ pub fn run() {
             }
         });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    /// Names in `dir` that look like `write_atomic` temp files and are files
+    fn leftover_temp_files(dir: &std::path::Path) -> Vec<String> {
+        std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap())
+            .filter(|entry| entry.file_type().unwrap().is_file())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .filter(|name| name.ends_with(".tmp"))
+            .collect()
+    }
+
+    #[test]
+    fn write_atomic_replaces_the_file() {
+        let dir = TempDir::new();
+        let path = dir.0.join("config.toml");
+        std::fs::write(&path, "old = 1\n").unwrap();
+
+        write_atomic(&path, "new = 2\n").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new = 2\n");
+        assert!(leftover_temp_files(&dir.0).is_empty());
+    }
+
+    #[test]
+    fn write_atomic_keeps_the_original_when_the_temp_file_cannot_be_created() {
+        let dir = TempDir::new();
+        let path = dir.0.join("config.toml");
+        std::fs::write(&path, "old = 1\n").unwrap();
+        // Directories squatting every temp name this process will try next
+        // (the counter is shared with other tests, hence the range)
+        for n in 0..256 {
+            let squatter = format!(".config.toml.{}.{}.tmp", std::process::id(), n);
+            std::fs::create_dir(dir.0.join(squatter)).unwrap();
+        }
+
+        assert!(write_atomic(&path, "new = 2\n").is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "old = 1\n");
+        assert!(leftover_temp_files(&dir.0).is_empty());
+    }
+
+    #[test]
+    fn write_atomic_removes_the_temp_file_when_the_rename_fails() {
+        let dir = TempDir::new();
+        let path = dir.0.join("config.toml");
+        // A non-empty directory in the way makes the rename fail after the
+        // temp file has been written
+        std::fs::create_dir(&path).unwrap();
+        std::fs::write(path.join("keep"), "x").unwrap();
+
+        assert!(write_atomic(&path, "new = 2\n").is_err());
+        assert_eq!(std::fs::read_to_string(path.join("keep")).unwrap(), "x");
+        assert!(leftover_temp_files(&dir.0).is_empty());
+    }
+
+    #[test]
+    fn readers_are_not_blocked_by_an_open_write_transaction() {
+        let dir = TempDir::new();
+        let path = dir.0.join("notes.db");
+        let writer = open_database(&path).unwrap();
+        writer.execute_batch(DATABASE_SCHEMA).unwrap();
+        writer
+            .execute("INSERT INTO items (id, type, title, sort_order) VALUES ('a', 'note', 'Before', 'a')", [])
+            .unwrap();
+
+        // The writer holds the write lock until the end of the test
+        writer.execute_batch("BEGIN IMMEDIATE").unwrap();
+        writer.execute("UPDATE items SET title = 'After' WHERE id = 'a'", []).unwrap();
+
+        let reader = open_database(&path).unwrap();
+        let mode: String = reader.query_row("PRAGMA journal_mode", [], |row| row.get(0)).unwrap();
+        assert_eq!(mode, "wal");
+        // Fail at once on a lock instead of waiting out BUSY_TIMEOUT
+        reader.busy_timeout(Duration::ZERO).unwrap();
+        let title: String = reader
+            .query_row("SELECT title FROM items WHERE id = 'a'", [], |row| row.get(0))
+            .unwrap();
+        // WAL gives the reader the last committed state without waiting
+        assert_eq!(title, "Before");
+
+        writer.execute_batch("COMMIT").unwrap();
+    }
+
+    #[test]
+    fn open_note_request_reads_note_deep_links() {
+        let request = OpenNoteRequest::from_url("irisnotes://note/abc-123?highlight=caf%C3%A9").unwrap();
+        assert_eq!(request.note_id, "abc-123");
+        assert_eq!(request.highlight.as_deref(), Some("café"));
+
+        let request = OpenNoteRequest::from_url("irisnotes://note/abc/?highlight=").unwrap();
+        assert_eq!(request.note_id, "abc");
+        assert_eq!(request.highlight, None);
+
+        for url in [
+            "https://note/abc",
+            "irisnotes://book/abc",
+            "irisnotes://note/",
+            "irisnotes://note/a/b",
+            "not a url",
+        ] {
+            assert!(OpenNoteRequest::from_url(url).is_none(), "{}", url);
+        }
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn open_note_request_reads_flags_or_a_deep_link_argument() {
+        let request =
+            OpenNoteRequest::from_args(&args(&["irisnotes", "--open-note", "n1", "--highlight=soup"]))
+                .unwrap();
+        assert_eq!(request.note_id, "n1");
+        assert_eq!(request.highlight.as_deref(), Some("soup"));
+
+        let request = OpenNoteRequest::from_args(&args(&["irisnotes", "irisnotes://note/n2"])).unwrap();
+        assert_eq!(request.note_id, "n2");
+
+        assert!(OpenNoteRequest::from_args(&args(&["irisnotes", "--open-note="])).is_none());
+        assert!(OpenNoteRequest::from_args(&args(&["irisnotes", "--highlight", "x"])).is_none());
+    }
+
+    #[test]
+    fn flag_value_takes_the_next_argument_or_an_equals_value() {
+        let argv = args(&["app", "--open-note", "n1", "--highlight=a=b", "--last"]);
+        assert_eq!(flag_value(&argv, "--open-note"), Some("n1"));
+        assert_eq!(flag_value(&argv, "--highlight"), Some("a=b"));
+        // Missing values, and a following flag, are not taken as the value
+        assert_eq!(flag_value(&argv, "--last"), None);
+        assert_eq!(flag_value(&args(&["--open-note", "--highlight"]), "--open-note"), None);
+        // A longer flag sharing the prefix is a different flag
+        assert_eq!(flag_value(&args(&["--open-notes", "x"]), "--open-note"), None);
+    }
+
+    #[test]
+    fn json_to_toml_drops_nulls() {
+        let toml = json_to_toml(
+            r#"{"theme": "light", "sync": null, "editor": {"lineWrapping": true, "pasteSanitizer": null},
+                "hotkeys": {"list": [{"key": "k", "when": null}]}}"#,
+        )
+        .unwrap();
+        let value: toml::Table = toml.parse().unwrap();
+        assert_eq!(value["theme"].as_str(), Some("light"));
+        assert!(!value.contains_key("sync"));
+        assert_eq!(value["editor"].as_table().unwrap().len(), 1);
+        let list = value["hotkeys"]["list"].as_array().unwrap();
+        assert_eq!(list[0].as_table().unwrap().keys().collect::<Vec<_>>(), ["key"]);
+
+        assert!(json_to_toml("{not json").is_err());
+        // TOML documents are tables
+        assert!(json_to_toml("[1, 2]").is_err());
+    }
+
+    #[test]
+    fn migrate_json_config_writes_toml_and_keeps_a_backup() {
+        let dir = TempDir::new();
+        std::fs::write(dir.0.join("config.json"), r#"{"theme": "light"}"#).unwrap();
+
+        assert!(migrate_json_config(&dir.0).unwrap());
+        let toml = std::fs::read_to_string(dir.0.join("config.toml")).unwrap();
+        assert_eq!(toml.trim(), r#"theme = "light""#);
+        assert!(!dir.0.join("config.json").exists());
+        assert!(dir.0.join("config.json.bak").exists());
+
+        // Once config.toml exists, a config.json is left alone
+        std::fs::write(dir.0.join("config.json"), "{}").unwrap();
+        assert!(!migrate_json_config(&dir.0).unwrap());
+        assert!(dir.0.join("config.json").exists());
+    }
+
+    #[test]
+    fn migrate_json_config_leaves_nothing_behind_on_bad_json() {
+        let dir = TempDir::new();
+        std::fs::write(dir.0.join("config.json"), "{").unwrap();
+
+        assert!(migrate_json_config(&dir.0).is_err());
+        assert!(!dir.0.join("config.toml").exists());
+        assert!(dir.0.join("config.json").exists());
+        assert!(!migrate_json_config(&TempDir::new().0).unwrap());
+    }
+
+    #[test]
+    fn watch_patterns_match_names_and_globs_within_one_directory() {
+        assert!(watch_pattern_matches("config.toml", "config.toml"));
+        assert!(!watch_pattern_matches("config.toml", "config.toml.bak"));
+        assert!(watch_pattern_matches("themes/*.toml", "themes/dark.toml"));
+        assert!(!watch_pattern_matches("themes/*.toml", "themes/old/dark.toml"));
+        assert!(!watch_pattern_matches("*.toml", "themes/dark.toml"));
+        assert!(watch_pattern_matches("*", ""));
+        assert!(watch_pattern_matches("hotkeys.?son", "hotkeys.json"));
+        assert!(!watch_pattern_matches("a?b", "a/b"));
+        assert!(watch_pattern_matches("*é*", "café.toml"));
+    }
+
+}
@@ -10,9 +10,10 @@ fn main() {
     // If --open-note is provided, start the GUI (Tauri handles this argument)
     // If a CLI command was provided, run it and exit
     if cli.open_note.is_none() && cli.command.is_some() {
+        let json = cli.json;
         if let Err(e) = irisnotes_lib::cli::run_cli(cli) {
-            if e != "no-command" {
-                eprintln!("Error: {}", e);
+            if e.kind != irisnotes_lib::cli::ErrorKind::NoCommand {
+                e.report(json);
                 std::process::exit(1);
             }
         } else {
@@ -0,0 +1,818 @@
+//! Markdown conversion for exports and imports
+//!
+//! `html_to_markdown` handles the subset of HTML the editor produces:
+//! headings, paragraphs, bold, italic, strikethrough, links, line breaks,
+//! (nested) lists, blockquotes, inline code and code blocks. Anything else is
+//! unwrapped to its text, so nothing is silently lost. It is the one converter
+//! behind every Markdown output (`show --markdown` and exports); plain-text
+//! output uses `irisnotes_text::strip_html` instead.
+//!
+//! `markdown_to_html` is the minimal reverse used by `import`: the same
+//! constructs plus blockquotes and code. Raw HTML in the source is escaped,
+//! not passed through.
+
+use irisnotes_text::decode_entities;
+
+/// A piece of tokenized HTML
+#[derive(Debug)]
+enum Token<'a> {
+    Open { name: String, attrs: &'a str },
+    Close(String),
+    Text(&'a str),
+}
+
+/// Split HTML into tags and text. Comments and doctypes are dropped; quoted
+/// attribute values may contain `>`.
+fn tokenize(html: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+
+    while pos < html.len() {
+        let rest = &html[pos..];
+        if let Some(after) = rest.strip_prefix("<!--") {
+            pos += 4 + after.find("-->").map_or(after.len(), |i| i + 3);
+            continue;
+        }
+        // A `<` that cannot start a tag (`a <= b`) is literal text
+        let starts_tag = rest.starts_with('<')
+            && rest[1..].starts_with(|c: char| c.is_ascii_alphabetic() || "/!?".contains(c));
+        if !starts_tag {
+            let first = rest.chars().next().map_or(1, char::len_utf8);
+            let end = rest[first..].find('<').map_or(rest.len(), |i| i + first);
+            tokens.push(Token::Text(&rest[..end]));
+            pos += end;
+            continue;
+        }
+
+        let mut quote = None;
+        let mut end = None;
+        for (i, c) in rest.char_indices().skip(1) {
+            match (quote, c) {
+                (None, '"' | '\'') => quote = Some(c),
+                (Some(q), _) if c == q => quote = None,
+                (None, '>') => {
+                    end = Some(i);
+                    break;
+                }
+                _ => {}
+            }
+        }
+        let Some(end) = end else {
+            // Unterminated tag: treat the rest as text
+            tokens.push(Token::Text(rest));
+            break;
+        };
+
+        let inner = &rest[1..end];
+        pos += end + 1;
+        if let Some(name) = inner.strip_prefix('/') {
+            tokens.push(Token::Close(name.trim().to_ascii_lowercase()));
+        } else if !inner.starts_with('!') && !inner.starts_with('?') {
+            let inner = inner.strip_suffix('/').unwrap_or(inner);
+            let name_end = inner
+                .find(|c: char| c.is_whitespace())
+                .unwrap_or(inner.len());
+            tokens.push(Token::Open {
+                name: inner[..name_end].to_ascii_lowercase(),
+                attrs: &inner[name_end..],
+            });
+        }
+    }
+    tokens
+}
+
+/// Value of attribute `name`, entity-decoded
+fn attribute(attrs: &str, name: &str) -> Option<String> {
+    let lower = attrs.to_ascii_lowercase();
+    let mut search = 0;
+    while let Some(found) = lower[search..].find(name) {
+        let start = search + found;
+        search = start + name.len();
+        let preceded_ok = start == 0 || lower.as_bytes()[start - 1].is_ascii_whitespace();
+        let rest = attrs[search..].trim_start();
+        let Some(value) = rest.strip_prefix('=').filter(|_| preceded_ok) else {
+            continue;
+        };
+        let value = value.trim_start();
+        let raw = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let inner = &value[1..];
+                &inner[..inner.find(quote).unwrap_or(inner.len())]
+            }
+            _ => &value[..value.find(char::is_whitespace).unwrap_or(value.len())],
+        };
+        return Some(decode_entities(raw));
+    }
+    None
+}
+
+/// Escape characters that would otherwise start Markdown formatting
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '_' | '`' | '[' | ']') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// An open `<ul>`/`<ol>` and the next number for ordered lists
+struct List {
+    ordered: bool,
+    next: usize,
+    /// Width of this level's marker, which continuation lines are indented by
+    indent: usize,
+}
+
+#[derive(Default)]
+struct Converter {
+    out: String,
+    lists: Vec<List>,
+    /// hrefs of open links, closed in order
+    links: Vec<Option<String>>,
+    /// Text on this line has not been written yet, so its prefix is still owed
+    line_start: bool,
+    /// Directly after a list marker, where a `<p>` must not break the line
+    item_start: bool,
+    /// Inside `<pre>`: text is copied verbatim
+    pre: bool,
+    /// Inside inline `<code>`: text is not escaped
+    code: bool,
+    /// Open `<blockquote>` elements, as the number of lists open around each
+    quotes: Vec<usize>,
+}
+
+impl Converter {
+    /// List indents and `> ` markers owed at the start of each line, outermost first
+    fn line_prefix(&self) -> String {
+        let mut prefix = String::new();
+        for depth in 0..=self.lists.len() {
+            let quotes = self.quotes.iter().filter(|&&q| q == depth).count();
+            prefix.push_str(&"> ".repeat(quotes));
+            if let Some(list) = self.lists.get(depth) {
+                prefix.push_str(&" ".repeat(list.indent));
+            }
+        }
+        prefix
+    }
+
+    /// A blank line inside the open blockquotes (`>` rather than empty)
+    fn blank_line(&self) -> String {
+        format!("{}\n", self.line_prefix().trim_end())
+    }
+
+    /// Whether the output ends with a blank line, quoted or not
+    fn at_blank_line(&self) -> bool {
+        self.out.strip_suffix('\n').is_some_and(|body| {
+            body.rsplit('\n')
+                .next()
+                .is_some_and(|last| last.trim_start_matches(['>', ' ']).is_empty())
+        })
+    }
+
+    /// Whether the innermost open container is a list rather than a blockquote
+    fn in_list(&self) -> bool {
+        self.lists.len() > self.quotes.last().copied().unwrap_or(0)
+    }
+
+    fn write(&mut self, s: &str) {
+        if s.is_empty() {
+            return;
+        }
+        if self.line_start {
+            let prefix = self.line_prefix();
+            self.out.push_str(&prefix);
+        }
+        self.line_start = false;
+        self.item_start = false;
+        self.out.push_str(s);
+    }
+
+    fn newline(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with('\n') {
+            self.out.push('\n');
+        }
+        self.line_start = true;
+    }
+
+    /// Line break inside `<pre>`, keeping blank code lines in the blockquote
+    fn code_newline(&mut self) {
+        if self.line_start {
+            let blank = self.blank_line();
+            self.out.push_str(&blank);
+        } else {
+            self.out.push('\n');
+        }
+        self.line_start = true;
+    }
+
+    /// End the current block with a blank line (a single line break inside lists)
+    fn block_break(&mut self) {
+        if self.item_start {
+            return;
+        }
+        self.newline();
+        if self.in_list() {
+            return;
+        }
+        if !self.out.is_empty() && !self.at_blank_line() {
+            let blank = self.blank_line();
+            self.out.push_str(&blank);
+        }
+    }
+
+    fn text(&mut self, raw: &str) {
+        let decoded = decode_entities(raw);
+        if self.pre {
+            for (i, line) in decoded.split('\n').enumerate() {
+                if i > 0 {
+                    self.code_newline();
+                }
+                self.write(line);
+            }
+            return;
+        }
+        let mut collapsed = String::with_capacity(decoded.len());
+        let mut prev_space = self.line_start || self.out.ends_with([' ', '\n']);
+        for c in decoded.chars() {
+            if c.is_whitespace() {
+                if !prev_space {
+                    collapsed.push(' ');
+                }
+                prev_space = true;
+            } else {
+                collapsed.push(c);
+                prev_space = false;
+            }
+        }
+        if self.code {
+            self.write(&collapsed);
+        } else {
+            let escaped = escape_text(&collapsed);
+            self.write(&escaped);
+        }
+    }
+
+    fn open(&mut self, name: &str, attrs: &str) {
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.block_break();
+                let level = name[1..].parse().unwrap_or(1);
+                self.write(&format!("{} ", "#".repeat(level)));
+            }
+            "p" | "div" => self.block_break(),
+            "br" if self.pre => self.code_newline(),
+            "br" => {
+                // Trailing backslash is CommonMark's explicit hard break
+                self.out.push('\\');
+                self.newline();
+            }
+            "pre" => {
+                self.block_break();
+                // The editor records the highlighting language on the <pre>
+                let language = attribute(attrs, "data-language").unwrap_or_default();
+                self.write(&format!("```{}", language.trim()));
+                self.newline();
+                self.pre = true;
+            }
+            "code" if !self.pre => {
+                self.write("`");
+                self.code = true;
+            }
+            "blockquote" => {
+                self.block_break();
+                self.quotes.push(self.lists.len());
+                // Like a list marker, the opening needs no break before its first block
+                self.item_start = true;
+            }
+            "hr" => {
+                self.block_break();
+                self.write("---");
+                self.block_break();
+            }
+            "strong" | "b" => self.write("**"),
+            "em" | "i" => self.write("*"),
+            "s" | "del" | "strike" => self.write("~~"),
+            "a" => {
+                let href = attribute(attrs, "href").filter(|h| !h.is_empty());
+                if href.is_some() {
+                    self.write("[");
+                }
+                self.links.push(href);
+            }
+            "ul" | "ol" => {
+                if self.lists.is_empty() {
+                    self.block_break();
+                } else {
+                    self.newline();
+                }
+                let start = attribute(attrs, "start")
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(1);
+                self.lists.push(List {
+                    ordered: name == "ol",
+                    next: start,
+                    indent: 0,
+                });
+            }
+            "li" => {
+                self.newline();
+                let marker = match self.lists.last_mut() {
+                    Some(list) if list.ordered => {
+                        list.next += 1;
+                        format!("{}. ", list.next - 1)
+                    }
+                    _ => "- ".to_string(),
+                };
+                // The marker sits at the parent's indent; its own content goes past it
+                if let Some(list) = self.lists.last_mut() {
+                    list.indent = 0;
+                }
+                self.write(&marker);
+                if let Some(list) = self.lists.last_mut() {
+                    list.indent = marker.len();
+                }
+                self.item_start = true;
+            }
+            _ => {}
+        }
+    }
+
+    fn close(&mut self, name: &str) {
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "p" | "div" => self.block_break(),
+            "strong" | "b" => self.write("**"),
+            "em" | "i" => self.write("*"),
+            "s" | "del" | "strike" => self.write("~~"),
+            "pre" => {
+                self.pre = false;
+                self.newline();
+                self.write("```");
+                self.block_break();
+            }
+            "code" if self.code => {
+                self.code = false;
+                self.write("`");
+            }
+            "blockquote" if !self.quotes.is_empty() => {
+                // Drop the quoted blank line left by the last block inside
+                self.newline();
+                let blank = format!("\n{}", self.blank_line());
+                if self.out.ends_with(&blank) {
+                    self.out.truncate(self.out.len() - blank.len() + 1);
+                }
+                self.quotes.pop();
+                self.block_break();
+            }
+            "a" => {
+                if let Some(Some(href)) = self.links.pop() {
+                    self.write(&format!("]({})", href.replace(' ', "%20")));
+                }
+            }
+            "li" => self.newline(),
+            "ul" | "ol" => {
+                self.lists.pop();
+                self.newline();
+                if self.lists.is_empty() {
+                    self.block_break();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Convert an HTML fragment from the editor to Markdown
+pub fn html_to_markdown(html: &str) -> String {
+    let mut converter = Converter::default();
+    for token in tokenize(html) {
+        match token {
+            Token::Open { name, attrs } => converter.open(&name, attrs),
+            Token::Close(name) => converter.close(&name),
+            Token::Text(text) => converter.text(text),
+        }
+    }
+
+    // Drop trailing spaces and repeated blank lines, except inside code blocks
+    let mut lines: Vec<&str> = Vec::new();
+    let mut in_fence = false;
+    for line in converter.out.lines() {
+        if line.trim_start_matches(['>', ' ']).starts_with("```") {
+            in_fence = !in_fence;
+        } else if in_fence {
+            lines.push(line);
+            continue;
+        }
+        let line = line.trim_end();
+        // Blank lines, including quoted ones (`>`), are never repeated
+        let blank = line.trim_start_matches(['>', ' ']).is_empty();
+        if blank && lines.last().is_some_and(|last| *last == line) {
+            continue;
+        }
+        lines.push(line);
+    }
+    lines.join("\n").trim().to_string()
+}
+
+/// Escape text for HTML element content or attribute values
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Byte offset of the next unescaped `delimiter` in `text`, if the run it
+/// closes is non-empty and does not start or end with whitespace
+fn closing_delimiter(text: &str, delimiter: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if text[i..].starts_with(delimiter) {
+            let inner = &text[..i];
+            if inner.is_empty() || inner.starts_with(char::is_whitespace) {
+                return None;
+            }
+            if inner.ends_with(char::is_whitespace) {
+                continue;
+            }
+            return Some(i);
+        }
+    }
+    None
+}
+
+/// Inline Markdown (emphasis, code, links, hard breaks) to HTML
+fn inline_to_html(text: &str) -> String {
+    let mut html = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        if let Some(after) = rest.strip_prefix('\\') {
+            match after.chars().next() {
+                Some('\n') => {
+                    html.push_str("<br>\n");
+                    rest = &after[1..];
+                }
+                Some(next) if next.is_ascii_punctuation() => {
+                    html.push_str(&escape_html(&next.to_string()));
+                    rest = &after[1..];
+                }
+                _ => {
+                    html.push('\\');
+                    rest = after;
+                }
+            }
+            continue;
+        }
+        if let Some(after) = rest.strip_prefix("  \n") {
+            html.push_str("<br>\n");
+            rest = after;
+            continue;
+        }
+
+        if c == '`' {
+            let run = rest.len() - rest.trim_start_matches('`').len();
+            let after = &rest[run..];
+            if let Some(end) = after.find(&rest[..run]) {
+                let code = after[..end].trim();
+                html.push_str(&format!("<code>{}</code>", escape_html(code)));
+                rest = &after[end + run..];
+                continue;
+            }
+        }
+
+        let mut matched = false;
+        for (delimiter, tag) in [
+            ("**", "strong"),
+            ("__", "strong"),
+            ("~~", "del"),
+            ("*", "em"),
+            ("_", "em"),
+        ] {
+            let Some(after) = rest.strip_prefix(delimiter) else {
+                continue;
+            };
+            // `_` only emphasizes at word boundaries, so snake_case stays as is
+            if delimiter.starts_with('_') && html.ends_with(|c: char| c.is_alphanumeric()) {
+                continue;
+            }
+            if let Some(end) = closing_delimiter(after, delimiter) {
+                html.push_str(&format!(
+                    "<{}>{}</{}>",
+                    tag,
+                    inline_to_html(&after[..end]),
+                    tag
+                ));
+                rest = &after[end + delimiter.len()..];
+                matched = true;
+                break;
+            }
+        }
+        if matched {
+            continue;
+        }
+
+        if let Some(after) = rest.strip_prefix('[') {
+            let link = closing_delimiter(after, "](").and_then(|text_end| {
+                let target = &after[text_end + 2..];
+                target
+                    .find(')')
+                    .map(|href_end| (text_end, &target[..href_end], text_end + 2 + href_end + 1))
+            });
+            if let Some((text_end, href, end)) = link {
+                let href = href.trim().replace("%20", " ");
+                html.push_str(&format!(
+                    "<a href=\"{}\">{}</a>",
+                    escape_html(&href),
+                    inline_to_html(&after[..text_end])
+                ));
+                rest = &after[end..];
+                continue;
+            }
+        }
+
+        html.push_str(&escape_html(&c.to_string()));
+        rest = &rest[c.len_utf8()..];
+    }
+    html
+}
+
+/// List marker at the start of `line`: (ordered, start number, marker width)
+fn list_marker(line: &str) -> Option<(bool, usize, usize)> {
+    let indent = line.len() - line.trim_start().len();
+    let trimmed = &line[indent..];
+    if let Some(rest) = trimmed.strip_prefix(['-', '*', '+']) {
+        return (rest.starts_with(' ') || rest.is_empty()).then_some((false, 1, indent + 2));
+    }
+    let digits = trimmed.len()
+        - trimmed
+            .trim_start_matches(|c: char| c.is_ascii_digit())
+            .len();
+    let rest = &trimmed[digits..];
+    if digits == 0 || digits > 9 || !rest.starts_with(['.', ')']) {
+        return None;
+    }
+    let rest = &rest[1..];
+    (rest.starts_with(' ') || rest.is_empty()).then(|| {
+        (
+            true,
+            trimmed[..digits].parse().unwrap_or(1),
+            indent + digits + 2,
+        )
+    })
+}
+
+/// ATX heading: level and text
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let trimmed = line.trim_start();
+    let level = trimmed.len() - trimmed.trim_start_matches('#').len();
+    let rest = &trimmed[level..];
+    if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with(' ')) {
+        return None;
+    }
+    Some((level, rest.trim().trim_end_matches('#').trim_end()))
+}
+
+fn is_rule(line: &str) -> bool {
+    let mut chars = line.chars().filter(|c| !c.is_whitespace());
+    let Some(first @ ('-' | '*' | '_')) = chars.next() else {
+        return false;
+    };
+    let mut count = 1;
+    for c in chars {
+        if c != first {
+            return false;
+        }
+        count += 1;
+    }
+    count >= 3
+}
+
+fn is_fence(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    trimmed.starts_with("```") || trimmed.starts_with("~~~")
+}
+
+/// Whether `line` starts a block other than a paragraph
+fn starts_block(line: &str) -> bool {
+    heading(line).is_some()
+        || is_rule(line)
+        || is_fence(line)
+        || line.trim_start().starts_with('>')
+        || list_marker(line).is_some()
+}
+
+/// Convert block-level Markdown. In a tight list item, paragraphs are not
+/// wrapped in `<p>`.
+fn blocks_to_html(lines: &[&str], tight: bool) -> String {
+    let mut html = String::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        if line.trim().is_empty() {
+            i += 1;
+            continue;
+        }
+
+        if is_fence(line) {
+            let (fence, language) = line.trim_start().split_at(3);
+            let mut code = Vec::new();
+            i += 1;
+            while i < lines.len() && !lines[i].trim_start().starts_with(fence) {
+                code.push(lines[i]);
+                i += 1;
+            }
+            i += 1;
+            let language = language.trim();
+            let open = if language.is_empty() {
+                "<pre>".to_string()
+            } else {
+                format!("<pre data-language=\"{}\">", escape_html(language))
+            };
+            html.push_str(&format!(
+                "{}<code>{}</code></pre>",
+                open,
+                escape_html(&code.join("\n"))
+            ));
+        } else if let Some((level, text)) = heading(line) {
+            html.push_str(&format!(
+                "<h{}>{}</h{}>",
+                level,
+                inline_to_html(text),
+                level
+            ));
+            i += 1;
+        } else if is_rule(line) {
+            html.push_str("<hr>");
+            i += 1;
+        } else if line.trim_start().starts_with('>') {
+            let mut quoted = Vec::new();
+            while i < lines.len() && lines[i].trim_start().starts_with('>') {
+                let content = &lines[i].trim_start()[1..];
+                quoted.push(content.strip_prefix(' ').unwrap_or(content));
+                i += 1;
+            }
+            html.push_str(&format!(
+                "<blockquote>{}</blockquote>",
+                blocks_to_html(&quoted, false)
+            ));
+        } else if let Some((ordered, start, _)) = list_marker(line) {
+            let indent = line.len() - line.trim_start().len();
+            let mut items: Vec<Vec<&str>> = Vec::new();
+            let mut content_indent = 0;
+            let mut loose = false;
+            while i < lines.len() {
+                let current = lines[i];
+                let current_indent = current.len() - current.trim_start().len();
+                match list_marker(current) {
+                    Some((o, _, width)) if current_indent == indent && o == ordered => {
+                        items.push(vec![current.get(width..).unwrap_or("")]);
+                        content_indent = width;
+                    }
+                    _ if current.trim().is_empty() => {
+                        // A blank line inside the list makes it loose, unless the list ends here
+                        let continues = lines.get(i + 1).is_some_and(|next| {
+                            let next_indent = next.len() - next.trim_start().len();
+                            next_indent > indent
+                                || (next_indent == indent
+                                    && list_marker(next).is_some_and(|(o, _, _)| o == ordered))
+                        });
+                        if !continues {
+                            break;
+                        }
+                        loose = true;
+                        if let Some(item) = items.last_mut() {
+                            item.push("");
+                        }
+                    }
+                    _ if current_indent > indent => {
+                        // Continuation or nested block: strip up to the item's content indent
+                        let strip = current_indent.min(content_indent);
+                        if let Some(item) = items.last_mut() {
+                            item.push(&current[strip..]);
+                        }
+                    }
+                    _ if !starts_block(current)
+                        && items.last().is_some_and(|item| {
+                            item.last().is_some_and(|l| !l.trim().is_empty())
+                        }) =>
+                    {
+                        // Lazy continuation of the item's paragraph
+                        if let Some(item) = items.last_mut() {
+                            item.push(current.trim_start());
+                        }
+                    }
+                    _ => break,
+                }
+                i += 1;
+            }
+
+            let tag = if ordered { "ol" } else { "ul" };
+            if ordered && start != 1 {
+                html.push_str(&format!("<ol start=\"{}\">", start));
+            } else {
+                html.push_str(&format!("<{}>", tag));
+            }
+            for item in items {
+                html.push_str(&format!("<li>{}</li>", blocks_to_html(&item, !loose)));
+            }
+            html.push_str(&format!("</{}>", tag));
+        } else {
+            let mut paragraph = vec![line.trim_start()];
+            i += 1;
+            while i < lines.len() && !lines[i].trim().is_empty() && !starts_block(lines[i]) {
+                paragraph.push(lines[i].trim_start());
+                i += 1;
+            }
+            let text = inline_to_html(paragraph.join("\n").trim_end());
+            if tight {
+                html.push_str(&text);
+            } else {
+                html.push_str(&format!("<p>{}</p>", text));
+            }
+        }
+    }
+    html
+}
+
+/// Convert a Markdown document to HTML for the editor
+pub fn markdown_to_html(markdown: &str) -> String {
+    let normalized = markdown.replace("\r\n", "\n").replace('\t', "    ");
+    let lines: Vec<&str> = normalized.lines().collect();
+    blocks_to_html(&lines, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_ascii_text_is_kept() {
+        assert_eq!(html_to_markdown("<p>Crème brûlée</p>"), "Crème brûlée");
+        // Text starting with a multi-byte character right after a tag
+        assert_eq!(html_to_markdown("<p><em>日本</em>語 &gt; ü</p>"), "*日本*語 > ü");
+        assert_eq!(html_to_markdown("€ <= 5"), "€ <= 5");
+    }
+
+    #[test]
+    fn bold_link_in_list_item() {
+        assert_eq!(
+            html_to_markdown("<ul><li><strong><a href=\"https://example.com\">t</a></strong></li></ul>"),
+            "- **[t](https://example.com)**"
+        );
+    }
+
+    #[test]
+    fn nested_lists_are_indented() {
+        assert_eq!(
+            html_to_markdown("<ol><li>one<ul><li><em>inner</em></li></ul></li><li>two</li></ol>"),
+            "1. one\n   - *inner*\n2. two"
+        );
+    }
+
+    #[test]
+    fn blocks_are_separated_by_blank_lines() {
+        assert_eq!(
+            html_to_markdown("<h2>Title</h2><p>Some <code>code</code>.</p><blockquote><p>quoted</p></blockquote>"),
+            "## Title\n\nSome `code`.\n\n> quoted"
+        );
+    }
+
+    #[test]
+    fn blockquotes_prefix_every_line() {
+        assert_eq!(
+            html_to_markdown("<blockquote><p>one</p><p>two</p><blockquote><p>deeper</p></blockquote></blockquote>"),
+            "> one\n>\n> two\n>\n> > deeper"
+        );
+        assert_eq!(html_to_markdown("<ul><li>item<blockquote><p>q</p></blockquote></li></ul>"), "- item\n  > q");
+    }
+
+    #[test]
+    fn code_blocks_become_fences() {
+        assert_eq!(
+            html_to_markdown("<pre data-language=\"rust\"><code>fn main() {\n\n    let a = 1 &lt; 2;\n}</code></pre>"),
+            "```rust\nfn main() {\n\n    let a = 1 < 2;\n}\n```"
+        );
+        assert_eq!(html_to_markdown("<blockquote><pre><code>a\nb</code></pre></blockquote>"), "> ```\n> a\n> b\n> ```");
+    }
+
+    #[test]
+    fn markdown_round_trips_through_html() {
+        let markdown = "# Title\n\nSome **bold** and *italic* with `code`.\n\n- one\n- two\n\n> quoted\n\n```rust\nlet a = 1;\n```";
+        assert_eq!(html_to_markdown(&markdown_to_html(markdown)), markdown);
+    }
+}
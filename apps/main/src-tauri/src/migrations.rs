@@ -0,0 +1,218 @@
+//! Schema migrations keyed by `PRAGMA user_version`
+//!
+//! Each migration moves the schema up one version. It runs in its own
+//! transaction together with the `user_version` bump, so a failed migration
+//! leaves the database at the previous version, and running `migrate` on an
+//! up-to-date database does nothing. The main app migrates on startup and the
+//! CLI on `irisnotes migrate`.
+//!
+//! Migration 1 is the whole of `schema/base.sql`, so a fresh database needs
+//! nothing else. Databases created before migrations existed are at version
+//! 0 with their tables already in place; migration 1 first brings their
+//! `items` table up to the columns `base.sql` indexes, then reapplies it
+//! (every statement there is `IF NOT EXISTS`). The frontend also reapplies
+//! `base.sql` on each start, so a later migration that changes a table
+//! `base.sql` also defines must check before it alters, as
+//! `add_column_if_missing` does.
+
+use rusqlite::{Connection, Transaction};
+
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    apply: fn(&Transaction) -> rusqlite::Result<()>,
+}
+
+/// Every migration, in version order
+pub const MIGRATIONS: &[Migration] = &[Migration {
+    version: 1,
+    description: "initial schema",
+    apply: initial_schema,
+}];
+
+/// `items` columns added after the first databases were created, with the
+/// definitions `base.sql` gives them
+const LATE_ITEM_COLUMNS: &[(&str, &str)] = &[
+    ("sort_order", "TEXT NOT NULL DEFAULT 'a0'"),
+    ("deleted_at", "TEXT NULL"),
+    ("view_count", "INTEGER NOT NULL DEFAULT 0"),
+    ("last_viewed_at", "TEXT NULL"),
+];
+
+fn has_table(tx: &Transaction, name: &str) -> rusqlite::Result<bool> {
+    tx.query_row(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        [name],
+        |row| row.get(0),
+    )
+}
+
+/// `ALTER TABLE ... ADD COLUMN`, skipped when the column already exists
+fn add_column_if_missing(
+    tx: &Transaction,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> rusqlite::Result<()> {
+    let exists: bool = tx.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info(?1) WHERE name = ?2",
+        [table, column],
+        |row| row.get(0),
+    )?;
+    if !exists {
+        tx.execute_batch(&format!(
+            "ALTER TABLE {} ADD COLUMN {} {}",
+            table, column, definition
+        ))?;
+    }
+    Ok(())
+}
+
+fn initial_schema(tx: &Transaction) -> rusqlite::Result<()> {
+    if has_table(tx, "items")? {
+        for (column, definition) in LATE_ITEM_COLUMNS {
+            add_column_if_missing(tx, "items", column, definition)?;
+        }
+        // Older databases carry an update_items_timestamp trigger that ignores
+        // sync_ctl; IF NOT EXISTS would keep it, so drop it to get the current one
+        tx.execute_batch("DROP TRIGGER IF EXISTS update_items_timestamp")?;
+    }
+    tx.execute_batch(crate::DATABASE_SCHEMA)
+}
+
+/// The schema version recorded in the database (0 before any migration)
+pub fn schema_version(conn: &Connection) -> rusqlite::Result<u32> {
+    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+}
+
+/// The version a fully migrated database is at
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map_or(0, |migration| migration.version)
+}
+
+/// Apply every migration newer than the database's schema version, in order.
+/// Returns the migrations applied; empty when the database was up to date.
+pub fn migrate(conn: &Connection) -> Result<Vec<&'static Migration>, String> {
+    let current = schema_version(conn)
+        .map_err(|e| format!("Failed to read schema version: {}", e))?;
+
+    let mut applied = Vec::new();
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        let tx = conn
+            .unchecked_transaction()
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+        (migration.apply)(&tx).map_err(|e| {
+            format!(
+                "Migration {} ({}) failed: {}",
+                migration.version, migration.description, e
+            )
+        })?;
+        tx.pragma_update(None, "user_version", migration.version)
+            .map_err(|e| format!("Failed to record schema version: {}", e))?;
+        tx.commit().map_err(|e| {
+            format!("Failed to commit migration {}: {}", migration.version, e)
+        })?;
+        applied.push(migration);
+    }
+    Ok(applied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An `items` table as the first releases created it, before `sort_order`,
+    /// `deleted_at` and view tracking, with the old timestamp trigger
+    const BASELINE_SCHEMA: &str = "
+        CREATE TABLE items (
+            id TEXT PRIMARY KEY,
+            type TEXT NOT NULL CHECK (type IN ('note', 'book', 'section')),
+            title TEXT NOT NULL DEFAULT 'Untitled',
+            content TEXT DEFAULT '',
+            content_type TEXT DEFAULT 'html',
+            content_raw TEXT NULL,
+            content_plaintext TEXT DEFAULT '',
+            parent_id TEXT NULL,
+            metadata TEXT DEFAULT '{}',
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+            word_count INTEGER DEFAULT 0,
+            character_count INTEGER DEFAULT 0
+        );
+        CREATE TRIGGER update_items_timestamp AFTER UPDATE ON items
+        BEGIN
+            UPDATE items SET updated_at = datetime('now') WHERE id = NEW.id;
+        END;
+        INSERT INTO items (id, type, title) VALUES ('n', 'note', 'Old note');
+    ";
+
+    fn has_column(conn: &Connection, table: &str, column: &str) -> bool {
+        conn.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info(?1) WHERE name = ?2",
+            [table, column],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn a_fresh_database_migrates_to_the_latest_version() {
+        let conn = Connection::open_in_memory().unwrap();
+        assert_eq!(schema_version(&conn).unwrap(), 0);
+
+        let applied = migrate(&conn).unwrap();
+        assert_eq!(applied.len(), MIGRATIONS.len());
+        assert_eq!(schema_version(&conn).unwrap(), latest_version());
+        assert!(has_column(&conn, "items", "sort_order"));
+        assert!(has_column(&conn, "item_tags", "item_id"));
+    }
+
+    #[test]
+    fn a_baseline_database_gains_the_late_columns_and_keeps_its_rows() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(BASELINE_SCHEMA).unwrap();
+
+        migrate(&conn).unwrap();
+        assert_eq!(schema_version(&conn).unwrap(), latest_version());
+        for (column, _) in LATE_ITEM_COLUMNS {
+            assert!(has_column(&conn, "items", column), "missing {}", column);
+        }
+        assert!(has_column(&conn, "changelog", "seq"));
+
+        let (title, sort_order): (String, String) = conn
+            .query_row("SELECT title, sort_order FROM items WHERE id = 'n'", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!(title, "Old note");
+        assert_eq!(sort_order, "a0");
+
+        // The timestamp trigger is the current one, which honours sync_ctl
+        let trigger: String = conn
+            .query_row(
+                "SELECT sql FROM sqlite_master WHERE type = 'trigger' AND name = 'update_items_timestamp'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(trigger.contains("sync_ctl"));
+    }
+
+    #[test]
+    fn migrating_an_up_to_date_database_does_nothing() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO items (id, type, title) VALUES ('b', 'book', 'Kept')",
+            [],
+        )
+        .unwrap();
+
+        assert!(migrate(&conn).unwrap().is_empty());
+        assert_eq!(schema_version(&conn).unwrap(), latest_version());
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM items", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+}
@@ -0,0 +1,601 @@
+//! Note operations behind the main app's Tauri commands
+//!
+//! The frontend used to write items with raw SQL through `tauri_plugin_sql`,
+//! repeating the schema rules in TypeScript. These functions are the
+//! validated path: they check the hierarchy (see `hierarchy.rs`), place items
+//! with fractional `sort_order` keys (see `sort_order.rs`) and keep the
+//! derived columns the search index is built from in step with the HTML.
+//! The CLI shares them for the same writes.
+
+use crate::error::AppError;
+use crate::sort_order::Placement;
+use irisnotes_search::MAX_PATH_DEPTH;
+use irisnotes_text::{strip_html, word_count};
+use rusqlite::{Connection, OptionalExtension};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Insert an item under `parent_id` at `placement`, returning its ID.
+/// The parent must already be checked (`hierarchy::validate_parent`); call
+/// inside the caller's transaction, since placing may renumber siblings.
+/// The FTS index is kept in sync by the `items_fts_insert` trigger.
+pub fn insert_item(
+    conn: &Connection,
+    item_type: &str,
+    title: &str,
+    html: &str,
+    parent_id: Option<&str>,
+    placement: Placement,
+) -> Result<String, AppError> {
+    let sort_order = crate::sort_order::sort_order_for(conn, parent_id, placement, None)
+        .map_err(AppError::Db)?;
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let plaintext = strip_html(html);
+    conn.execute(
+        "INSERT INTO items (
+            id, type, title, content, content_type, content_plaintext,
+            parent_id, sort_order, word_count, character_count
+        ) VALUES (?1, ?2, ?3, ?4, 'html', ?5, ?6, ?7, ?8, ?9)",
+        rusqlite::params![
+            id,
+            item_type,
+            title,
+            html,
+            plaintext,
+            parent_id,
+            sort_order,
+            word_count(&plaintext) as i64,
+            plaintext.chars().count() as i64,
+        ],
+    )
+    .map_err(|e| AppError::Db(format!("Failed to create {}: {}", item_type, e)))?;
+    Ok(id)
+}
+
+/// The item's title with its ancestors, root first: "Book / Section / Note".
+/// Empty when the item does not exist.
+pub fn get_item_path(conn: &Connection, id: &str) -> rusqlite::Result<String> {
+    // One query for the whole chain; called once per hit by `search`
+    let mut stmt = conn.prepare_cached(
+        "WITH RECURSIVE ancestors(title, parent_id, depth) AS (
+            SELECT title, parent_id, 0 FROM items WHERE id = ?1
+            UNION ALL
+            SELECT items.title, items.parent_id, ancestors.depth + 1
+            FROM items JOIN ancestors ON items.id = ancestors.parent_id
+            WHERE ancestors.depth < ?2
+        )
+        SELECT title FROM ancestors ORDER BY depth DESC",
+    )?;
+    let path_parts = stmt
+        .query_map(rusqlite::params![id, MAX_PATH_DEPTH as i64], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(path_parts.join(" / "))
+}
+
+/// Tag names on an item, alphabetically
+pub fn get_item_tags(conn: &Connection, item_id: &str) -> rusqlite::Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT tags.name FROM item_tags
+         JOIN tags ON tags.id = item_tags.tag_id
+         WHERE item_tags.item_id = ?1
+         ORDER BY tags.name COLLATE NOCASE",
+    )?;
+    let tags = stmt
+        .query_map([item_id], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+    Ok(tags)
+}
+
+/// Titles are shown in the tree and matched by search, so one is required
+fn check_title(title: &str) -> Result<&str, AppError> {
+    let title = title.trim();
+    if title.is_empty() {
+        return Err(AppError::InvalidInput("Title cannot be empty".into()));
+    }
+    Ok(title)
+}
+
+/// Like `hierarchy::validate_parent`, but a missing or trashed parent is
+/// `NotFound` rather than a rule violation
+fn check_parent(conn: &Connection, item_type: &str, parent_id: Option<&str>) -> Result<(), AppError> {
+    let Some(parent_id) = parent_id else {
+        return crate::hierarchy::check_parent_type(item_type, None).map_err(AppError::InvalidInput);
+    };
+    let parent_type: Option<String> = conn
+        .query_row(
+            "SELECT type FROM items WHERE id = ?1 AND deleted_at IS NULL",
+            [parent_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    let Some(parent_type) = parent_type else {
+        return Err(AppError::NotFound(format!("Parent item '{}' not found", parent_id)));
+    };
+    crate::hierarchy::check_parent_type(item_type, Some(&parent_type)).map_err(AppError::InvalidInput)
+}
+
+/// Create a note under `parent_id` (the root when `None`) after its
+/// siblings, returning the new note's ID
+pub fn create_note(
+    conn: &Connection,
+    title: &str,
+    parent_id: Option<&str>,
+    html: &str,
+) -> Result<String, AppError> {
+    let title = check_title(title)?;
+    check_parent(conn, "note", parent_id)?;
+
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| AppError::Db(format!("Failed to start transaction: {}", e)))?;
+    let id = insert_item(&tx, "note", title, html, parent_id, Placement::Append)?;
+    tx.commit()
+        .map_err(|e| AppError::Db(format!("Failed to commit note: {}", e)))?;
+    Ok(id)
+}
+
+/// Replace a note's HTML body and refresh its plaintext and counts.
+/// The FTS index is kept in sync by the `items_fts_update` trigger.
+pub fn set_content(conn: &Connection, id: &str, html: &str) -> Result<(), AppError> {
+    let plaintext = strip_html(html);
+    conn.execute(
+        "UPDATE items
+         SET content = ?1, content_plaintext = ?2, word_count = ?3, character_count = ?4
+         WHERE id = ?5",
+        rusqlite::params![
+            html,
+            plaintext,
+            word_count(&plaintext) as i64,
+            plaintext.chars().count() as i64,
+            id,
+        ],
+    )
+    .map_err(|e| AppError::Db(format!("Failed to save note: {}", e)))?;
+    Ok(())
+}
+
+/// Whether the note `id` is in the trash. `NotFound` when there is no such
+/// note; books and sections are not notes.
+fn note_is_deleted(conn: &Connection, id: &str) -> Result<bool, AppError> {
+    conn.query_row(
+        "SELECT deleted_at IS NOT NULL FROM items WHERE id = ?1 AND type = 'note'",
+        [id],
+        |row| row.get(0),
+    )
+    .optional()?
+    .ok_or_else(|| AppError::NotFound(format!("Note '{}' not found", id)))
+}
+
+/// Change a live note's title and/or content, leaving fields passed as
+/// `None` untouched. `updated_at` is bumped by the `update_items_timestamp` trigger.
+pub fn update_note(
+    conn: &Connection,
+    id: &str,
+    title: Option<&str>,
+    html: Option<&str>,
+) -> Result<(), AppError> {
+    if title.is_none() && html.is_none() {
+        return Err(AppError::InvalidInput("Nothing to update: pass a title or content".into()));
+    }
+    let title = title.map(check_title).transpose()?;
+    if note_is_deleted(conn, id)? {
+        return Err(AppError::InvalidInput(format!("Note '{}' is in the trash", id)));
+    }
+
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| AppError::Db(format!("Failed to start transaction: {}", e)))?;
+    if let Some(title) = title {
+        tx.execute("UPDATE items SET title = ?1 WHERE id = ?2", [title, id])
+            .map_err(|e| AppError::Db(format!("Failed to rename note: {}", e)))?;
+    }
+    if let Some(html) = html {
+        set_content(&tx, id, html)?;
+    }
+    tx.commit()
+        .map_err(|e| AppError::Db(format!("Failed to commit note: {}", e)))?;
+    Ok(())
+}
+
+/// Move a note to the trash (`deleted_at`), or with `hard` remove it for
+/// good along with its tags, versions and search index row. A hard delete
+/// also empties a note that is already in the trash.
+pub fn delete_note(conn: &Connection, id: &str, hard: bool) -> Result<(), AppError> {
+    let deleted = note_is_deleted(conn, id)?;
+    if deleted && !hard {
+        return Ok(());
+    }
+
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| AppError::Db(format!("Failed to start transaction: {}", e)))?;
+    if hard {
+        crate::hierarchy::purge_subtree(&tx, id).map_err(AppError::Db)?;
+    } else {
+        crate::hierarchy::soft_delete_subtree(&tx, id).map_err(AppError::Db)?;
+    }
+    tx.commit()
+        .map_err(|e| AppError::Db(format!("Failed to commit delete: {}", e)))?;
+    Ok(())
+}
+
+/// One item of the tree returned by `get_tree`, with its children nested
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TreeNode {
+    pub id: String,
+    pub title: String,
+    #[serde(rename = "type")]
+    pub item_type: String,
+    /// Only set for trashed items, which are included on request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<String>,
+    pub children: Vec<TreeNode>,
+}
+
+/// The whole book/section/note hierarchy, each level in `sort_order`.
+/// Trashed items, and anything under them, are left out unless
+/// `include_deleted` is set.
+pub fn tree(conn: &Connection, include_deleted: bool) -> Result<Vec<TreeNode>, AppError> {
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT id, title, type, parent_id, deleted_at FROM items
+             WHERE ?1 OR deleted_at IS NULL
+             ORDER BY sort_order",
+        )
+        .map_err(|e| AppError::Db(format!("Failed to load items: {}", e)))?;
+    let rows = stmt
+        .query_map([include_deleted], |row| {
+            Ok((
+                TreeNode {
+                    id: row.get(0)?,
+                    title: row.get(1)?,
+                    item_type: row.get(2)?,
+                    deleted_at: row.get(4)?,
+                    children: Vec::new(),
+                },
+                row.get::<_, Option<String>>(3)?,
+            ))
+        })
+        .map_err(|e| AppError::Db(format!("Failed to load items: {}", e)))?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| AppError::Db(format!("Failed to load items: {}", e)))?;
+
+    // Group by parent, keeping each group in sort order, then assemble from
+    // the root down. Items whose parent was filtered out, and parent chains
+    // that loop (see `irisnotes doctor`), are never reached.
+    let mut children: HashMap<Option<String>, Vec<TreeNode>> = HashMap::new();
+    for (node, parent_id) in rows {
+        children.entry(parent_id).or_default().push(node);
+    }
+
+    fn attach(
+        parent_id: Option<String>,
+        children: &mut HashMap<Option<String>, Vec<TreeNode>>,
+    ) -> Vec<TreeNode> {
+        let mut nodes = children.remove(&parent_id).unwrap_or_default();
+        for node in &mut nodes {
+            node.children = attach(Some(node.id.clone()), children);
+        }
+        nodes
+    }
+
+    Ok(attach(None, &mut children))
+}
+
+/// A sibling's place after a move, as returned by `move_item`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SiblingOrder {
+    pub id: String,
+    pub sort_order: String,
+}
+
+/// Move the live item `id` under `new_parent_id` (the root when `None`) at
+/// `position` among its new siblings, appending when `None` or past the end.
+/// `position` is the index the item ends up at, counted among the other
+/// siblings: moving an item down within its own parent is one less than
+/// the drop slot in the list that still shows it. Descendants follow
+/// through their `parent_id`. Returns the new parent's children in their
+/// new order.
+pub fn move_item(
+    conn: &Connection,
+    id: &str,
+    new_parent_id: Option<&str>,
+    position: Option<usize>,
+) -> Result<Vec<SiblingOrder>, AppError> {
+    let item_type: String = conn
+        .query_row(
+            "SELECT type FROM items WHERE id = ?1 AND deleted_at IS NULL",
+            [id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("Item '{}' not found", id)))?;
+    check_parent(conn, &item_type, new_parent_id)?;
+    crate::hierarchy::check_not_descendant(conn, id, new_parent_id)
+        .map_err(AppError::InvalidInput)?;
+
+    let placement = match position {
+        Some(position) => Placement::At(position),
+        None => Placement::Append,
+    };
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| AppError::Db(format!("Failed to start transaction: {}", e)))?;
+    let sort_order = crate::sort_order::sort_order_for(&tx, new_parent_id, placement, Some(id))
+        .map_err(AppError::Db)?;
+    tx.execute(
+        "UPDATE items SET parent_id = ?1, sort_order = ?2 WHERE id = ?3",
+        rusqlite::params![new_parent_id, sort_order, id],
+    )
+    .map_err(|e| AppError::Db(format!("Failed to move item: {}", e)))?;
+    let siblings = crate::sort_order::siblings(&tx, new_parent_id, None).map_err(AppError::Db)?;
+    tx.commit()
+        .map_err(|e| AppError::Db(format!("Failed to commit move: {}", e)))?;
+
+    Ok(siblings
+        .into_iter()
+        .map(|(id, sort_order)| SiblingOrder { id, sort_order })
+        .collect())
+}
+
+/// A result limit from the frontend, `irisnotes_search::DEFAULT_LIMIT` when omitted
+fn check_limit(limit: Option<usize>) -> Result<usize, AppError> {
+    let limit = limit.unwrap_or(irisnotes_search::DEFAULT_LIMIT);
+    if !irisnotes_search::LIMIT_RANGE.contains(&limit) {
+        return Err(AppError::InvalidInput(format!(
+            "Limit must be {}-{}",
+            irisnotes_search::LIMIT_RANGE.start(),
+            irisnotes_search::LIMIT_RANGE.end()
+        )));
+    }
+    Ok(limit)
+}
+
+/// Run a quick-search query (`words ~content @book #section /`) the way the
+/// quick app does
+pub fn search(
+    conn: &Connection,
+    query: &str,
+    limit: Option<usize>,
+    max_per_book: Option<usize>,
+) -> Result<Vec<irisnotes_search::SearchResult>, AppError> {
+    let limit = check_limit(limit)?;
+    if max_per_book == Some(0) {
+        return Err(AppError::InvalidInput("max_per_book must be at least 1".into()));
+    }
+    irisnotes_search::run_search(conn, query, max_per_book, limit).map_err(AppError::Db)
+}
+
+/// The most recently modified live notes, newest first, with path and preview
+pub fn recent(
+    conn: &Connection,
+    limit: Option<usize>,
+) -> Result<Vec<irisnotes_search::SearchResult>, AppError> {
+    irisnotes_search::recent_notes(conn, check_limit(limit)?).map_err(AppError::Db)
+}
+
+/// One note as the GUI opens it, e.g. for `--open-note` and deep links
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteDto {
+    pub id: String,
+    pub title: String,
+    /// Stored HTML, unmodified
+    pub content: String,
+    #[serde(rename = "type")]
+    pub item_type: String,
+    pub parent_id: Option<String>,
+    /// "Book / Section / Note"
+    pub path: String,
+    pub tags: Vec<String>,
+}
+
+/// The live note `id` with its path and tags; `NotFound` when it is missing,
+/// in the trash, or a book or section
+pub fn get_note(conn: &Connection, id: &str) -> Result<NoteDto, AppError> {
+    let note = conn
+        .query_row(
+            "SELECT id, title, content, type, parent_id FROM items
+             WHERE id = ?1 AND type = 'note' AND deleted_at IS NULL",
+            [id],
+            |row| {
+                Ok(NoteDto {
+                    id: row.get(0)?,
+                    title: row.get(1)?,
+                    content: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                    item_type: row.get(3)?,
+                    parent_id: row.get(4)?,
+                    path: String::new(),
+                    tags: Vec::new(),
+                })
+            },
+        )
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("Note '{}' not found", id)))?;
+
+    Ok(NoteDto {
+        path: get_item_path(conn, id)?,
+        // Databases from before tags existed simply have none
+        tags: get_item_tags(conn, id).unwrap_or_default(),
+        ..note
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{insert, memory_db};
+
+    #[test]
+    fn item_path_lists_every_ancestor_root_first() {
+        let conn = memory_db();
+        let book = insert_item(&conn, "book", "Recipes", "", None, Placement::Append).unwrap();
+        check_parent(&conn, "section", Some(&book)).unwrap();
+        let section =
+            insert_item(&conn, "section", "Soups", "", Some(&book), Placement::Append).unwrap();
+        let note = create_note(&conn, "Leek", Some(&section), "").unwrap();
+
+        assert_eq!(get_item_path(&conn, &note).unwrap(), "Recipes / Soups / Leek");
+        assert_eq!(get_item_path(&conn, &book).unwrap(), "Recipes");
+        assert_eq!(get_item_path(&conn, "missing").unwrap(), "");
+    }
+
+    #[test]
+    fn item_path_follows_chains_deeper_than_the_hierarchy_allows() {
+        // Nested sections are rejected by the hierarchy checks, so insert them raw
+        let conn = memory_db();
+        insert(&conn, "b", "book", None);
+        let mut parent = "b".to_string();
+        for level in 1..9 {
+            let id = format!("s{}", level);
+            insert(&conn, &id, "section", Some(&parent));
+            parent = id;
+        }
+        insert(&conn, "n", "note", Some(&parent));
+
+        assert_eq!(get_item_path(&conn, "n").unwrap(), "b / s1 / s2 / s3 / s4 / s5 / s6 / s7 / s8 / n");
+    }
+
+    #[test]
+    fn item_path_stops_on_a_parent_cycle() {
+        let conn = memory_db();
+        insert(&conn, "b", "book", None);
+        insert(&conn, "x", "section", Some("b"));
+        insert(&conn, "y", "section", Some("b"));
+        conn.execute_batch(
+            "UPDATE items SET parent_id = 'y' WHERE id = 'x';
+             UPDATE items SET parent_id = 'x' WHERE id = 'y';",
+        )
+        .unwrap();
+
+        let path = get_item_path(&conn, "x").unwrap();
+        assert_eq!(path.split(" / ").count(), MAX_PATH_DEPTH + 1);
+        assert!(path.ends_with("y / x"));
+    }
+
+    #[test]
+    fn get_note_only_returns_live_notes() {
+        let conn = memory_db();
+        insert(&conn, "b", "book", None);
+        insert(&conn, "s", "section", Some("b"));
+        insert(&conn, "n", "note", Some("s"));
+        insert(&conn, "gone", "note", Some("s"));
+        conn.execute("UPDATE items SET deleted_at = datetime('now') WHERE id = 'gone'", [])
+            .unwrap();
+
+        let note = get_note(&conn, "n").unwrap();
+        assert_eq!((note.path.as_str(), note.parent_id.as_deref()), ("b / s / n", Some("s")));
+        for id in ["b", "s", "gone", "missing"] {
+            assert!(matches!(get_note(&conn, id), Err(AppError::NotFound(_))), "{}", id);
+        }
+    }
+
+    #[test]
+    fn dtos_serialize_in_camel_case() {
+        let conn = memory_db();
+        insert(&conn, "b", "book", None);
+        insert(&conn, "n", "note", Some("b"));
+
+        let note = serde_json::to_value(get_note(&conn, "n").unwrap()).unwrap();
+        assert_eq!(note["parentId"], "b");
+        assert_eq!(note["type"], "note");
+        let order = serde_json::to_value(SiblingOrder { id: "n".into(), sort_order: "a0".into() }).unwrap();
+        assert_eq!(order["sortOrder"], "a0");
+    }
+
+    #[test]
+    fn counts_are_taken_from_the_plain_text() {
+        let conn = memory_db();
+        insert(&conn, "b", "book", None);
+        let counts = |id: &str| {
+            conn.query_row(
+                "SELECT word_count, character_count FROM items WHERE id = ?1",
+                [id],
+                |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)),
+            )
+            .unwrap()
+        };
+
+        let id = create_note(&conn, "Lunch", Some("b"), "<p>Fish &amp; <em>chips</em></p>").unwrap();
+        assert_eq!(counts(&id), (2, 12));
+        set_content(&conn, &id, "<h1 class=\"title\">Café</h1>").unwrap();
+        assert_eq!(counts(&id), (1, 4));
+    }
+
+    fn ids(siblings: &[SiblingOrder]) -> Vec<&str> {
+        siblings.iter().map(|sibling| sibling.id.as_str()).collect()
+    }
+
+    #[test]
+    fn move_item_reorders_the_root() {
+        let conn = memory_db();
+        for id in ["a", "b", "c"] {
+            insert(&conn, id, "note", None);
+        }
+
+        assert_eq!(ids(&move_item(&conn, "c", None, Some(0)).unwrap()), ["c", "a", "b"]);
+        assert_eq!(ids(&move_item(&conn, "a", None, Some(1)).unwrap()), ["c", "a", "b"]);
+    }
+
+    #[test]
+    fn move_item_down_counts_the_other_siblings() {
+        let conn = memory_db();
+        insert(&conn, "b", "book", None);
+        for id in ["n1", "n2", "n3", "n4"] {
+            insert(&conn, id, "note", Some("b"));
+        }
+
+        // Dropped between n3 and n4: index 2 once n1 is left out
+        let siblings = move_item(&conn, "n1", Some("b"), Some(2)).unwrap();
+        assert_eq!(ids(&siblings), ["n2", "n3", "n1", "n4"]);
+    }
+
+    #[test]
+    fn move_item_appends_past_the_end_and_without_a_position() {
+        let conn = memory_db();
+        insert(&conn, "b", "book", None);
+        insert(&conn, "n1", "note", Some("b"));
+        insert(&conn, "n2", "note", Some("b"));
+        insert(&conn, "loose", "note", None);
+        insert(&conn, "other", "note", None);
+
+        let siblings = move_item(&conn, "loose", Some("b"), Some(99)).unwrap();
+        assert_eq!(ids(&siblings), ["n1", "n2", "loose"]);
+        let siblings = move_item(&conn, "other", Some("b"), None).unwrap();
+        assert_eq!(ids(&siblings), ["n1", "n2", "loose", "other"]);
+
+        let parent: Option<String> = conn
+            .query_row("SELECT parent_id FROM items WHERE id = 'loose'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(parent.as_deref(), Some("b"));
+    }
+
+    #[test]
+    fn move_item_refuses_to_nest_a_section_or_close_a_cycle() {
+        let conn = memory_db();
+        insert(&conn, "b", "book", None);
+        insert(&conn, "s1", "section", Some("b"));
+        insert(&conn, "s2", "section", Some("b"));
+
+        for (id, parent) in [("s1", "s2"), ("s1", "s1"), ("b", "s1")] {
+            assert!(
+                matches!(move_item(&conn, id, Some(parent), None), Err(AppError::InvalidInput(_))),
+                "{} under {}",
+                id,
+                parent
+            );
+        }
+        let parents: Vec<Option<String>> = conn
+            .prepare("SELECT parent_id FROM items ORDER BY id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(parents, [None, Some("b".to_string()), Some("b".to_string())]);
+    }
+}
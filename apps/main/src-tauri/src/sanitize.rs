@@ -0,0 +1,241 @@
+//! HTML sanitizer for imported and pasted content
+//!
+//! Keeps only an allowlist of tags and attributes and drops everything else.
+//! Disallowed tags are unwrapped (their text survives), `<script>`/`<style>`
+//! are removed together with their contents, and event handler (`on*`)
+//! attributes can never be allowlisted (`open`, which is not one, can).
+//!
+//! Allowlist entries are written as `tag` or `tag[attr attr ...]`, e.g.
+//! `a[href title]`. Heading ranges can be written as `h1-h6`.
+
+use std::collections::{HashMap, HashSet};
+
+/// Tags kept when no allowlist is configured
+pub const DEFAULT_ALLOWLIST: &[&str] = &[
+    "p",
+    "b",
+    "strong",
+    "i",
+    "em",
+    "u",
+    "a[href]",
+    "ul",
+    "ol",
+    "li",
+    "h1-h6",
+    "code",
+    "pre",
+    "blockquote",
+    "br",
+];
+
+/// Tags whose contents are dropped along with the tag; never allowlisted
+const CONTENT_STRIPPED_TAGS: &[&str] = &["script", "style"];
+
+/// Attributes that start with `on` but are not event handlers (`<details open>`)
+const NON_EVENT_ON_ATTRIBUTES: &[&str] = &["open"];
+
+/// Every `on*` attribute is treated as an event handler, so one added by a
+/// future HTML version is refused too, except the few known not to be
+fn is_event_handler(attr: &str) -> bool {
+    attr.starts_with("on") && !NON_EVENT_ON_ATTRIBUTES.contains(&attr)
+}
+
+/// Allowed tags mapped to the attributes allowed on each
+#[derive(Debug, Clone)]
+pub struct Allowlist {
+    tags: HashMap<String, HashSet<String>>,
+}
+
+impl Default for Allowlist {
+    fn default() -> Self {
+        let mut allowlist = Allowlist {
+            tags: HashMap::new(),
+        };
+        for spec in DEFAULT_ALLOWLIST {
+            allowlist
+                .keep(spec)
+                .expect("default allowlist entries are valid");
+        }
+        allowlist
+    }
+}
+
+impl Allowlist {
+    /// Default allowlist adjusted by `keep` (added) and `strip` (removed) entries
+    pub fn from_options(keep: &[String], strip: &[String]) -> Result<Self, String> {
+        let mut allowlist = Allowlist::default();
+        for spec in keep {
+            allowlist.keep(spec)?;
+        }
+        for spec in strip {
+            allowlist.strip(spec)?;
+        }
+        Ok(allowlist)
+    }
+
+    /// Allow a tag, or extra attributes on a tag
+    pub fn keep(&mut self, spec: &str) -> Result<(), String> {
+        let (tags, attrs) = parse_spec(spec)?;
+        for tag in tags {
+            if CONTENT_STRIPPED_TAGS.contains(&tag.as_str()) {
+                return Err(format!("<{}> cannot be allowlisted", tag));
+            }
+            self.tags.entry(tag).or_default().extend(attrs.iter().cloned());
+        }
+        Ok(())
+    }
+
+    /// Disallow a tag entirely, or only the listed attributes when given (`a[href]`)
+    pub fn strip(&mut self, spec: &str) -> Result<(), String> {
+        let (tags, attrs) = parse_spec(spec)?;
+        for tag in tags {
+            if attrs.is_empty() {
+                self.tags.remove(&tag);
+            } else if let Some(allowed) = self.tags.get_mut(&tag) {
+                for attr in &attrs {
+                    allowed.remove(attr);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse `tag`, `tag[attr ...]` or `h1-h6[attr ...]` into tags and attributes
+fn parse_spec(spec: &str) -> Result<(Vec<String>, Vec<String>), String> {
+    let spec = spec.trim().to_lowercase();
+    let (tag_part, attrs) = match spec.split_once('[') {
+        Some((tag, rest)) => {
+            let inner = rest
+                .strip_suffix(']')
+                .ok_or_else(|| format!("Invalid allowlist entry '{}': missing ']'", spec))?;
+            let attrs: Vec<String> = inner
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|a| !a.is_empty())
+                .map(str::to_string)
+                .collect();
+            (tag.trim(), attrs)
+        }
+        None => (spec.as_str(), Vec::new()),
+    };
+
+    if let Some(attr) = attrs.iter().find(|a| is_event_handler(a)) {
+        return Err(format!(
+            "Invalid allowlist entry '{}': event handler attribute '{}' is not allowed",
+            spec, attr
+        ));
+    }
+
+    let tags = match tag_part.split_once('-') {
+        Some((from, to)) => expand_heading_range(from, to)
+            .ok_or_else(|| format!("Invalid allowlist entry '{}': bad tag range", spec))?,
+        None => vec![tag_part.to_string()],
+    };
+
+    if tags.iter().any(|t| t.is_empty() || !t.chars().all(|c| c.is_ascii_alphanumeric())) {
+        return Err(format!("Invalid allowlist entry '{}'", spec));
+    }
+
+    Ok((tags, attrs))
+}
+
+/// Expand `h1`-`h6` style ranges
+fn expand_heading_range(from: &str, to: &str) -> Option<Vec<String>> {
+    let start: u8 = from.strip_prefix('h')?.parse().ok()?;
+    let end: u8 = to.strip_prefix('h')?.parse().ok()?;
+    if !(1..=6).contains(&start) || !(start..=6).contains(&end) {
+        return None;
+    }
+    Some((start..=end).map(|n| format!("h{}", n)).collect())
+}
+
+/// Sanitize an HTML fragment against an allowlist
+pub fn sanitize_html(html: &str, allowlist: &Allowlist) -> String {
+    let tags: HashSet<&str> = allowlist.tags.keys().map(String::as_str).collect();
+    let tag_attributes: HashMap<&str, HashSet<&str>> = allowlist
+        .tags
+        .iter()
+        .map(|(tag, attrs)| (tag.as_str(), attrs.iter().map(String::as_str).collect()))
+        .collect();
+
+    ammonia::Builder::empty()
+        .tags(tags)
+        .tag_attributes(tag_attributes)
+        .generic_attributes(HashSet::new())
+        .clean_content_tags(CONTENT_STRIPPED_TAGS.iter().copied().collect())
+        .link_rel(None)
+        .clean(html)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clean(html: &str) -> String {
+        sanitize_html(html, &Allowlist::default())
+    }
+
+    fn clean_with(html: &str, keep: &[&str], strip: &[&str]) -> String {
+        let owned = |specs: &[&str]| specs.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let allowlist = Allowlist::from_options(&owned(keep), &owned(strip)).unwrap();
+        sanitize_html(html, &allowlist)
+    }
+
+    #[test]
+    fn disallowed_tags_are_unwrapped() {
+        assert_eq!(clean("<div><p>a <span>b</span></p></div>"), "<p>a b</p>");
+        assert_eq!(clean("<h2>t</h2><table><tr><td>x</td></tr></table>"), "<h2>t</h2>x");
+    }
+
+    #[test]
+    fn script_and_style_are_removed_with_their_contents() {
+        assert_eq!(clean("<p>a</p><script>alert(1)</script><style>p{}</style>"), "<p>a</p>");
+    }
+
+    #[test]
+    fn event_handlers_and_unlisted_attributes_are_dropped() {
+        assert_eq!(
+            clean("<p onclick=\"x()\" class=\"c\">a</p><a href=\"https://x.org\" onmouseover=\"y()\">l</a>"),
+            "<p>a</p><a href=\"https://x.org\">l</a>"
+        );
+    }
+
+    #[test]
+    fn keep_adds_tags_and_attributes() {
+        assert_eq!(
+            clean_with("<span class=\"k\">a</span><a href=\"/x\" title=\"t\">l</a>", &["span[class]", "a[title]"], &[]),
+            "<span class=\"k\">a</span><a href=\"/x\" title=\"t\">l</a>"
+        );
+    }
+
+    #[test]
+    fn strip_removes_tags_or_single_attributes() {
+        assert_eq!(clean_with("<p><b>a</b> <i>b</i></p>", &[], &["b"]), "<p>a <i>b</i></p>");
+        assert_eq!(clean_with("<a href=\"/x\">l</a>", &[], &["a[href]"]), "<a>l</a>");
+        assert_eq!(clean_with("<h1>a</h1><h4>b</h4>", &[], &["h2-h6"]), "<h1>a</h1>b");
+    }
+
+    #[test]
+    fn event_handlers_and_script_cannot_be_kept() {
+        let mut allowlist = Allowlist::default();
+        assert!(allowlist.keep("p[onclick]").unwrap_err().contains("event handler"));
+        assert!(allowlist.keep("details[onToggle]").unwrap_err().contains("event handler"));
+        assert!(allowlist.keep("script").unwrap_err().contains("cannot be allowlisted"));
+        assert!(allowlist.keep("a[href").is_err());
+        assert!(allowlist.keep("h0-h9").is_err());
+    }
+
+    #[test]
+    fn open_is_not_an_event_handler() {
+        assert_eq!(
+            clean_with(
+                "<details open ontoggle=\"x()\"><summary>s</summary>b</details>",
+                &["details[open]", "summary"],
+                &[]
+            ),
+            "<details open=\"\"><summary>s</summary>b</details>"
+        );
+    }
+}
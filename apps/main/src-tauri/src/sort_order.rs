@@ -0,0 +1,464 @@
+//! Sibling ordering for the items table
+//!
+//! `sort_order` holds fractional-index keys generated by the frontend with the
+//! `fractional-indexing` package (`a0`, `a1`, `a0V`, ...). `key_between` is a
+//! port of its `generateKeyBetween`, so keys written here interleave with keys
+//! written by the GUI. Keys compare as plain ASCII strings (0-9 < A-Z < a-z).
+
+use rusqlite::{params, Connection};
+
+const DIGITS: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+const ZERO: u8 = DIGITS[0];
+const SMALLEST_INTEGER: &str = "A00000000000000000000000000";
+
+fn digit_index(c: u8) -> Result<usize, String> {
+    DIGITS
+        .iter()
+        .position(|&d| d == c)
+        .ok_or_else(|| format!("Invalid sort key digit '{}'", c as char))
+}
+
+/// Length of the integer part, encoded by its head character
+fn integer_length(head: u8) -> Result<usize, String> {
+    match head {
+        b'a'..=b'z' => Ok((head - b'a') as usize + 2),
+        b'A'..=b'Z' => Ok((b'Z' - head) as usize + 2),
+        _ => Err(format!("Invalid sort key head '{}'", head as char)),
+    }
+}
+
+fn integer_part(key: &str) -> Result<&str, String> {
+    let head = *key.as_bytes().first().ok_or("Empty sort key")?;
+    let len = integer_length(head)?;
+    key.get(..len)
+        .ok_or_else(|| format!("Invalid sort key '{}'", key))
+}
+
+fn validate_key(key: &str) -> Result<(), String> {
+    if key == SMALLEST_INTEGER {
+        return Err(format!("Invalid sort key '{}'", key));
+    }
+    let int = integer_part(key)?;
+    if key.as_bytes()[int.len()..].last() == Some(&ZERO) {
+        return Err(format!("Invalid sort key '{}': trailing zero", key));
+    }
+    Ok(())
+}
+
+/// Key strictly between two fractional parts (`b = None` means no upper bound)
+fn midpoint(a: &[u8], b: Option<&[u8]>) -> Result<Vec<u8>, String> {
+    if let Some(b) = b {
+        // Shared prefix (a padded with zeros) carries over unchanged
+        let mut n = 0;
+        while n < b.len() && a.get(n).copied().unwrap_or(ZERO) == b[n] {
+            n += 1;
+        }
+        if n > 0 {
+            let mut result = b[..n].to_vec();
+            result.extend(midpoint(a.get(n..).unwrap_or(&[]), Some(&b[n..]))?);
+            return Ok(result);
+        }
+    }
+
+    let digit_a = match a.first() {
+        Some(&c) => digit_index(c)?,
+        None => 0,
+    };
+    let digit_b = match b {
+        Some(b) => digit_index(*b.first().ok_or("Sort keys out of order")?)?,
+        None => DIGITS.len(),
+    };
+
+    if digit_b - digit_a > 1 {
+        let mid = (digit_a + digit_b).div_ceil(2);
+        Ok(vec![DIGITS[mid]])
+    } else if let Some(b) = b.filter(|b| b.len() > 1) {
+        Ok(vec![b[0]])
+    } else {
+        let mut result = vec![DIGITS[digit_a]];
+        result.extend(midpoint(a.get(1..).unwrap_or(&[]), None)?);
+        Ok(result)
+    }
+}
+
+fn increment_integer(int: &str) -> Option<String> {
+    let bytes = int.as_bytes();
+    let head = bytes[0];
+    let mut digits = bytes[1..].to_vec();
+
+    for d in digits.iter_mut().rev() {
+        let next = DIGITS.iter().position(|&c| c == *d)? + 1;
+        if next < DIGITS.len() {
+            *d = DIGITS[next];
+            return Some(format!(
+                "{}{}",
+                head as char,
+                String::from_utf8_lossy(&digits)
+            ));
+        }
+        *d = ZERO;
+    }
+
+    // Carried out of every digit: grow into the next head
+    match head {
+        b'Z' => Some(format!("a{}", ZERO as char)),
+        b'z' => None,
+        _ => {
+            let head = head + 1;
+            if head > b'a' {
+                digits.push(ZERO);
+            } else {
+                digits.pop();
+            }
+            Some(format!(
+                "{}{}",
+                head as char,
+                String::from_utf8_lossy(&digits)
+            ))
+        }
+    }
+}
+
+fn decrement_integer(int: &str) -> Option<String> {
+    let bytes = int.as_bytes();
+    let head = bytes[0];
+    let mut digits = bytes[1..].to_vec();
+    let max = DIGITS[DIGITS.len() - 1];
+
+    for d in digits.iter_mut().rev() {
+        let index = DIGITS.iter().position(|&c| c == *d)?;
+        if index > 0 {
+            *d = DIGITS[index - 1];
+            return Some(format!(
+                "{}{}",
+                head as char,
+                String::from_utf8_lossy(&digits)
+            ));
+        }
+        *d = max;
+    }
+
+    match head {
+        b'a' => Some(format!("Z{}", max as char)),
+        b'A' => None,
+        _ => {
+            let head = head - 1;
+            if head < b'Z' {
+                digits.push(max);
+            } else {
+                digits.pop();
+            }
+            Some(format!(
+                "{}{}",
+                head as char,
+                String::from_utf8_lossy(&digits)
+            ))
+        }
+    }
+}
+
+/// Generate a key sorting strictly between `a` and `b` (`None` = unbounded)
+pub fn key_between(a: Option<&str>, b: Option<&str>) -> Result<String, String> {
+    if let Some(a) = a {
+        validate_key(a)?;
+    }
+    if let Some(b) = b {
+        validate_key(b)?;
+    }
+
+    match (a, b) {
+        (Some(a), Some(b)) if a >= b => Err(format!("Sort keys out of order: '{}' >= '{}'", a, b)),
+        (None, None) => Ok(format!("a{}", ZERO as char)),
+        (None, Some(b)) => {
+            let ib = integer_part(b)?;
+            let fb = &b[ib.len()..];
+            if ib == SMALLEST_INTEGER {
+                let mid = midpoint(b"", Some(fb.as_bytes()))?;
+                return Ok(format!("{}{}", ib, String::from_utf8_lossy(&mid)));
+            }
+            if ib < b {
+                return Ok(ib.to_string());
+            }
+            decrement_integer(ib).ok_or_else(|| "Cannot sort before the smallest key".to_string())
+        }
+        (Some(a), None) => {
+            let ia = integer_part(a)?;
+            let fa = &a[ia.len()..];
+            match increment_integer(ia) {
+                Some(next) => Ok(next),
+                None => {
+                    let mid = midpoint(fa.as_bytes(), None)?;
+                    Ok(format!("{}{}", ia, String::from_utf8_lossy(&mid)))
+                }
+            }
+        }
+        (Some(a), Some(b)) => {
+            let ia = integer_part(a)?;
+            let fa = &a[ia.len()..];
+            let ib = integer_part(b)?;
+            let fb = &b[ib.len()..];
+            if ia == ib {
+                let mid = midpoint(fa.as_bytes(), Some(fb.as_bytes()))?;
+                return Ok(format!("{}{}", ia, String::from_utf8_lossy(&mid)));
+            }
+            let next = increment_integer(ia).ok_or("Cannot sort after the largest key")?;
+            if next.as_str() < b {
+                return Ok(next);
+            }
+            let mid = midpoint(fa.as_bytes(), None)?;
+            Ok(format!("{}{}", ia, String::from_utf8_lossy(&mid)))
+        }
+    }
+}
+
+/// Where a new or moved item goes among its siblings
+#[derive(Debug, Clone, Copy)]
+pub enum Placement<'a> {
+    /// After the last sibling (the GUI's default for new items)
+    Append,
+    /// Directly before the sibling with this ID
+    Before(&'a str),
+    /// Directly after the sibling with this ID
+    After(&'a str),
+    /// At this index among the other siblings (the tree's drop position);
+    /// past the end appends
+    At(usize),
+}
+
+/// Live children of `parent_id` as (id, sort_order), in display order
+pub fn siblings(
+    conn: &Connection,
+    parent_id: Option<&str>,
+    exclude_id: Option<&str>,
+) -> Result<Vec<(String, String)>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, sort_order FROM items
+             WHERE parent_id IS ?1 AND deleted_at IS NULL AND id IS NOT ?2
+             ORDER BY sort_order, id",
+        )
+        .map_err(|e| format!("Failed to read siblings: {}", e))?;
+    let rows = stmt
+        .query_map(params![parent_id, exclude_id], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })
+        .map_err(|e| format!("Failed to read siblings: {}", e))?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| format!("Failed to read siblings: {}", e))?;
+    Ok(rows)
+}
+
+/// Give every sibling a fresh, strictly increasing key in its current order.
+/// Needed when legacy rows share a key, leaving no room between them.
+fn renumber_siblings(conn: &Connection, siblings: &mut [(String, String)]) -> Result<(), String> {
+    let mut previous: Option<String> = None;
+    for (id, sort_order) in siblings.iter_mut() {
+        let id = &*id;
+        let key = key_between(previous.as_deref(), None)?;
+        conn.execute(
+            "UPDATE items SET sort_order = ?1 WHERE id = ?2",
+            params![key, id],
+        )
+        .map_err(|e| format!("Failed to reorder siblings: {}", e))?;
+        *sort_order = key.clone();
+        previous = Some(key);
+    }
+    Ok(())
+}
+
+/// Find a sibling under `parent_id` by ID, or by case-insensitive title
+pub fn find_sibling(
+    conn: &Connection,
+    parent_id: Option<&str>,
+    id_or_title: &str,
+) -> Result<String, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id FROM items
+             WHERE parent_id IS ?1 AND deleted_at IS NULL
+               AND (id = ?2 OR LOWER(title) = LOWER(?2))
+             ORDER BY id = ?2 DESC, sort_order",
+        )
+        .map_err(|e| format!("Failed to look up sibling: {}", e))?;
+    let ids = stmt
+        .query_map(params![parent_id, id_or_title], |row| {
+            row.get::<_, String>(0)
+        })
+        .map_err(|e| format!("Failed to look up sibling: {}", e))?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| format!("Failed to look up sibling: {}", e))?;
+
+    match ids.as_slice() {
+        [] => Err(format!(
+            "No sibling '{}' found under the target parent",
+            id_or_title
+        )),
+        [id] => Ok(id.clone()),
+        [first, ..] if first == id_or_title => Ok(first.clone()),
+        _ => Err(format!(
+            "Several siblings are titled '{}'; pass an ID instead",
+            id_or_title
+        )),
+    }
+}
+
+/// Compute the `sort_order` for an item placed under `parent_id`.
+/// `item_id` is the item being moved (excluded from its own siblings), if any.
+/// May renumber siblings to make room, so call it inside the caller's transaction.
+pub fn sort_order_for(
+    conn: &Connection,
+    parent_id: Option<&str>,
+    placement: Placement,
+    item_id: Option<&str>,
+) -> Result<String, String> {
+    let mut siblings = siblings(conn, parent_id, item_id)?;
+
+    let bounds =
+        |siblings: &[(String, String)]| -> Result<(Option<String>, Option<String>), String> {
+            let position = |anchor: &str| {
+                siblings
+                    .iter()
+                    .position(|(id, _)| id == anchor)
+                    .ok_or_else(|| {
+                        format!("Item '{}' is not a sibling under the target parent", anchor)
+                    })
+            };
+            let key_at = |i: usize| siblings.get(i).map(|(_, key)| key.clone());
+            match placement {
+                Placement::Append => Ok((siblings.last().map(|(_, key)| key.clone()), None)),
+                Placement::Before(anchor) => {
+                    let i = position(anchor)?;
+                    Ok((i.checked_sub(1).and_then(key_at), key_at(i)))
+                }
+                Placement::After(anchor) => {
+                    let i = position(anchor)?;
+                    Ok((key_at(i), key_at(i + 1)))
+                }
+                Placement::At(i) => {
+                    let i = i.min(siblings.len());
+                    Ok((i.checked_sub(1).and_then(key_at), key_at(i)))
+                }
+            }
+        };
+
+    let (before, after) = bounds(&siblings)?;
+    if let Ok(key) = key_between(before.as_deref(), after.as_deref()) {
+        return Ok(key);
+    }
+
+    // Neighbours share a key (or hold a malformed one): spread them out and retry
+    renumber_siblings(conn, &mut siblings)?;
+    let (before, after) = bounds(&siblings)?;
+    key_between(before.as_deref(), after.as_deref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{insert, memory_db};
+
+    /// Move `id` among its siblings the way `move_item` and `reorder` do
+    fn place(conn: &Connection, id: &str, placement: Placement) {
+        let parent_id: Option<String> = conn
+            .query_row("SELECT parent_id FROM items WHERE id = ?1", [id], |row| row.get(0))
+            .unwrap();
+        let key = sort_order_for(conn, parent_id.as_deref(), placement, Some(id)).unwrap();
+        conn.execute("UPDATE items SET sort_order = ?1 WHERE id = ?2", params![key, id])
+            .unwrap();
+    }
+
+    /// Sibling IDs in display order, checking their keys strictly increase
+    fn order(conn: &Connection, parent_id: Option<&str>) -> Vec<String> {
+        let siblings = siblings(conn, parent_id, None).unwrap();
+        for pair in siblings.windows(2) {
+            assert!(pair[0].1 < pair[1].1, "{:?} not below {:?}", pair[0], pair[1]);
+        }
+        siblings.into_iter().map(|(id, _)| id).collect()
+    }
+
+    #[test]
+    fn key_between_matches_fractional_indexing() {
+        assert_eq!(key_between(None, None).unwrap(), "a0");
+        assert_eq!(key_between(Some("a0"), None).unwrap(), "a1");
+        assert_eq!(key_between(None, Some("a0")).unwrap(), "Zz");
+        assert_eq!(key_between(Some("a0"), Some("a1")).unwrap(), "a0V");
+        assert_eq!(key_between(Some("az"), None).unwrap(), "b00");
+        assert!(key_between(Some("a1"), Some("a0")).is_err());
+    }
+
+    #[test]
+    fn appended_items_keep_insertion_order() {
+        let conn = memory_db();
+        insert(&conn, "b", "book", None);
+        for id in ["n1", "n2", "n3"] {
+            insert(&conn, id, "note", Some("b"));
+        }
+        let keys: Vec<String> = siblings(&conn, Some("b"), None)
+            .unwrap()
+            .into_iter()
+            .map(|(_, key)| key)
+            .collect();
+        assert_eq!(keys, ["a0", "a1", "a2"]);
+    }
+
+    #[test]
+    fn moves_to_either_end_and_between_siblings() {
+        let conn = memory_db();
+        insert(&conn, "b", "book", None);
+        for id in ["n1", "n2", "n3", "n4"] {
+            insert(&conn, id, "note", Some("b"));
+        }
+
+        place(&conn, "n3", Placement::Before("n1"));
+        assert_eq!(order(&conn, Some("b")), ["n3", "n1", "n2", "n4"]);
+        place(&conn, "n1", Placement::Append);
+        assert_eq!(order(&conn, Some("b")), ["n3", "n2", "n4", "n1"]);
+        place(&conn, "n4", Placement::At(0));
+        assert_eq!(order(&conn, Some("b")), ["n4", "n3", "n2", "n1"]);
+        place(&conn, "n4", Placement::After("n1"));
+        assert_eq!(order(&conn, Some("b")), ["n3", "n2", "n1", "n4"]);
+        place(&conn, "n2", Placement::At(99));
+        assert_eq!(order(&conn, Some("b")), ["n3", "n1", "n4", "n2"]);
+        place(&conn, "n2", Placement::At(1));
+        assert_eq!(order(&conn, Some("b")), ["n3", "n2", "n1", "n4"]);
+        // Placing next to itself leaves the order alone
+        place(&conn, "n1", Placement::After("n2"));
+        assert_eq!(order(&conn, Some("b")), ["n3", "n2", "n1", "n4"]);
+
+        let keys: Vec<String> = siblings(&conn, Some("b"), None)
+            .unwrap()
+            .into_iter()
+            .map(|(_, key)| key)
+            .collect();
+        assert_eq!(keys, ["Zz", "a0", "a1", "a5"]);
+    }
+
+    #[test]
+    fn repeated_moves_between_the_same_neighbours_keep_ordering() {
+        let conn = memory_db();
+        insert(&conn, "b", "book", None);
+        for id in ["first", "last", "x", "y"] {
+            insert(&conn, id, "note", Some("b"));
+        }
+        for _ in 0..20 {
+            place(&conn, "x", Placement::After("first"));
+            place(&conn, "y", Placement::Before("x"));
+        }
+        assert_eq!(order(&conn, Some("b")), ["first", "y", "x", "last"]);
+    }
+
+    #[test]
+    fn shared_keys_are_renumbered_to_make_room() {
+        let conn = memory_db();
+        insert(&conn, "b", "book", None);
+        for id in ["n1", "n2", "n3"] {
+            insert(&conn, id, "note", Some("b"));
+        }
+        // Legacy rows with one key for everything
+        conn.execute("UPDATE items SET sort_order = 'a0' WHERE parent_id = 'b'", [])
+            .unwrap();
+
+        place(&conn, "n3", Placement::Before("n2"));
+        assert_eq!(order(&conn, Some("b")), ["n1", "n3", "n2"]);
+    }
+}
@@ -0,0 +1,211 @@
+//! Unicode box-drawing tables for CLI output
+//!
+//! Column widths are measured in terminal cells (`unicode-width`), so CJK and
+//! other wide characters line up. When the table is wider than the terminal,
+//! the widest columns are shrunk first and their cells truncated with `…`.
+
+use colored::Colorize;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Columns are never shrunk below this many cells
+const MIN_COLUMN_WIDTH: usize = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Right,
+}
+
+pub struct Column {
+    pub header: &'static str,
+    pub align: Align,
+}
+
+impl Column {
+    pub fn left(header: &'static str) -> Self {
+        Self {
+            header,
+            align: Align::Left,
+        }
+    }
+
+    pub fn right(header: &'static str) -> Self {
+        Self {
+            header,
+            align: Align::Right,
+        }
+    }
+}
+
+/// Width of the terminal stdout is attached to, if any
+pub fn terminal_width() -> Option<usize> {
+    terminal_size::terminal_size().map(|(terminal_size::Width(w), _)| w as usize)
+}
+
+/// Truncate to at most `max_width` cells, never splitting a character
+pub fn truncate_to_width(s: &str, max_width: usize) -> String {
+    if s.width() <= max_width {
+        return s.to_string();
+    }
+    if max_width == 0 {
+        return String::new();
+    }
+
+    // Reserve one cell for the ellipsis
+    let mut result = String::new();
+    let mut width = 0;
+    for c in s.chars() {
+        let w = c.width().unwrap_or(0);
+        if width + w > max_width - 1 {
+            break;
+        }
+        result.push(c);
+        width += w;
+    }
+    result.push('…');
+    result
+}
+
+fn pad(s: &str, width: usize, align: Align) -> String {
+    let fill = " ".repeat(width.saturating_sub(s.width()));
+    match align {
+        Align::Left => format!("{}{}", s, fill),
+        Align::Right => format!("{}{}", fill, s),
+    }
+}
+
+/// Natural column widths, shrunk (widest first) until the table fits `max_width`
+fn fit_widths(columns: &[Column], rows: &[Vec<String>], max_width: Option<usize>) -> Vec<usize> {
+    let mut widths: Vec<usize> = columns.iter().map(|c| c.header.width()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.width());
+        }
+    }
+
+    let Some(max_width) = max_width else {
+        return widths;
+    };
+
+    // Each column costs "│ " + content + " ", plus the closing "│"
+    let overhead = columns.len() * 3 + 1;
+    while widths.iter().sum::<usize>() + overhead > max_width {
+        let Some(widest) = widths
+            .iter_mut()
+            .filter(|w| **w > MIN_COLUMN_WIDTH)
+            .max_by_key(|w| **w)
+        else {
+            break;
+        };
+        *widest -= 1;
+    }
+    widths
+}
+
+fn border(widths: &[usize], left: &str, mid: &str, right: &str) -> String {
+    let segments: Vec<String> = widths.iter().map(|w| "─".repeat(w + 2)).collect();
+    format!("{}{}{}", left, segments.join(mid), right)
+}
+
+/// Render rows as a bordered table, fitting `max_width` cells when given
+pub fn render(columns: &[Column], rows: &[Vec<String>], max_width: Option<usize>) -> String {
+    let widths = fit_widths(columns, rows, max_width);
+    let bar = "│".dimmed().to_string();
+    let mut lines = Vec::with_capacity(rows.len() + 4);
+
+    let format_row = |cells: Vec<String>| -> String {
+        let inner: Vec<String> = cells.iter().map(|cell| format!(" {} ", cell)).collect();
+        format!("{}{}{}", bar, inner.join(&bar), bar)
+    };
+
+    lines.push(border(&widths, "┌", "┬", "┐").dimmed().to_string());
+    lines.push(format_row(
+        columns
+            .iter()
+            .zip(&widths)
+            .map(|(column, &width)| pad(column.header, width, column.align).bold().to_string())
+            .collect(),
+    ));
+    lines.push(border(&widths, "├", "┼", "┤").dimmed().to_string());
+    for row in rows {
+        lines.push(format_row(
+            columns
+                .iter()
+                .zip(&widths)
+                .zip(row)
+                .map(|((column, &width), cell)| {
+                    pad(&truncate_to_width(cell, width), width, column.align)
+                })
+                .collect(),
+        ));
+    }
+    lines.push(border(&widths, "└", "┴", "┘").dimmed().to_string());
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(cells: &[&[&str]]) -> Vec<Vec<String>> {
+        cells
+            .iter()
+            .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn truncate_to_width_counts_cells_and_keeps_characters_whole() {
+        assert_eq!(truncate_to_width("short", 10), "short");
+        assert_eq!(truncate_to_width("exactly", 7), "exactly");
+        assert_eq!(truncate_to_width("truncated", 6), "trunc…");
+        assert_eq!(truncate_to_width("anything", 0), "");
+        assert_eq!(truncate_to_width("café au lait", 5), "café…");
+        // Each CJK character takes two cells; one that would straddle the limit is dropped
+        assert_eq!(truncate_to_width("日本語テキスト", 6), "日本…");
+        assert_eq!(truncate_to_width("日本語テキスト", 7), "日本語…");
+    }
+
+    #[test]
+    fn widths_fit_the_widest_cell_or_header() {
+        let columns = [Column::left("Title"), Column::right("Words")];
+        let rows = rows(&[&["A longer title", "3"], &["日本語", "12345678"]]);
+        assert_eq!(fit_widths(&columns, &rows, None), [14, 8]);
+    }
+
+    #[test]
+    fn the_widest_column_shrinks_first_and_not_below_the_minimum() {
+        let columns = [Column::left("Path"), Column::left("Title")];
+        let rows = rows(&[&["a/very/long/path/to/the/note", "Short title"]]);
+        // Natural widths 28 and 11, plus 7 cells of borders and padding
+        assert_eq!(fit_widths(&columns, &rows, Some(46)), [28, 11]);
+        assert_eq!(fit_widths(&columns, &rows, Some(36)), [18, 11]);
+        assert_eq!(fit_widths(&columns, &rows, Some(20)), [7, 6]);
+        assert_eq!(fit_widths(&columns, &rows, Some(5)), [6, 6]);
+    }
+
+    #[test]
+    fn render_pads_aligns_and_truncates_cells() {
+        colored::control::set_override(false);
+        let columns = [Column::left("Title"), Column::right("Words")];
+        let rows = rows(&[&["Café", "7"], &["A title far too long to fit", "1234"]]);
+
+        let table = render(&columns, &rows, Some(25));
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "┌───────────────┬───────┐",
+                "│ Title         │ Words │",
+                "├───────────────┼───────┤",
+                "│ Café          │     7 │",
+                "│ A title far … │  1234 │",
+                "└───────────────┴───────┘",
+            ]
+        );
+        for line in &lines {
+            assert!(line.width() <= 25, "{}", line);
+        }
+    }
+}
@@ -0,0 +1,66 @@
+//! Fixtures shared by the unit tests
+
+use rusqlite::{params, Connection};
+
+/// An in-memory database with the current schema (schema/base.sql plus migrations)
+pub fn memory_db() -> Connection {
+    let conn = Connection::open_in_memory().unwrap();
+    crate::migrations::migrate(&conn).unwrap();
+    conn
+}
+
+/// Insert an item titled `id` after its siblings, bypassing hierarchy checks
+pub fn insert(conn: &Connection, id: &str, item_type: &str, parent_id: Option<&str>) {
+    let sort_order = crate::sort_order::sort_order_for(
+        conn,
+        parent_id,
+        crate::sort_order::Placement::Append,
+        None,
+    )
+    .unwrap();
+    conn.execute(
+        "INSERT INTO items (id, type, title, parent_id, sort_order) VALUES (?1, ?2, ?1, ?3, ?4)",
+        params![id, item_type, parent_id, sort_order],
+    )
+    .unwrap();
+}
+
+/// A fresh directory under the system temp dir, removed on drop
+pub struct TempDir(pub std::path::PathBuf);
+
+impl TempDir {
+    pub fn new() -> Self {
+        let path = std::env::temp_dir().join(format!("irisnotes-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Run `f` with each environment variable set (or removed for `None`),
+/// restoring the previous values afterwards. The environment is
+/// process-wide, so callers take turns.
+pub fn with_env<T>(vars: &[(&str, Option<&str>)], f: impl FnOnce() -> T) -> T {
+    static ENV_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    let _guard = ENV_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let saved: Vec<_> = vars.iter().map(|(name, _)| (*name, std::env::var_os(name))).collect();
+    for (name, value) in vars {
+        match value {
+            Some(value) => std::env::set_var(name, value),
+            None => std::env::remove_var(name),
+        }
+    }
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(f));
+    for (name, value) in saved {
+        match value {
+            Some(value) => std::env::set_var(name, value),
+            None => std::env::remove_var(name),
+        }
+    }
+    result.unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+}
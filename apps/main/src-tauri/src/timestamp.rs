@@ -0,0 +1,67 @@
+//! Timestamp display for CLI output
+//!
+//! Items store SQLite `datetime('now')` strings (`2024-01-15 10:30:00`, UTC),
+//! but synced or imported rows may carry ISO-8601/RFC 3339 strings or Unix
+//! epochs. `format_timestamp` accepts all of these and renders them with the
+//! configured strftime-style `date_format`, defaulting to ISO-8601.
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+
+/// Used when neither `--date-format` nor `[cli] date_format` is set
+pub const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%SZ";
+
+/// Epoch values above this are taken as milliseconds (year 5138 in seconds)
+const EPOCH_MILLIS_THRESHOLD: i64 = 100_000_000_000;
+
+/// Reject strftime patterns chrono cannot render, so a typo fails up front
+pub fn validate_date_format(format: &str) -> Result<(), String> {
+    if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+        return Err(format!("Invalid date format '{}'", format));
+    }
+    Ok(())
+}
+
+/// Parse a stored timestamp (SQLite datetime, ISO-8601, or epoch s/ms) as UTC
+pub fn parse_timestamp(raw: &str) -> Option<DateTime<Utc>> {
+    let raw = raw.trim();
+
+    if let Ok(epoch) = raw.parse::<i64>() {
+        return if epoch.abs() >= EPOCH_MILLIS_THRESHOLD {
+            DateTime::from_timestamp_millis(epoch)
+        } else {
+            DateTime::from_timestamp(epoch, 0)
+        };
+    }
+
+    if let Ok(parsed) = DateTime::parse_from_rfc3339(raw) {
+        return Some(parsed.with_timezone(&Utc));
+    }
+
+    // Naive forms are stored in UTC
+    for pattern in [
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M",
+    ] {
+        if let Ok(parsed) = NaiveDateTime::parse_from_str(raw, pattern) {
+            return Some(parsed.and_utc());
+        }
+    }
+
+    NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|datetime| datetime.and_utc())
+}
+
+/// Render a stored timestamp for display. Unparseable values are shown as stored.
+pub fn format_timestamp(raw: &str, format: Option<&str>) -> String {
+    let format = format.unwrap_or(DEFAULT_DATE_FORMAT);
+    match parse_timestamp(raw) {
+        Some(datetime) if validate_date_format(format).is_ok() => {
+            datetime.format(format).to_string()
+        }
+        _ => raw.to_string(),
+    }
+}
@@ -7,6 +7,13 @@ export const quickSearchQueryAtom = atom<string>("");
 // Full-text search sidebar (Ctrl+Shift+F) - search in content
 export const fullTextSearchQueryAtom = atom<string>("");
 
+// Term to highlight in a note once its editor opens (set by the quick app handoff)
+export interface PendingNoteHighlight {
+	noteId: string;
+	term: string;
+}
+export const pendingNoteHighlightAtom = atom<PendingNoteHighlight | null>(null);
+
 // Sidebar view mode: "tree" | "search"
 export type SidebarViewMode = "tree" | "search";
 export const sidebarViewModeAtom = atom<SidebarViewMode>("tree");
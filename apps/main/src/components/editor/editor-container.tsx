@@ -1,5 +1,7 @@
 import type React from "react";
-import { useRef } from "react";
+import { useCallback, useRef } from "react";
+import { useAtom } from "jotai";
+import { pendingNoteHighlightAtom } from "@/atoms/search";
 import { useRightClickMenu, useRightClickMenuActions, useEditorZoom } from "@/hooks";
 import { RightClickMenu } from "../right-click-menu";
 import type { EditorRightClickData } from "@/types/right-click-menu";
@@ -42,6 +44,14 @@ export const EditorContainer: React.FC<EditorContainerProps> = ({
 	// Enable Ctrl+scroll wheel zoom
 	useEditorZoom(containerRef);
 
+	// Term handed over from the quick app for this note, cleared once shown
+	const [pendingHighlight, setPendingHighlight] = useAtom(pendingNoteHighlightAtom);
+	const highlightTerm =
+		noteId && pendingHighlight?.noteId === noteId ? pendingHighlight.term : undefined;
+	const handleHighlightApplied = useCallback(() => {
+		setPendingHighlight(null);
+	}, [setPendingHighlight]);
+
 	const handleEditorRightClick = (event: React.MouseEvent) => {
 		const rightClickData: EditorRightClickData = {
 			noteId,
@@ -82,6 +92,8 @@ export const EditorContainer: React.FC<EditorContainerProps> = ({
 						toolbarVisible={toolbarVisible}
 						autoFocus={autoFocus}
 						titleBar={titleBar}
+						highlightTerm={highlightTerm}
+						onHighlightApplied={handleHighlightApplied}
 					/>
 				)}
 				<ZoomIndicator />
@@ -5,7 +5,7 @@ import { EditorView } from "prosemirror-view";
 import { DOMParser, DOMSerializer } from "prosemirror-model";
 import { useSetAtom, useAtomValue } from "jotai";
 import { invoke } from "@tauri-apps/api/core";
-import { useLineWrapping } from "@/hooks";
+import { useConfig, useLineWrapping } from "@/hooks";
 import { editorCursorPositionStore } from "@/hooks/use-editor-view-toggle";
 import { activeEditorViewStore } from "./active-editor-view-store";
 import { editorStatsAtom } from "@/atoms/editor-stats";
@@ -28,6 +28,7 @@ import {
 	type LinkDialogState,
 } from "./plugins/autolink";
 import { DIRECT_TEXT_COLORS, DIRECT_HIGHLIGHT_COLORS } from "./format-constants";
+import { errorMessage } from "@/utils/app-error";
 import { CodeBlockView,  detectLanguage } from "./codemirror-nodeview";
 import { editorSchema } from "./schema";
 import { DetailsNodeView } from "./plugins/details-nodeview";
@@ -49,6 +50,8 @@ import {
 	shouldLocalizeImageSrc,
 } from "./image-utils";
 import type { EditorKeybindings } from "@/config/default-editor-keybindings";
+import type { SanitizeOptions } from "@/types";
+import { sanitizeHtml } from "@/utils/sanitize-html";
 import { open } from "@tauri-apps/plugin-dialog";
 import "prosemirror-view/style/prosemirror.css";
 import "@/styles/prosemirror.css";
@@ -106,6 +109,9 @@ interface ProseMirrorEditorProps {
 	initialCursorPosition?: number;
 	autoFocus?: boolean;
 	titleBar?: ReactNode;
+	/** Open find-in-note with this term (scrolls to the first match) */
+	highlightTerm?: string;
+	onHighlightApplied?: () => void;
 }
 
 export function ProseMirrorEditor({
@@ -116,6 +122,8 @@ export function ProseMirrorEditor({
 	initialCursorPosition,
 	autoFocus = false,
 	titleBar,
+	highlightTerm,
+	onHighlightApplied,
 }: ProseMirrorEditorProps) {
 	const editorRef = useRef<HTMLDivElement>(null);
 	const viewRef = useRef<EditorView | null>(null);
@@ -126,8 +134,11 @@ export function ProseMirrorEditor({
 	const setEditorStatsRef = useRef(setEditorStats);
 	const editorKeybindings = useAtomValue(editorKeybindingsAtom);
 	const editorKeybindingsRef = useRef<EditorKeybindings>(editorKeybindings);
+	const { config } = useConfig();
+	const pasteSanitizerRef = useRef<SanitizeOptions | undefined>(config.editor.pasteSanitizer);
 	const pendingImageLocalizationsRef = useRef<Set<string>>(new Set());
 	const [showSearch, setShowSearch] = useState(false);
+	const [searchInitialQuery, setSearchInitialQuery] = useState<string | undefined>();
 	const [activePicker, setActivePicker] = useState<FormatPickerType | null>(null);
 	const [errorToast, setErrorToast] = useState<string | null>(null);
 	const [showTableDialog, setShowTableDialog] = useState(false);
@@ -142,8 +153,17 @@ export function ProseMirrorEditor({
 
 	const handleSearchClose = useCallback(() => {
 		setShowSearch(false);
+		setSearchInitialQuery(undefined);
 	}, []);
 
+	// Show a handed-over term through the find bar, which highlights every match
+	useEffect(() => {
+		if (!highlightTerm || !viewRef.current) return;
+		setSearchInitialQuery(highlightTerm);
+		setShowSearch(true);
+		onHighlightApplied?.();
+	}, [highlightTerm, onHighlightApplied]);
+
 	const showError = useCallback((message: string) => {
 		setErrorToast(message);
 		setTimeout(() => setErrorToast(null), 4000);
@@ -159,6 +179,11 @@ export function ProseMirrorEditor({
 		editorKeybindingsRef.current = editorKeybindings;
 	}, [editorKeybindings]);
 
+	// Keep paste sanitizer settings current for the paste handler
+	useEffect(() => {
+		pasteSanitizerRef.current = config.editor.pasteSanitizer;
+	}, [config.editor.pasteSanitizer]);
+
 	// Helper to calculate editor stats from state
 	const calculateAndUpdateStats = (state: EditorState) => {
 		const { doc, selection } = state;
@@ -253,7 +278,7 @@ export function ProseMirrorEditor({
 					}
 				} catch (err) {
 					console.error("Failed to insert image from text:", err);
-					showError(`Failed to insert image: ${err}`);
+					showError(`Failed to insert image: ${errorMessage(err)}`);
 				}
 			})();
 
@@ -557,7 +582,7 @@ export function ProseMirrorEditor({
 							))
 							.catch((err) => {
 								console.error("Failed to paste clipboard image:", err);
-								showError(`Failed to paste image: ${err}`);
+								showError(`Failed to paste image: ${errorMessage(err)}`);
 							});
 						return true;
 					}
@@ -572,7 +597,7 @@ export function ProseMirrorEditor({
 						})
 						.catch((err) => {
 							console.error("Failed to paste clipboard image:", err);
-							showError(`Failed to paste image: ${err}`);
+							showError(`Failed to paste image: ${errorMessage(err)}`);
 						});
 					return true;
 				}
@@ -589,6 +614,29 @@ export function ProseMirrorEditor({
 				}
 
 				const html = event.clipboardData?.getData("text/html");
+
+				// 3. Allowlist sanitizer (opt-in via editor.pasteSanitizer in config)
+				const pasteSanitizer = pasteSanitizerRef.current;
+				if (html && pasteSanitizer) {
+					event.preventDefault();
+					sanitizeHtml(normalizePastedHtml(html), pasteSanitizer)
+						.then((clean) => {
+							const contentDiv = document.createElement("div");
+							contentDiv.innerHTML = clean;
+							const slice = DOMParser.fromSchema(mySchema).parseSlice(contentDiv, {
+								preserveWhitespace: true,
+							});
+							pasteView.dispatch(
+								pasteView.state.tr.replaceSelection(slice).scrollIntoView(),
+							);
+						})
+						.catch((err) => {
+							console.error("Failed to sanitize pasted HTML:", err);
+							showError(`Failed to paste: ${errorMessage(err)}`);
+						});
+					return true;
+				}
+
 				if (html && /<img[\s>]/i.test(html)) {
 					event.preventDefault();
 					(async () => {
@@ -611,7 +659,7 @@ export function ProseMirrorEditor({
 						}
 					})().catch((err) => {
 						console.error("Failed to paste HTML images:", err);
-						showError(`Failed to paste image: ${err}`);
+						showError(`Failed to paste image: ${errorMessage(err)}`);
 					});
 					return true;
 				}
@@ -657,7 +705,7 @@ export function ProseMirrorEditor({
 						))
 						.catch((err) => {
 							console.error("Failed to insert dropped image:", err);
-							showError(`Failed to insert image: ${err}`);
+							showError(`Failed to insert image: ${errorMessage(err)}`);
 						});
 					return true;
 				}
@@ -834,7 +882,7 @@ export function ProseMirrorEditor({
 						await saveFilePathAndInsertImage(v, mySchema, filePath);
 					} catch (err) {
 						console.error("Failed to insert image:", err);
-						showError(`Failed to insert image: ${err}`);
+						showError(`Failed to insert image: ${errorMessage(err)}`);
 					}
 				})();
 				return;
@@ -986,7 +1034,12 @@ export function ProseMirrorEditor({
 			className="h-full w-full bg-white dark:bg-gray-900 text-gray-900 dark:text-gray-100 flex flex-col overflow-hidden relative"
 		>
 			{showSearch && (
-				<SearchBar view={viewRef.current} onClose={handleSearchClose} />
+				<SearchBar
+					key={searchInitialQuery}
+					view={viewRef.current}
+					initialQuery={searchInitialQuery}
+					onClose={handleSearchClose}
+				/>
 			)}
 			{toolbarVisible && (
 				<EditorToolbar editorView={viewRef.current} schema={mySchema} />
@@ -11,11 +11,13 @@ import {
 
 interface SearchBarProps {
 	view: EditorView | null;
+	/** Query to search for as soon as the bar opens */
+	initialQuery?: string;
 	onClose: () => void;
 }
 
-export function SearchBar({ view, onClose }: SearchBarProps) {
-	const [query, setQuery] = useState("");
+export function SearchBar({ view, initialQuery, onClose }: SearchBarProps) {
+	const [query, setQuery] = useState(initialQuery ?? "");
 	const [matchInfo, setMatchInfo] = useState({ current: 0, total: 0 });
 	const inputRef = useRef<HTMLInputElement>(null);
 
@@ -50,6 +52,15 @@ export function SearchBar({ view, onClose }: SearchBarProps) {
 		[view, updateMatchInfo]
 	);
 
+	// Run the initial query once so its matches are highlighted right away
+	const initialQueryRef = useRef(initialQuery);
+	useEffect(() => {
+		if (initialQueryRef.current) {
+			handleQueryChange(initialQueryRef.current);
+			initialQueryRef.current = undefined;
+		}
+	}, [handleQueryChange]);
+
 	// Handle keyboard shortcuts
 	const handleKeyDown = useCallback(
 		(e: React.KeyboardEvent) => {
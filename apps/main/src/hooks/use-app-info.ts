@@ -1,5 +1,6 @@
 import { useCallback, useEffect, useState } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { errorMessage } from "@/utils/app-error";
 
 interface AppInfo {
 	development_mode: boolean;
@@ -22,7 +23,7 @@ export const useAppInfo = () => {
 			setAppInfo(info);
 		} catch (err) {
 			console.error("Failed to load app info:", err);
-			setError(`Failed to load app info: ${err}`);
+			setError(`Failed to load app info: ${errorMessage(err)}`);
 		} finally {
 			setLoading(false);
 		}
@@ -121,7 +121,10 @@ export const useConfig = () => {
 		// Set up file watcher once (singleton — never unlistens).
 		invoke("setup_config_watcher")
 			.then(() =>
-				listen("config-file-changed", () => {
+				listen<{ filename?: string }>("config-file-changed", ({ payload }) => {
+					// Other config files (hotkeys, autocorrect, ...) have their own hooks
+					const filename = payload?.filename;
+					if (filename && filename !== "config.toml" && filename !== "config.json") return;
 					if (isWriting) return; // our own write — skip
 					loadConfigFromDisk().then((merged) => setConfig(merged));
 				}),
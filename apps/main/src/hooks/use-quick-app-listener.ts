@@ -1,19 +1,26 @@
 import { useEffect, useRef } from "react";
 import { listen } from "@tauri-apps/api/event";
 import { useTabManagement } from "./use-tab-management";
-import { useAtomValue } from "jotai";
+import { useAtomValue, useSetAtom } from "jotai";
 import { itemsAtom } from "@/atoms";
+import { pendingNoteHighlightAtom } from "@/atoms/search";
+
+/** Payload of `open-note-from-quick` (older builds sent the bare note ID) */
+type OpenNoteRequest = string | { noteId: string; highlight?: string | null };
 
 /**
  * Hook to listen for events from the quick app (IrisNotes Quick Search)
  * 
  * When user selects a note in the quick app, it launches main app with --open-note arg.
  * The main app's single-instance plugin receives this and emits an event.
- * This hook listens for that event and opens the note in a tab.
+ * This hook listens for that event and opens the note in a tab. When the quick
+ * app passes a search term, the editor highlights it and scrolls to it.
  */
 export const useQuickAppListener = () => {
 	const { openItemInTab } = useTabManagement();
 	const items = useAtomValue(itemsAtom);
+	const setPendingHighlight = useSetAtom(pendingNoteHighlightAtom);
+	const setPendingHighlightRef = useRef(setPendingHighlight);
 	
 	// Use refs to avoid stale closure issues
 	const itemsRef = useRef(items);
@@ -34,13 +41,16 @@ export const useQuickAppListener = () => {
 
 		const setupListener = async () => {
 			try {
-				const unlistenFn = await listen<string>("open-note-from-quick", (event) => {
+				const unlistenFn = await listen<OpenNoteRequest>("open-note-from-quick", (event) => {
 					// Guard against StrictMode double-mounting
 					if (isCancelled) {
 						return;
 					}
 					
-					const noteId = event.payload;
+					const request = event.payload;
+					const noteId = typeof request === "string" ? request : request.noteId;
+					const highlight = typeof request === "string" ? null : request.highlight;
+					setPendingHighlightRef.current(highlight ? { noteId, term: highlight } : null);
 
 					// Find the note in items (using ref to get current value)
 					const currentItems = itemsRef.current;
@@ -6,6 +6,7 @@ import { syncStateAtom, syncNowRequestAtom } from "@/atoms/sync";
 import { useConfig } from "./use-config";
 import { useNotesStorage } from "./use-notes-storage";
 import { runSync } from "@/storage/sync/sync-engine";
+import { errorMessage } from "@/utils/app-error";
 
 /**
  * Background sync loop. Local-first: the app always reads/writes the local DB;
@@ -73,7 +74,7 @@ export function useSync() {
 					setSyncState((s) => ({
 						...s,
 						status: "error",
-						lastError: e instanceof Error ? e.message : String(e),
+						lastError: errorMessage(e),
 					}));
 					console.warn("[sync] cycle failed:", e);
 				} finally {
@@ -41,7 +41,9 @@ import {
 	writeTextFile,
 } from "@tauri-apps/plugin-fs";
 import { join } from "@tauri-apps/api/path";
+import type { SanitizeOptions } from "@/types";
 import type { FlexibleItem } from "@/types/items";
+import { sanitizeHtml } from "@/utils/sanitize-html";
 import type { StorageAdapter } from "./types";
 
 // ============================================================================
@@ -64,6 +66,8 @@ export interface ImportOptions {
 	conflictStrategy?: "skip" | "overwrite" | "rename";
 	/** Import only specific types */
 	types?: Array<"note" | "book" | "section">;
+	/** Sanitize HTML note content against an allowlist (kept as-is when unset) */
+	sanitize?: SanitizeOptions;
 }
 
 export interface ExportResult {
@@ -336,7 +340,8 @@ export async function importFromFolder(
 					await join(options.sourceDir, folderName),
 					null, // No parent
 					existingIds,
-					options.conflictStrategy || "skip"
+					options.conflictStrategy || "skip",
+					options.sanitize
 				);
 				importedCount += result.imported;
 				skippedCount += result.skipped;
@@ -348,7 +353,8 @@ export async function importFromFolder(
 					await join(options.sourceDir, folderName),
 					folderName,
 					existingIds,
-					options.conflictStrategy || "skip"
+					options.conflictStrategy || "skip",
+					options.sanitize
 				);
 				importedCount += bookResult.imported;
 				skippedCount += bookResult.skipped;
@@ -380,7 +386,8 @@ async function importBookFolder(
 	bookPath: string,
 	bookTitle: string,
 	existingIds: Set<string>,
-	conflictStrategy: "skip" | "overwrite" | "rename"
+	conflictStrategy: "skip" | "overwrite" | "rename",
+	sanitize?: SanitizeOptions
 ): Promise<{ imported: number; skipped: number; errors: string[] }> {
 	const errors: string[] = [];
 	let imported = 0;
@@ -415,7 +422,8 @@ async function importBookFolder(
 					entry.name,
 					bookId,
 					existingIds,
-					conflictStrategy
+					conflictStrategy,
+					sanitize
 				);
 				imported += sectionResult.imported;
 				skipped += sectionResult.skipped;
@@ -427,7 +435,8 @@ async function importBookFolder(
 					entryPath,
 					bookId,
 					existingIds,
-					conflictStrategy
+					conflictStrategy,
+					sanitize
 				);
 				if (noteResult.imported) imported++;
 				if (noteResult.skipped) skipped++;
@@ -450,7 +459,8 @@ async function importSectionFolder(
 	sectionTitle: string,
 	bookId: string,
 	existingIds: Set<string>,
-	conflictStrategy: "skip" | "overwrite" | "rename"
+	conflictStrategy: "skip" | "overwrite" | "rename",
+	sanitize?: SanitizeOptions
 ): Promise<{ imported: number; skipped: number; errors: string[] }> {
 	const errors: string[] = [];
 	let imported = 0;
@@ -480,7 +490,8 @@ async function importSectionFolder(
 			sectionPath,
 			sectionId,
 			existingIds,
-			conflictStrategy
+			conflictStrategy,
+			sanitize
 		);
 		imported += notesResult.imported;
 		skipped += notesResult.skipped;
@@ -500,7 +511,8 @@ async function importNotesFromFolder(
 	folderPath: string,
 	parentId: string | null,
 	existingIds: Set<string>,
-	conflictStrategy: "skip" | "overwrite" | "rename"
+	conflictStrategy: "skip" | "overwrite" | "rename",
+	sanitize?: SanitizeOptions
 ): Promise<{ imported: number; skipped: number; errors: string[] }> {
 	const errors: string[] = [];
 	let imported = 0;
@@ -517,7 +529,8 @@ async function importNotesFromFolder(
 					notePath,
 					parentId,
 					existingIds,
-					conflictStrategy
+					conflictStrategy,
+					sanitize
 				);
 				if (result.imported) imported++;
 				if (result.skipped) skipped++;
@@ -539,7 +552,8 @@ async function importNoteFile(
 	filePath: string,
 	parentId: string | null,
 	existingIds: Set<string>,
-	conflictStrategy: "skip" | "overwrite" | "rename"
+	conflictStrategy: "skip" | "overwrite" | "rename",
+	sanitize?: SanitizeOptions
 ): Promise<{ imported: boolean; skipped: boolean; error?: string }> {
 	try {
 		const content = await readTextFile(filePath);
@@ -557,12 +571,18 @@ async function importNoteFile(
 			return { imported: false, skipped: true };
 		}
 
+		const contentType = (parsed.content_type as "html" | "markdown" | "plain") || "html";
+		const noteContent =
+			sanitize && contentType === "html"
+				? await sanitizeHtml(parsed.content, sanitize)
+				: parsed.content;
+
 		// Create the note
 		const result = await adapter.createItem({
 			type: "note",
 			title: parsed.title,
-			content: parsed.content,
-			content_type: (parsed.content_type as "html" | "markdown" | "plain") || "html",
+			content: noteContent,
+			content_type: contentType,
 			parent_id: parentId || undefined,
 			metadata: parsed.metadata,
 		});
@@ -589,6 +609,23 @@ async function importNoteFile(
 // UTILITIES
 // ============================================================================
 
+/**
+ * Strip YAML quotes from a frontmatter value ("..." as the CLI writes titles, or '...')
+ */
+function unquoteYamlValue(value: string): string {
+	if (value.length >= 2 && value.startsWith('"') && value.endsWith('"')) {
+		try {
+			return JSON.parse(value);
+		} catch {
+			return value;
+		}
+	}
+	if (value.length >= 2 && value.startsWith("'") && value.endsWith("'")) {
+		return value.slice(1, -1).replace(/''/g, "'");
+	}
+	return value;
+}
+
 /**
  * Parse YAML frontmatter from a markdown file
  */
@@ -631,7 +668,7 @@ function parseNoteFrontmatter(content: string): {
 					result.id = value;
 					break;
 				case "title":
-					result.title = value || "Imported Note";
+					result.title = unquoteYamlValue(value) || "Imported Note";
 					break;
 				case "content_type":
 					result.content_type = value;
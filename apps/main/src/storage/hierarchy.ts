@@ -8,8 +8,8 @@ const HIERARCHY_RULES: Record<ItemType, (ItemType | null)[]> = {
 	// Books can only be at root level
 	book: [null],
 
-	// Sections can be at root or inside books
-	section: [null, "book"],
+	// Sections can only be inside books (schema/base.sql rejects root sections)
+	section: ["book"],
 
 	// Notes can be anywhere (root, books, or sections)
 	note: [null, "book", "section"],
@@ -267,6 +267,7 @@ export interface StorageSettings {
 	sqlite?: {
 		database_path: string;
 	};
+	encryption_key?: string; // SQLCipher key; needs a backend built with the `sqlcipher` feature
 }
 
 export type { ThemeName } from "@/config/themes";
@@ -284,6 +285,14 @@ export interface SyncSettings {
 	databasePath?: string;
 }
 
+/** Adjustments to the HTML sanitizer's default tag allowlist (`tag` or `tag[attr ...]`). */
+export interface SanitizeOptions {
+	/** Extra tags/attributes to keep, e.g. `span[style]` */
+	keep?: string[];
+	/** Default tags (or `tag[attr]` attributes) to drop, e.g. `u` */
+	strip?: string[];
+}
+
 export interface AppConfig {
 	theme?: import("@/config/themes").ThemeName; // Active theme
 	editor: {
@@ -291,6 +300,8 @@ export interface AppConfig {
 		toolbarVisible: boolean;
 		titleBarVisible?: boolean;
 		metadataBarVisible?: boolean;
+		/** Sanitize pasted HTML against an allowlist (off when unset) */
+		pasteSanitizer?: SanitizeOptions;
 	};
 	debug: {
 		enableExampleNote: boolean;
@@ -0,0 +1,32 @@
+/**
+ * Error returned by a failed Tauri command (see `src-tauri/src/error.rs`).
+ * `kind` is stable and meant for branching; `message` is for display.
+ */
+export interface AppError {
+	kind:
+		| "io"
+		| "db"
+		| "not_found"
+		| "config"
+		| "parse"
+		| "invalid_input"
+		| "network"
+		| "other";
+	message: string;
+}
+
+export function isAppError(error: unknown): error is AppError {
+	return (
+		typeof error === "object" &&
+		error !== null &&
+		typeof (error as AppError).kind === "string" &&
+		typeof (error as AppError).message === "string"
+	);
+}
+
+/** Human-readable text for anything thrown by `invoke` or plain JS code */
+export function errorMessage(error: unknown): string {
+	if (isAppError(error)) return error.message;
+	if (error instanceof Error) return error.message;
+	return String(error);
+}
@@ -0,0 +1,15 @@
+import { invoke } from "@tauri-apps/api/core";
+import type { SanitizeOptions } from "@/types";
+
+/**
+ * Sanitize an HTML fragment in the backend: keeps the default allowlist
+ * (p, b/strong, i/em, u, a[href], lists, headings, code, pre, blockquote, br)
+ * adjusted by `keep`/`strip`, and drops every other tag and attribute.
+ */
+export function sanitizeHtml(html: string, options: SanitizeOptions = {}): Promise<string> {
+	return invoke<string>("sanitize_html", {
+		html,
+		keep: options.keep ?? null,
+		strip: options.strip ?? null,
+	});
+}
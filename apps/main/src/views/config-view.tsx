@@ -8,6 +8,7 @@ import { itemsAtom, notesAtom, booksAtom, sectionsAtom } from "@/atoms/items";
 import { openAsciiArtTabAtom, openAutocorrectTabAtom, openHotkeysTabAtom } from "@/atoms/panes";
 import { exportSettings, importSettings } from "@/storage/settings";
 import { invoke } from "@tauri-apps/api/core";
+import { errorMessage } from "@/utils/app-error";
 import { getVersion } from "@tauri-apps/api/app";
 import type {
 	CursorWidth,
@@ -221,7 +222,7 @@ export function ConfigView() {
 					: `Deleted ${deleted} orphaned image${deleted === 1 ? "" : "s"}.`,
 			);
 		} catch (err) {
-			setCleanupResult(`Error: ${err}`);
+			setCleanupResult(`Error: ${errorMessage(err)}`);
 		} finally {
 			setIsCleaning(false);
 		}
@@ -1,5 +1,6 @@
 import { useState, useRef, useCallback, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { errorMessage } from "@/utils/app-error";
 import * as Icons from "lucide-react";
 
 // Preset colors for quick selection
@@ -178,7 +179,7 @@ export function IconEditorView() {
 					}
 				}
 			} catch (e) {
-				setApplyError(String(e));
+				setApplyError(errorMessage(e));
 			}
 		},
 		[svgString],
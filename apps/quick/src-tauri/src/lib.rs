@@ -1,170 +1,171 @@
-use rusqlite::{Connection, Result as SqliteResult};
+use irisnotes_search::{highlight_term, run_search, SearchResult};
+use irisnotes_text::strip_html;
+use rusqlite::{Connection, OpenFlags};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Mutex;
 use tauri::{
     image::Image,
     menu::{Menu, MenuItem},
-    tray::TrayIconBuilder,
+    tray::{TrayIcon, TrayIconBuilder},
     AppHandle, Emitter, Manager, PhysicalPosition, PhysicalSize, State, WebviewWindow, WindowEvent,
 };
+use tauri_plugin_clipboard_manager::ClipboardExt;
 use tauri_plugin_global_shortcut::{Code, GlobalShortcutExt, Modifiers, Shortcut};
 
-// Search result structure
-#[derive(Debug, Serialize, Clone)]
-pub struct SearchResult {
-    id: String,
-    title: String,
-    snippet: String,
-    content_preview: String, // First ~60 chars of plain text content
-    book_name: Option<String>,
-    section_name: Option<String>,
-    match_type: String, // "title", "content", or "parent"
-    word_count: i32,    // Approximate word count of note content
-}
-
-/// Strip HTML tags and decode common entities for plain text output
-fn strip_html(html: &str) -> String {
-    // Remove HTML tags
-    let mut result = String::with_capacity(html.len());
-    let mut in_tag = false;
-
-    for c in html.chars() {
-        match c {
-            '<' => in_tag = true,
-            '>' => in_tag = false,
-            _ if !in_tag => result.push(c),
-            _ => {}
-        }
-    }
-
-    // Decode common HTML entities
-    let result = result
-        .replace("&nbsp;", " ")
-        .replace("&amp;", "&")
-        .replace("&lt;", "<")
-        .replace("&gt;", ">")
-        .replace("&quot;", "\"")
-        .replace("&#39;", "'")
-        .replace("&apos;", "'");
-
-    // Collapse multiple spaces
-    let mut prev_space = false;
-    let collapsed: String = result
-        .chars()
-        .filter(|c| {
-            if c.is_whitespace() {
-                if prev_space {
-                    return false;
-                }
-                prev_space = true;
-            } else {
-                prev_space = false;
-            }
-            true
-        })
-        .collect();
+// Longest note text sent to the preview pane, in characters
+const NOTE_CONTENT_LIMIT: usize = 10_000;
 
-    collapsed.trim().to_string()
-}
+// Shown by the UI when searching before the main app has created the database
+const DB_MISSING_ERROR: &str = "No notes database found — open the main app first";
+// How long a query waits for the main app to release a write lock
+const BUSY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+// Prepared statements kept per connection. The search SQL differs only by which
+// filters a query uses, so a few dozen shapes cover everything a user types.
+const STATEMENT_CACHE_CAPACITY: usize = 64;
 
 // Database connection state
 pub struct DbState {
     conn: Mutex<Option<Connection>>,
+    // Where the database is, or why that could not be worked out
+    path: Result<PathBuf, String>,
+    // Sequence number of the latest search request, so older ones can bail out
+    search_seq: AtomicU64,
 }
 
 impl DbState {
-    fn new() -> Self {
+    fn new(path: Result<PathBuf, String>) -> Self {
         Self {
             conn: Mutex::new(None),
+            path,
+            search_seq: AtomicU64::new(0),
         }
     }
 
-    fn init(&self, path: &PathBuf) -> SqliteResult<()> {
-        let conn = Connection::open(path)?;
+    fn init(&self) -> Result<(), String> {
+        let conn = open_database(self.path.as_ref()?)?;
         *self.conn.lock().unwrap() = Some(conn);
         Ok(())
     }
+
+    // Run `f` with the connection, opening the database first if it has
+    // appeared since startup (the main app creates it on first launch)
+    fn with_conn<T>(&self, f: impl FnOnce(&Connection) -> Result<T, String>) -> Result<T, String> {
+        let mut guard = self.conn.lock().unwrap();
+        if guard.is_none() {
+            *guard = Some(open_database(self.path.as_ref()?)?);
+        }
+        let conn = guard.as_ref().ok_or(DB_MISSING_ERROR)?;
+        f(conn)
+    }
 }
 
-// Get the database path (same as main IrisNotes app)
-fn get_database_path() -> PathBuf {
-    // In development, use the dev database from monorepo root
-    #[cfg(debug_assertions)]
-    {
-        // Find monorepo root by looking for pnpm-workspace.yaml
-        let exe_path = std::env::current_exe().ok();
-        let mut project_root = exe_path
-            .and_then(|p| p.parent().map(|p| p.to_path_buf()))
-            .unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
+// Unlock an encrypted database with the key the main app and the CLI use
+// (IRISNOTES_DB_KEY, else [storage] encryption_key); must precede any read
+fn unlock_database(conn: &Connection) -> Result<(), String> {
+    let Some(key) = irisnotes_paths::resolve_database_key()? else {
+        return Ok(());
+    };
+    if !cfg!(feature = "sqlcipher") {
+        return Err("An encryption key is configured, but Quick Search was built without \
+                    SQLCipher support (`--features sqlcipher`)"
+            .to_string());
+    }
+    conn.pragma_update(None, "key", &key)
+        .map_err(|e| format!("Failed to set encryption key: {}", e))
+}
 
-        // Navigate up to find monorepo root
-        loop {
-            if project_root.join("pnpm-workspace.yaml").exists() {
-                break;
-            }
-            if !project_root.pop() {
-                // Fallback: try home directory path
-                let home = dirs::home_dir().unwrap_or_default();
-                project_root = home.join("repos/irisnotes");
-                break;
+// Open an existing notes database. Never creates the file: an empty database
+// here would shadow the one the main app is about to initialize.
+// Read-only, so the quick app never takes a write lock. In WAL mode (the
+// journal mode is the main app's choice) reads then proceed alongside its
+// writes; in rollback mode they wait out each commit via the busy timeout.
+fn open_database(path: &PathBuf) -> Result<Connection, String> {
+    if !path.exists() {
+        return Err(DB_MISSING_ERROR.to_string());
+    }
+
+    let flags = OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX | OpenFlags::SQLITE_OPEN_URI;
+    let conn = Connection::open_with_flags(path, flags)
+        .map_err(|e| format!("Failed to open database: {}", e))?;
+    unlock_database(&conn)?;
+    // Wait out the main app's writes instead of failing with "database is locked"
+    conn.busy_timeout(BUSY_TIMEOUT)
+        .map_err(|e| format!("Failed to configure database: {}", e))?;
+    // Searches run on every keystroke; reuse their statements instead of re-parsing the SQL
+    conn.set_prepared_statement_cache_capacity(STATEMENT_CACHE_CAPACITY);
+
+    let has_items: bool = conn
+        .query_row(
+            "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type='table' AND name='items'",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| match e.sqlite_error_code() {
+            // What a wrong key, or a missing one, looks like
+            Some(rusqlite::ErrorCode::NotADatabase) => {
+                "The database could not be read: check its encryption key".to_string()
             }
-        }
-
-        let dev_path = project_root.join("dev/notes.db");
-        if dev_path.exists() {
-            return dev_path;
-        }
-        
-        // Fallback to config dir
-        dirs::config_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join("irisnotes")
-            .join("notes.db")
+            _ => format!("Failed to read database schema: {}", e),
+        })?;
+    if !has_items {
+        return Err(DB_MISSING_ERROR.to_string());
     }
 
-    #[cfg(not(debug_assertions))]
-    {
-        // Production: use config dir (same as main app)
-        dirs::config_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join("irisnotes")
-            .join("notes.db")
+    Ok(conn)
+}
+
+// Whether the database could be opened, for the UI to explain empty results
+#[derive(Debug, Serialize)]
+pub struct DbStatus {
+    open: bool,
+    path: String,
+    error: Option<String>,
+}
+
+// Report the database status, retrying the open if it failed before
+#[tauri::command]
+fn db_status(state: State<DbState>) -> DbStatus {
+    let result = state.with_conn(|_| Ok(()));
+    DbStatus {
+        open: result.is_ok(),
+        path: state.path.as_ref().map(|path| path.display().to_string()).unwrap_or_default(),
+        error: result.err(),
     }
 }
 
-// Get the config directory (same as main IrisNotes app)
-fn get_config_dir() -> PathBuf {
-    #[cfg(debug_assertions)]
-    {
-        // In development, use the dev directory from monorepo root
-        let exe_path = std::env::current_exe().ok();
-        let mut project_root = exe_path
-            .and_then(|p| p.parent().map(|p| p.to_path_buf()))
-            .unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
+// Last search query, sent back with `window-shown` when `preserve_query` is on
+pub struct QueryState {
+    preserve: bool,
+    last_query: Mutex<String>,
+}
 
-        // Navigate up to find monorepo root
-        loop {
-            if project_root.join("pnpm-workspace.yaml").exists() {
-                break;
-            }
-            if !project_root.pop() {
-                let home = dirs::home_dir().unwrap_or_default();
-                project_root = home.join("repos/irisnotes");
-                break;
-            }
+impl QueryState {
+    fn new(preserve: bool) -> Self {
+        Self {
+            preserve,
+            last_query: Mutex::new(String::new()),
         }
-
-        project_root.join("dev")
     }
+}
 
-    #[cfg(not(debug_assertions))]
-    {
-        // Production: use config dir (same as main app)
-        dirs::config_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join("irisnotes")
-    }
+// Result limit for searches, from `[quick] search_limit`
+pub struct SearchSettings {
+    limit: usize,
+}
+
+// Get the database path (same file as the main IrisNotes app and the CLI,
+// see irisnotes_paths). An error is kept and reported by every search rather
+// than guessed around: a relative `notes.db` would be some other database.
+fn get_database_path() -> Result<PathBuf, String> {
+    irisnotes_paths::resolve_database_path()
+        .map_err(|e| format!("Could not locate the notes database: {}", e))
+}
+
+// Get the config directory (same as main IrisNotes app)
+fn get_config_dir() -> PathBuf {
+    irisnotes_paths::config_dir().unwrap_or_else(|_| PathBuf::from("."))
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -273,258 +274,247 @@ fn read_config() -> Result<String, String> {
     }
 }
 
-// Parsed search query with field-specific filters
-struct ParsedQuery {
-    /// Free text tokens for title search (AND-combined)
-    title_tokens: Vec<String>,
-    /// ~content filter
-    content: Option<String>,
-    /// @book filter
-    book: Option<String>,
-    /// #section filter
-    section: Option<String>,
-    /// / means root notes only
-    root_only: bool,
-}
-
-fn parse_query(input: &str) -> ParsedQuery {
-    let mut title_tokens = Vec::new();
-    let mut content = None;
-    let mut book = None;
-    let mut section = None;
-    let mut root_only = false;
-
-    let chars: Vec<char> = input.chars().collect();
-    let len = chars.len();
-    let mut i = 0;
-
-    while i < len {
-        // Skip whitespace
-        if chars[i].is_whitespace() {
-            i += 1;
-            continue;
-        }
+// Quick app settings, read from the [quick] table of the shared config.toml
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct QuickConfig {
+    /// Show the search window immediately on launch instead of starting hidden
+    start_visible: bool,
+    /// Restore the last query when the window is shown instead of clearing it
+    preserve_query: bool,
+    /// Maximum number of search results (`irisnotes_search::DEFAULT_LIMIT` when unset)
+    search_limit: Option<usize>,
+    /// Global shortcut that toggles the window, e.g. "CmdOrCtrl+Shift+Space"
+    shortcut: Option<String>,
+}
 
-        if chars[i] == '/' && (i + 1 >= len || chars[i + 1].is_whitespace()) {
-            root_only = true;
-            i += 1;
-        } else if chars[i] == '@' || chars[i] == '#' || chars[i] == '~' {
-            let prefix = chars[i];
-            i += 1;
-            let value = extract_value(&chars, &mut i);
-            if !value.is_empty() {
-                match prefix {
-                    '@' => book = Some(value.to_lowercase()),
-                    '#' => section = Some(value.to_lowercase()),
-                    '~' => content = Some(value.to_lowercase()),
-                    _ => {}
-                }
-            }
-        } else {
-            // Regular text token — title search
-            let start = i;
-            while i < len && !chars[i].is_whitespace() {
-                i += 1;
+impl QuickConfig {
+    // `search_limit`, falling back to the default when unset or out of range
+    fn search_limit(&self) -> usize {
+        match self.search_limit {
+            Some(limit) if irisnotes_search::LIMIT_RANGE.contains(&limit) => limit,
+            Some(limit) => {
+                eprintln!(
+                    "Ignoring quick.search_limit {}: must be {}-{}",
+                    limit,
+                    irisnotes_search::LIMIT_RANGE.start(),
+                    irisnotes_search::LIMIT_RANGE.end()
+                );
+                irisnotes_search::DEFAULT_LIMIT
             }
-            let token: String = chars[start..i].iter().collect();
-            title_tokens.push(token);
+            None => irisnotes_search::DEFAULT_LIMIT,
         }
     }
 
-    ParsedQuery { title_tokens, content, book, section, root_only }
-}
-
-/// Extract a value after @ or #, supporting quoted strings: @"my book" or @word
-fn extract_value(chars: &[char], i: &mut usize) -> String {
-    let len = chars.len();
-    if *i >= len {
-        return String::new();
+    // `shortcut`, falling back to Ctrl+Shift+Space when unset or unparseable
+    // (more compatible than Super+Space)
+    fn shortcut(&self) -> Shortcut {
+        let default = Shortcut::new(Some(Modifiers::CONTROL | Modifiers::SHIFT), Code::Space);
+        match self.shortcut.as_deref().map(parse_shortcut) {
+            Some(Ok(shortcut)) => shortcut,
+            Some(Err(e)) => {
+                eprintln!("Ignoring quick.shortcut: {}; using Ctrl+Shift+Space", e);
+                default
+            }
+            None => default,
+        }
     }
+}
 
-    if chars[*i] == '"' {
-        // Quoted value: read until closing quote
-        *i += 1; // skip opening quote
-        let start = *i;
-        while *i < len && chars[*i] != '"' {
-            *i += 1;
-        }
-        let value: String = chars[start..*i].iter().collect();
-        if *i < len {
-            *i += 1; // skip closing quote
+/// Parse a shortcut like `CmdOrCtrl+Shift+Space`: any modifiers, then one key.
+/// Keys are letters, digits, `F1`-`F24`, common names (`Space`, `Enter`, `Esc`,
+/// `Up`) or any W3C key code (`PageDown`, `BracketLeft`); all case-insensitive
+/// except the W3C codes.
+fn parse_shortcut(input: &str) -> Result<Shortcut, String> {
+    let mut tokens: Vec<&str> = input.split('+').map(str::trim).collect();
+    let key = tokens
+        .pop()
+        .filter(|key| !key.is_empty())
+        .ok_or_else(|| format!("'{}' has no key", input))?;
+
+    let mut modifiers = Modifiers::empty();
+    for token in tokens {
+        modifiers |= match token.to_lowercase().as_str() {
+            "ctrl" | "control" => Modifiers::CONTROL,
+            "shift" => Modifiers::SHIFT,
+            "alt" | "option" => Modifiers::ALT,
+            "super" | "cmd" | "command" | "meta" => Modifiers::SUPER,
+            "cmdorctrl" | "commandorcontrol" if cfg!(target_os = "macos") => Modifiers::SUPER,
+            "cmdorctrl" | "commandorcontrol" => Modifiers::CONTROL,
+            _ => return Err(format!("unknown modifier '{}' in '{}'", token, input)),
+        };
+    }
+
+    let lower = key.to_lowercase();
+    let code_name = match lower.as_str() {
+        k if k.len() == 1 && k.chars().all(|c| c.is_ascii_alphabetic()) => {
+            format!("Key{}", k.to_uppercase())
         }
-        value
-    } else {
-        // Unquoted: read until whitespace
-        let start = *i;
-        while *i < len && !chars[*i].is_whitespace() {
-            *i += 1;
+        k if k.len() == 1 && k.chars().all(|c| c.is_ascii_digit()) => format!("Digit{}", k),
+        k if k.starts_with('f') && k.len() > 1 && k[1..].chars().all(|c| c.is_ascii_digit()) => {
+            k.to_uppercase()
         }
-        chars[start..*i].iter().collect()
-    }
+        "space" => "Space".to_string(),
+        "enter" | "return" => "Enter".to_string(),
+        "tab" => "Tab".to_string(),
+        "esc" | "escape" => "Escape".to_string(),
+        "backspace" => "Backspace".to_string(),
+        "delete" | "del" => "Delete".to_string(),
+        "up" => "ArrowUp".to_string(),
+        "down" => "ArrowDown".to_string(),
+        "left" => "ArrowLeft".to_string(),
+        "right" => "ArrowRight".to_string(),
+        _ => key.to_string(),
+    };
+    let code = code_name
+        .parse::<Code>()
+        .map_err(|_| format!("unknown key '{}' in '{}'", key, input))?;
+
+    Ok(Shortcut::new((!modifiers.is_empty()).then_some(modifiers), code))
 }
 
-/// Escape SQL LIKE wildcards (% and _) so they match literally
-fn escape_like(s: &str) -> String {
-    s.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct SharedConfig {
+    quick: QuickConfig,
+}
+
+fn load_quick_config() -> QuickConfig {
+    let toml_path = get_config_dir().join("config.toml");
+    let Ok(content) = std::fs::read_to_string(&toml_path) else {
+        return QuickConfig::default();
+    };
+
+    match toml::from_str::<SharedConfig>(&content) {
+        Ok(config) => config.quick,
+        Err(e) => {
+            eprintln!("Failed to parse quick settings in {}: {}", toml_path.display(), e);
+            QuickConfig::default()
+        }
+    }
 }
 
 // Search notes command
+// Async so keystrokes arriving while a search holds the connection run
+// concurrently. The query runs on the blocking pool, so waiting for the
+// connection lock never stalls an async worker; any search superseded by a
+// newer one by then returns None instead of querying, so stale results never land
 #[tauri::command]
-fn search_notes(query: String, state: State<DbState>) -> Result<Vec<SearchResult>, String> {
-    let guard = state.conn.lock().unwrap();
-    let conn = guard.as_ref().ok_or("Database not initialized")?;
-
-    let parsed = parse_query(&query);
-
-    // If nothing to search, return empty
-    if parsed.title_tokens.is_empty() && parsed.content.is_none() && parsed.book.is_none() && parsed.section.is_none() && !parsed.root_only {
-        return Ok(vec![]);
-    }
-
-    let has_title = !parsed.title_tokens.is_empty();
-    let has_content = parsed.content.is_some();
-    let has_book = parsed.book.is_some();
-    let has_section = parsed.section.is_some();
-    let root_only = parsed.root_only;
-
-    // Build dynamic params and WHERE conditions
-    let mut params: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
-    let mut conditions: Vec<String> = Vec::new();
-
-    // Title filter: one LIKE per token, AND-combined
-    if has_title {
-        let mut title_conds = Vec::new();
-        for token in &parsed.title_tokens {
-            let escaped = escape_like(&token.to_lowercase());
-            title_conds.push("LOWER(i.title) LIKE '%' || ? || '%' ESCAPE '\\'".to_string());
-            params.push(Box::new(escaped));
+async fn search_notes(
+    app: AppHandle,
+    query: String,
+    max_per_book: Option<usize>,
+) -> Result<Option<Vec<SearchResult>>, String> {
+    let seq = app.state::<DbState>().search_seq.fetch_add(1, Ordering::SeqCst) + 1;
+
+    let query_state = app.state::<QueryState>();
+    if query_state.preserve {
+        *query_state.last_query.lock().unwrap() = query.clone();
+    }
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<DbState>();
+        let limit = app.state::<SearchSettings>().limit;
+        state.with_conn(|conn| {
+            if state.search_seq.load(Ordering::SeqCst) != seq {
+                return Ok(None);
+            }
+            run_search(conn, &query, max_per_book, limit).map(Some)
+        })
+    })
+    .await
+    .map_err(|e| format!("Search task failed: {}", e))?
+}
+
+// Format a search result as a single clipboard line
+fn format_result_line(result: &SearchResult, format: &str) -> String {
+    let location = &result.path;
+
+    match format {
+        "markdown" => {
+            let link = format!("[{}](note://{})", result.title.replace(']', "\\]"), result.id);
+            if location.is_empty() {
+                format!("- {}", link)
+            } else {
+                format!("- {} — {}", link, location)
+            }
+        }
+        _ => {
+            if location.is_empty() {
+                result.title.clone()
+            } else {
+                format!("{} / {}", location, result.title)
+            }
         }
-        conditions.push(format!("({})", title_conds.join(" AND ")));
-    }
-
-    // Content filter (~content: FTS + LIKE fallback)
-    if let Some(ref content) = parsed.content {
-        let content_lower = escape_like(&content.to_lowercase());
-        let content_fts = content_lower
-            .split_whitespace()
-            .map(|word| format!("{}*", word.replace('"', "")))
-            .collect::<Vec<_>>()
-            .join(" ");
-        params.push(Box::new(content_fts));
-        params.push(Box::new(content_lower));
-        conditions.push(
-            "(i.id IN (SELECT fts.item_id FROM items_fts fts WHERE items_fts MATCH ?) OR LOWER(i.content) LIKE '%' || ? || '%' ESCAPE '\\')".to_string()
-        );
     }
+}
 
-    // Book filter (@book)
-    if let Some(ref book) = parsed.book {
-        let book_lower = escape_like(&book.to_lowercase());
-        params.push(Box::new(book_lower.clone()));
-        params.push(Box::new(book_lower));
-        conditions.push(
-            "((p.type = 'book' AND LOWER(p.title) LIKE '%' || ? || '%' ESCAPE '\\') OR (pp.type = 'book' AND LOWER(pp.title) LIKE '%' || ? || '%' ESCAPE '\\'))".to_string()
-        );
+// Copy search results to the clipboard as a plain or markdown list
+// Returns the number of results copied
+#[tauri::command]
+fn copy_search_results(
+    app: AppHandle,
+    query: String,
+    format: Option<String>,
+    state: State<DbState>,
+    settings: State<SearchSettings>,
+) -> Result<usize, String> {
+    let format = format.unwrap_or_else(|| "plain".to_string());
+    if format != "plain" && format != "markdown" {
+        return Err(format!("Unknown copy format: {} (expected plain or markdown)", format));
     }
 
-    // Section filter (#section)
-    if let Some(ref section) = parsed.section {
-        let section_lower = escape_like(&section.to_lowercase());
-        params.push(Box::new(section_lower));
-        conditions.push(
-            "(p.type = 'section' AND LOWER(p.title) LIKE '%' || ? || '%' ESCAPE '\\')".to_string()
-        );
+    let results = state.with_conn(|conn| run_search(conn, &query, None, settings.limit))?;
+
+    if results.is_empty() {
+        return Ok(0);
     }
 
-    // Root filter (/)
-    if root_only {
-        conditions.push("i.parent_id IS NULL".to_string());
-    }
-
-    let where_clause = conditions.join("\n          AND ");
-
-    let sql = format!("
-        SELECT
-            i.id,
-            i.title,
-            p.title as parent_title,
-            p.type as parent_type,
-            pp.title as grandparent_title,
-            LENGTH(i.content) as content_length,
-            i.content as raw_content
-        FROM items i
-        LEFT JOIN items p ON i.parent_id = p.id
-        LEFT JOIN items pp ON p.parent_id = pp.id
-        WHERE i.type = 'note'
-          AND i.deleted_at IS NULL
-          AND {}
-        ORDER BY i.title
-        LIMIT 30
-    ", where_clause);
-
-    let param_refs: Vec<&dyn rusqlite::types::ToSql> = params.iter().map(|p| p.as_ref()).collect();
-    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
-
-    let results = stmt
-        .query_map(param_refs.as_slice(), |row| {
-            let parent_title: Option<String> = row.get(2)?;
-            let parent_type: Option<String> = row.get(3)?;
-            let grandparent_title: Option<String> = row.get(4)?;
-            let content_length: i32 = row.get::<_, Option<i32>>(5)?.unwrap_or(0);
-            let raw_content: String = row.get::<_, Option<String>>(6)?.unwrap_or_default();
-
-            let plain_content = strip_html(&raw_content);
-            let content_preview = if plain_content.chars().count() > 80 {
-                let preview: String = plain_content.chars().take(80).collect();
-                format!("{}...", preview)
-            } else if plain_content.is_empty() {
-                "Empty note".to_string()
-            } else {
-                plain_content
-            };
+    let text = results
+        .iter()
+        .map(|result| format_result_line(result, &format))
+        .collect::<Vec<_>>()
+        .join("\n");
 
-            let (book_name, section_name) = match parent_type.as_deref() {
-                Some("section") => (grandparent_title.clone(), parent_title.clone()),
-                Some("book") => (parent_title.clone(), None),
-                _ => (None, None),
-            };
+    app.clipboard()
+        .write_text(text)
+        .map_err(|e| format!("Failed to write to clipboard: {}", e))?;
 
-            let note_title: String = row.get(1)?;
-            let match_type = if has_title {
-                "title"
-            } else if has_content {
-                "content"
-            } else if root_only {
-                "root"
-            } else if has_book || has_section {
-                "parent"
-            } else {
-                "title"
-            }.to_string();
-
-            let word_count = content_length / 6;
-
-            Ok(SearchResult {
-                id: row.get(0)?,
-                title: note_title,
-                snippet: String::new(),
-                content_preview,
-                book_name,
-                section_name,
-                match_type,
-                word_count,
-            })
-        })
-        .map_err(|e| e.to_string())?;
+    Ok(results.len())
+}
 
-    let mut search_results = Vec::new();
-    for result in results {
-        if let Ok(r) = result {
-            search_results.push(r);
+// Full plain-text content of a note, for the preview pane
+// Capped at NOTE_CONTENT_LIMIT characters, with "..." marking the cut
+#[tauri::command]
+fn get_note_content(note_id: String, state: State<DbState>) -> Result<String, String> {
+    state.with_conn(|conn| {
+        // Runs as the selection moves through the results, so cache it like the search
+        let content: Option<String> = conn
+            .prepare_cached(
+                "SELECT content FROM items WHERE id = ?1 AND type = 'note' AND deleted_at IS NULL",
+            )
+            .and_then(|mut stmt| stmt.query_row([&note_id], |row| row.get(0)))
+            .map_err(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => format!("Note not found: {}", note_id),
+                e => format!("Failed to read note: {}", e),
+            })?;
+
+        let text = strip_html(&content.unwrap_or_default());
+        if text.chars().count() > NOTE_CONTENT_LIMIT {
+            let truncated: String = text.chars().take(NOTE_CONTENT_LIMIT).collect();
+            Ok(format!("{}...", truncated))
+        } else {
+            Ok(text)
         }
-    }
+    })
+}
 
-    Ok(search_results)
+// Copy a note's ID to the clipboard, e.g. for use with the irisnotes CLI
+#[tauri::command]
+fn copy_note_id(app: AppHandle, note_id: String) -> Result<(), String> {
+    app.clipboard()
+        .write_text(note_id)
+        .map_err(|e| format!("Failed to write to clipboard: {}", e))
 }
 
 // Get the main app executable path
@@ -532,20 +522,7 @@ fn get_main_app_path() -> Option<PathBuf> {
     #[cfg(debug_assertions)]
     {
         // In development, main app is in apps/main/src-tauri/target/debug/
-        let exe_path = std::env::current_exe().ok();
-        let mut project_root = exe_path
-            .and_then(|p| p.parent().map(|p| p.to_path_buf()))
-            .unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
-
-        loop {
-            if project_root.join("pnpm-workspace.yaml").exists() {
-                break;
-            }
-            if !project_root.pop() {
-                break;
-            }
-        }
-
+        let project_root = irisnotes_paths::workspace_root().unwrap_or_default();
         return Some(project_root.join("apps/main/src-tauri/target/debug/irisnotes"));
     }
 
@@ -564,7 +541,7 @@ fn get_main_app_path() -> Option<PathBuf> {
 // Open note in main IrisNotes app by launching it with --open-note argument
 // The main app's single-instance plugin will receive this and emit an event
 #[tauri::command]
-fn open_note_in_main_app(note_id: String) -> Result<(), String> {
+fn open_note_in_main_app(note_id: String, highlight: Option<String>) -> Result<(), String> {
     let main_app_path = get_main_app_path()
         .ok_or("Could not find main app executable")?;
     
@@ -575,8 +552,12 @@ fn open_note_in_main_app(note_id: String) -> Result<(), String> {
     // Launch main app with --open-note argument
     // If main app is already running, single-instance plugin will receive the args
     // If not running, it will start fresh and receive the args on startup
-    std::process::Command::new(&main_app_path)
-        .arg(format!("--open-note={}", note_id))
+    let mut command = std::process::Command::new(&main_app_path);
+    command.arg(format!("--open-note={}", note_id));
+    if let Some(term) = highlight.as_deref().and_then(highlight_term) {
+        command.arg(format!("--highlight={}", term));
+    }
+    command
         .spawn()
         .map_err(|e| format!("Failed to launch main app: {}", e))?;
     
@@ -593,8 +574,12 @@ fn toggle_window(app: &AppHandle) {
     if let Some(window) = app.get_webview_window("main") {
         let _ = window.show();
         let _ = window.set_focus();
-        // Emit event to clear search field
-        let _ = window.emit("window-shown", ());
+        // Emit event to reset the search field: null clears it, a string restores that query
+        let restored_query = app
+            .try_state::<QueryState>()
+            .filter(|state| state.preserve)
+            .map(|state| state.last_query.lock().unwrap().clone());
+        let _ = window.emit("window-shown", restored_query);
     }
 }
 
@@ -602,13 +587,16 @@ fn toggle_window(app: &AppHandle) {
 // Supports both SVG and PNG formats
 // Theme-specific icons: quick-tray-icon-dark.{svg,png} and quick-tray-icon-light.{svg,png}
 fn load_custom_tray_icon() -> Option<Image<'static>> {
-    let icon_path = get_tray_icon_path()?;
-    
+    load_tray_icon_file(&get_tray_icon_path()?)
+}
+
+// Load a tray icon file, SVG or raster
+fn load_tray_icon_file(icon_path: &Path) -> Option<Image<'static>> {
     if !icon_path.exists() {
         return None;
     }
     
-    let file_bytes = std::fs::read(&icon_path).ok()?;
+    let file_bytes = std::fs::read(icon_path).ok()?;
     
     // Check if it's an SVG file
     let extension = icon_path.extension()?.to_str()?;
@@ -710,52 +698,57 @@ fn is_dark_theme() -> bool {
 }
 
 fn get_tray_icon_path() -> Option<PathBuf> {
-    let is_dark = is_dark_theme();
-    
-    // Theme-specific icon names (use opposite color for visibility)
-    // Dark theme -> use light icon, Light theme -> use dark icon
-    let theme_suffix = if is_dark { "-light" } else { "-dark" };
-    
-    // Extensions to try, in order of preference (SVG first for quality)
-    let extensions = ["svg", "png"];
-    
-    #[cfg(debug_assertions)]
-    {
-        // In development, check dev folder first
-        let exe_path = std::env::current_exe().ok();
-        let mut project_root = exe_path
-            .and_then(|p| p.parent().map(|p| p.to_path_buf()))
-            .unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
+    tray_icon_path(is_dark_theme())
+}
+
+// How often the tray icon watcher re-checks the system theme
+const THEME_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3);
 
-        // Navigate up to find monorepo root
+// Swap the tray icon when the system theme changes at runtime. A new theme
+// must be seen on two polls in a row before the icon is rebuilt, so rapid
+// toggling settles first. Does nothing without theme-specific icon files.
+fn watch_theme(tray: TrayIcon) {
+    if tray_icon_path(true) == tray_icon_path(false) {
+        return;
+    }
+
+    std::thread::spawn(move || {
+        let mut current = is_dark_theme();
+        let mut pending = None;
         loop {
-            if project_root.join("pnpm-workspace.yaml").exists() {
-                break;
+            std::thread::sleep(THEME_POLL_INTERVAL);
+            let is_dark = is_dark_theme();
+            if is_dark == current {
+                pending = None;
+                continue;
             }
-            if !project_root.pop() {
-                break;
+            if pending != Some(is_dark) {
+                pending = Some(is_dark);
+                continue;
             }
-        }
 
-        // Try theme-specific icons first (SVG then PNG)
-        for ext in &extensions {
-            let themed_path = project_root.join(format!("dev/quick-tray-icon{}.{}", theme_suffix, ext));
-            if themed_path.exists() {
-                return Some(themed_path);
-            }
-        }
-        
-        // Fall back to generic icons (SVG then PNG)
-        for ext in &extensions {
-            let dev_path = project_root.join(format!("dev/quick-tray-icon.{}", ext));
-            if dev_path.exists() {
-                return Some(dev_path);
+            pending = None;
+            current = is_dark;
+            let Some(icon) = tray_icon_path(is_dark).and_then(|path| load_tray_icon_file(&path)) else {
+                continue;
+            };
+            if let Err(e) = tray.set_icon(Some(icon)) {
+                eprintln!("Failed to update tray icon for theme change: {}", e);
             }
         }
-    }
+    });
+}
+
+fn tray_icon_path(is_dark: bool) -> Option<PathBuf> {
+    // Theme-specific icon names (use opposite color for visibility)
+    // Dark theme -> use light icon, Light theme -> use dark icon
+    let theme_suffix = if is_dark { "-light" } else { "-dark" };
     
-    // Production: check config dir
-    let config_dir = dirs::config_dir()?.join("irisnotes");
+    // Extensions to try, in order of preference (SVG first for quality)
+    let extensions = ["svg", "png"];
+    
+    // The config dir is dev/ in the monorepo in development builds
+    let config_dir = irisnotes_paths::config_dir().ok()?;
     
     // Try theme-specific icons first (SVG then PNG)
     for ext in &extensions {
@@ -790,11 +783,20 @@ pub fn run() {
         }
     }
 
-    let db_state = DbState::new();
+    let quick_config = load_quick_config();
+    // --show overrides the config for this launch only
+    let start_visible = quick_config.start_visible || std::env::args().any(|arg| arg == "--show");
+    let shortcut = quick_config.shortcut();
 
-    // Initialize database
-    let db_path = get_database_path();
-    let _ = db_state.init(&db_path);
+    // Initialize database. A missing database is not fatal: searches retry
+    // opening it and report DB_MISSING_ERROR until the main app creates it.
+    let db_state = DbState::new(get_database_path());
+    if let Err(e) = db_state.init() {
+        match &db_state.path {
+            Ok(path) => eprintln!("{} ({})", e, path.display()),
+            Err(_) => eprintln!("{}", e),
+        }
+    }
 
     tauri::Builder::default()
         .plugin(tauri_plugin_single_instance::init(|app, _args, _cwd| {
@@ -803,9 +805,21 @@ pub fn run() {
         }))
         .plugin(tauri_plugin_global_shortcut::Builder::new().build())
         .plugin(tauri_plugin_shell::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
         .manage(db_state)
-        .invoke_handler(tauri::generate_handler![search_notes, open_note_in_main_app, hide_window, read_config])
-        .setup(|app| {
+        .manage(QueryState::new(quick_config.preserve_query))
+        .manage(SearchSettings { limit: quick_config.search_limit() })
+        .invoke_handler(tauri::generate_handler![
+            search_notes,
+            copy_search_results,
+            copy_note_id,
+            get_note_content,
+            db_status,
+            open_note_in_main_app,
+            hide_window,
+            read_config
+        ])
+        .setup(move |app| {
             // Create system tray
             let quit = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
             let show = MenuItem::with_id(app, "show", "Show Search", true, None::<&str>)?;
@@ -835,7 +849,8 @@ pub fn run() {
                 tray_builder = tray_builder.icon(icon);
             }
 
-            let _tray = tray_builder.build(app)?;
+            let tray = tray_builder.build(app)?;
+            watch_theme(tray);
 
             if let Some(window) = app.get_webview_window("main") {
                 let state_path = window_state_file_path();
@@ -859,14 +874,21 @@ pub fn run() {
                 });
             }
 
-            // Register global shortcut (Ctrl+Shift+Space - more compatible than Super+Space)
+            // Register the global shortcut ([quick] shortcut, Ctrl+Shift+Space by default)
             // Note: This may not work on Wayland - use system keybinding instead
-            let shortcut = Shortcut::new(Some(Modifiers::CONTROL | Modifiers::SHIFT), Code::Space);
             let app_handle = app.handle().clone();
             
-            let _ = app.global_shortcut().on_shortcut(shortcut, move |_app, _shortcut, _event| {
+            let registered = app.global_shortcut().on_shortcut(shortcut, move |_app, _shortcut, _event| {
                 toggle_window(&app_handle);
             });
+            if let Err(e) = registered {
+                eprintln!("Failed to register the global shortcut: {}", e);
+            }
+
+            // Window starts hidden (tauri.conf.json); show it now if requested
+            if start_visible {
+                toggle_window(app.handle());
+            }
 
             Ok(())
         })
@@ -883,3 +905,41 @@ pub fn run() {
             }
         });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_shortcut_reads_modifiers_and_a_key() {
+        let shortcut = |mods: Modifiers, code: Code| Shortcut::new(Some(mods), code);
+        assert_eq!(
+            parse_shortcut("Ctrl+Shift+Space").unwrap(),
+            shortcut(Modifiers::CONTROL | Modifiers::SHIFT, Code::Space)
+        );
+        assert_eq!(parse_shortcut("alt + k").unwrap(), shortcut(Modifiers::ALT, Code::KeyK));
+        assert_eq!(parse_shortcut("Super+5").unwrap(), shortcut(Modifiers::SUPER, Code::Digit5));
+        assert_eq!(parse_shortcut("ctrl+esc").unwrap(), shortcut(Modifiers::CONTROL, Code::Escape));
+        assert_eq!(
+            parse_shortcut("Shift+PageDown").unwrap(),
+            shortcut(Modifiers::SHIFT, Code::PageDown)
+        );
+        assert_eq!(parse_shortcut("f12").unwrap(), Shortcut::new(None, Code::F12));
+
+        let cmd_or_ctrl = if cfg!(target_os = "macos") { Modifiers::SUPER } else { Modifiers::CONTROL };
+        assert_eq!(parse_shortcut("CmdOrCtrl+Up").unwrap(), shortcut(cmd_or_ctrl, Code::ArrowUp));
+    }
+
+    #[test]
+    fn parse_shortcut_rejects_missing_keys_and_unknown_names() {
+        for input in ["", "Ctrl+", "Ctrl+Shift+ "] {
+            assert!(parse_shortcut(input).unwrap_err().contains("has no key"), "{:?}", input);
+        }
+        let err = parse_shortcut("Hyper+K").unwrap_err();
+        assert!(err.contains("unknown modifier 'Hyper'"), "{}", err);
+        let err = parse_shortcut("Ctrl+Nope").unwrap_err();
+        assert!(err.contains("unknown key 'Nope'"), "{}", err);
+        // W3C codes are case-sensitive
+        assert!(parse_shortcut("Ctrl+pagedown").is_err());
+    }
+}
@@ -9,8 +9,18 @@ interface SearchResult {
   content_preview: string;
   book_name: string | null;
   section_name: string | null;
-  match_type: string;
+  path: string; // Full ancestor path, "Book / Section / ..."; empty at root level
+  match_type: string; // "recent" when listing recent notes for a blank query
   word_count: number;
+  created_at: string;
+  updated_at: string;
+  score: number; // 0-1 relevance from full-text search, -1 when not ranked
+}
+
+interface DbStatus {
+  open: boolean;
+  path: string;
+  error: string | null;
 }
 
 interface Config {
@@ -37,7 +47,14 @@ export function App() {
   const [results, setResults] = useState<SearchResult[]>([]);
   const [selectedIndex, setSelectedIndex] = useState(0);
   const [isLoading, setIsLoading] = useState(false);
+  const [searchError, setSearchError] = useState<string | null>(null);
+  // Why the notes database could not be opened, shown in place of results
+  const [dbError, setDbError] = useState<string | null>(null);
   const [showCheatSheet, setShowCheatSheet] = useState(false);
+  // Short-lived message next to the search box, e.g. after copying a note ID
+  const [notice, setNotice] = useState<string | null>(null);
+  // Bumped on every window-shown so the recent list refreshes even if the query is unchanged
+  const [shownAt, setShownAt] = useState(0);
   const inputRef = useRef<HTMLInputElement | null>(null);
   const resultsRef = useRef<HTMLDivElement | null>(null);
 
@@ -63,10 +80,12 @@ export function App() {
   useEffect(() => {
     inputRef.current?.focus();
     
-    // Listen for window-shown event to clear search and refresh theme
-    const unlisten = listen("window-shown", async () => {
-      setQuery("");
+    // Listen for window-shown event to reset search and refresh theme
+    // The payload carries the last query when `preserve_query` is enabled
+    const unlisten = listen<string | null>("window-shown", async (event) => {
+      setQuery(event.payload ?? "");
       setResults([]);
+      setShownAt(Date.now());
       setSelectedIndex(0);
       inputRef.current?.focus();
       
@@ -87,6 +106,13 @@ export function App() {
     };
   }, []);
 
+  // Check the database on startup and whenever the window is shown
+  useEffect(() => {
+    invoke<DbStatus>("db_status")
+      .then((status) => setDbError(status.open ? null : `${status.error} (${status.path})`))
+      .catch((err) => console.error("Failed to check database:", err));
+  }, [shownAt]);
+
   // Scroll selected item into view
   useEffect(() => {
     if (resultsRef.current && results.length > 0) {
@@ -97,36 +123,37 @@ export function App() {
     }
   }, [selectedIndex, results.length]);
 
-  // Debounced search
+  // Debounced search; a blank query lists recent notes right away
   useEffect(() => {
-    if (!query.trim()) {
-      setResults([]);
-      setSelectedIndex(0);
-      return;
-    }
-
     const timer = setTimeout(async () => {
       setIsLoading(true);
       try {
-        const searchResults = await invoke<SearchResult[]>("search_notes", {
+        // null means a newer search superseded this one before it ran
+        const searchResults = await invoke<SearchResult[] | null>("search_notes", {
           query: query.trim(),
         });
+        if (searchResults === null) return;
         setResults(searchResults);
         setSelectedIndex(0);
+        setSearchError(null);
+        setDbError(null);
       } catch (err) {
         console.error("Search error:", err);
         setResults([]);
+        // Backend errors (e.g. missing database) are plain strings meant for display
+        setSearchError(String(err));
       } finally {
         setIsLoading(false);
       }
-    }, 150);
+    }, query.trim() ? 150 : 0);
 
     return () => clearTimeout(timer);
-  }, [query]);
+  }, [query, shownAt]);
 
   const openNote = useCallback(async (noteId: string) => {
     try {
-      await invoke("open_note_in_main_app", { noteId });
+      // The backend picks the term to highlight out of the raw query
+      await invoke("open_note_in_main_app", { noteId, highlight: query.trim() || null });
       // Clear search state after opening
       setQuery("");
       setResults([]);
@@ -135,10 +162,34 @@ export function App() {
     } catch (err) {
       console.error("Failed to open note:", err);
     }
+  }, [query]);
+
+  // Clear the notice a moment after it appears
+  useEffect(() => {
+    if (!notice) return;
+    const timer = setTimeout(() => setNotice(null), 2000);
+    return () => clearTimeout(timer);
+  }, [notice]);
+
+  const copyNoteId = useCallback(async (noteId: string) => {
+    try {
+      await invoke("copy_note_id", { noteId });
+      setNotice("Note ID copied");
+    } catch (err) {
+      console.error("Failed to copy note ID:", err);
+      setNotice(`Copy failed: ${err}`);
+    }
   }, []);
 
   const handleKeyDown = useCallback(
     async (e: React.KeyboardEvent) => {
+      if ((e.ctrlKey || e.metaKey) && e.shiftKey && e.key.toLowerCase() === "c") {
+        e.preventDefault();
+        if (results[selectedIndex]) {
+          copyNoteId(results[selectedIndex].id);
+        }
+        return;
+      }
       switch (e.key) {
         case "F1":
           e.preventDefault();
@@ -174,7 +225,7 @@ export function App() {
           break;
       }
     },
-    [results, selectedIndex, openNote, showCheatSheet]
+    [results, selectedIndex, openNote, copyNoteId, showCheatSheet]
   );
 
   const getMatchTypeLabel = (matchType: string) => {
@@ -183,6 +234,7 @@ export function App() {
       case "content": return "Content";
       case "parent": return "Parent";
       case "root": return "Root";
+      case "recent": return "Recent";
       default: return "";
     }
   };
@@ -193,6 +245,7 @@ export function App() {
       case "content": return "Matched in note content";
       case "parent": return "Note is inside a matching book/section";
       case "root": return "Note is at root level";
+      case "recent": return "Recently modified note";
       default: return "";
     }
   };
@@ -226,6 +279,8 @@ export function App() {
               <div className="cheat-sheet-section">
                 <div className="cheat-sheet-title">Quoting</div>
                 <div className="cheat-sheet-row"><kbd>@"My Book"</kbd><span>Use quotes for spaces</span></div>
+                <div className="cheat-sheet-row"><kbd>~"exact phrase"</kbd><span>Content has the exact phrase</span></div>
+                <div className="cheat-sheet-row"><kbd>~cake OR pie</kbd><span>Content has either word (also AND, NOT)</span></div>
               </div>
               <div className="cheat-sheet-section">
                 <div className="cheat-sheet-title">Examples</div>
@@ -238,6 +293,7 @@ export function App() {
                 <div className="cheat-sheet-title">Navigation</div>
                 <div className="cheat-sheet-row"><kbd>↑ ↓</kbd><span>Navigate results</span></div>
                 <div className="cheat-sheet-row"><kbd>Enter</kbd><span>Open selected note</span></div>
+                <div className="cheat-sheet-row"><kbd>Ctrl+Shift+C</kbd><span>Copy selected note's ID</span></div>
                 <div className="cheat-sheet-row"><kbd>Esc</kbd><span>Close</span></div>
               </div>
             </div>
@@ -255,6 +311,7 @@ export function App() {
           autoFocus
         />
         {isLoading && <div className="loading-indicator">...</div>}
+        {notice && <div className="search-notice">{notice}</div>}
       </div>
 
       <div className="results-table" ref={resultsRef}>
@@ -268,17 +325,17 @@ export function App() {
             <span className="col-title" title={result.title}>{result.title}</span>
             <span className="col-book">
               {result.book_name ? (
-                <span className="tag tag-book" title={result.book_name}>{result.book_name}</span>
+                <span className="tag tag-book" title={result.path}>{result.book_name}</span>
               ) : (
                 <span className="tag tag-root">Root</span>
               )}
             </span>
             <span className="col-section">
               {result.section_name && (
-                <span className="tag tag-section" title={result.section_name}>{result.section_name}</span>
+                <span className="tag tag-section" title={result.path}>{result.section_name}</span>
               )}
             </span>
-            <span className="col-words" title={`Approximately ${result.word_count} words`}>
+            <span className="col-words" title={`${result.word_count} words`}>
               {formatWordCount(result.word_count)}
             </span>
             <span 
@@ -290,8 +347,8 @@ export function App() {
           </div>
         ))}
 
-        {query && !isLoading && results.length === 0 && (
-          <div className="no-results">No notes found</div>
+        {(query || dbError) && !isLoading && results.length === 0 && (
+          <div className="no-results">{dbError ?? searchError ?? "No notes found"}</div>
         )}
       </div>
     </div>
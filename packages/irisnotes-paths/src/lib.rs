@@ -0,0 +1,231 @@
+//! Config, data and database locations shared by the IrisNotes apps
+//!
+//! The main app, its CLI and the quick search overlay must all open the same
+//! `notes.db` and read the same `config.toml`, so they resolve both here.
+//! Each location is taken from the first of:
+//!
+//! 1. its environment variable (`IRISNOTES_CONFIG_DIR`, `IRISNOTES_DATA_DIR`,
+//!    or `IRISNOTES_DB` for the database file itself)
+//! 2. in development builds, `dev/` in the monorepo root (the directory
+//!    holding `pnpm-workspace.yaml`), searched upward from the executable and
+//!    then from the current directory
+//! 3. the platform config dir (`~/.config/irisnotes` on Linux)
+//!
+//! A development build run outside the monorepo falls through to step 3.
+//! Nothing here creates directories; callers that write do that themselves.
+//!
+//! The SQLCipher key for an encrypted database is resolved here too
+//! (`IRISNOTES_DB_KEY`, else `[storage] encryption_key` in `config.toml`), so
+//! every app unlocks the file it opens with the same key.
+
+use std::path::{Path, PathBuf};
+
+/// Overrides the config directory
+pub const CONFIG_DIR_ENV: &str = "IRISNOTES_CONFIG_DIR";
+/// Overrides the data directory (database and assets)
+pub const DATA_DIR_ENV: &str = "IRISNOTES_DATA_DIR";
+/// Overrides the database file
+pub const DATABASE_ENV: &str = "IRISNOTES_DB";
+/// Supplies the database's SQLCipher key, overriding `[storage] encryption_key`
+pub const DATABASE_KEY_ENV: &str = "IRISNOTES_DB_KEY";
+
+/// File name of the database inside the data directory
+const DATABASE_FILE: &str = "notes.db";
+/// File name of the shared config inside the config directory
+const CONFIG_FILE: &str = "config.toml";
+/// The file that marks the monorepo root (`apps/*` have their own `package.json`)
+const WORKSPACE_MARKER: &str = "pnpm-workspace.yaml";
+
+/// Debug builds, or a release build started by `tauri dev`
+pub fn is_development_mode() -> bool {
+    cfg!(debug_assertions) || std::env::var("TAURI_ENV").as_deref() == Ok("dev")
+}
+
+/// Path named by an environment variable, if it is set and not empty
+fn env_path(name: &str) -> Option<PathBuf> {
+    std::env::var_os(name)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Nearest ancestor of `start` (or `start` itself) holding the workspace marker
+fn find_workspace_root(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .find(|dir| dir.join(WORKSPACE_MARKER).exists())
+        .map(Path::to_path_buf)
+}
+
+/// The monorepo root, searched upward from the executable, then from the
+/// current directory (for binaries run from `target/` outside the tree)
+pub fn workspace_root() -> Option<PathBuf> {
+    let exe_dir = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf));
+    exe_dir
+        .and_then(|dir| find_workspace_root(&dir))
+        .or_else(|| find_workspace_root(&std::env::current_dir().ok()?))
+}
+
+/// `dev/` in the monorepo root, in development builds only
+fn dev_dir() -> Option<PathBuf> {
+    if !is_development_mode() {
+        return None;
+    }
+    workspace_root().map(|root| root.join("dev"))
+}
+
+/// `irisnotes` in the platform config dir
+fn platform_dir() -> Result<PathBuf, String> {
+    dirs::config_dir()
+        .map(|dir| dir.join("irisnotes"))
+        .ok_or_else(|| "Failed to get system config directory".to_string())
+}
+
+/// Directory holding `config.toml` and the other config files
+pub fn config_dir() -> Result<PathBuf, String> {
+    match env_path(CONFIG_DIR_ENV).or_else(dev_dir) {
+        Some(dir) => Ok(dir),
+        None => platform_dir(),
+    }
+}
+
+/// Directory holding the database and assets. The same as `config_dir` in the
+/// default layouts, so everything lives in one place.
+pub fn data_dir() -> Result<PathBuf, String> {
+    match env_path(DATA_DIR_ENV).or_else(dev_dir) {
+        Some(dir) => Ok(dir),
+        None => platform_dir(),
+    }
+}
+
+/// The database file every app opens: `IRISNOTES_DB`, else `notes.db` in
+/// `data_dir`
+pub fn resolve_database_path() -> Result<PathBuf, String> {
+    match env_path(DATABASE_ENV) {
+        Some(path) => Ok(path),
+        None => Ok(data_dir()?.join(DATABASE_FILE)),
+    }
+}
+
+/// The key the database is encrypted with: `IRISNOTES_DB_KEY`, else
+/// `[storage] encryption_key` in `config.toml`. `None` (the database is plain
+/// SQLite) when neither is set or the key is empty.
+pub fn resolve_database_key() -> Result<Option<String>, String> {
+    if let Some(key) = std::env::var_os(DATABASE_KEY_ENV).filter(|value| !value.is_empty()) {
+        return key
+            .into_string()
+            .map(Some)
+            .map_err(|_| format!("{} is not valid UTF-8", DATABASE_KEY_ENV));
+    }
+
+    let path = config_dir()?.join(CONFIG_FILE);
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+    // Only this one key is read; the apps validate the rest of the file themselves
+    let config: toml::Table = text
+        .parse()
+        .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+    match config.get("storage").and_then(|storage| storage.get("encryption_key")) {
+        None => Ok(None),
+        Some(toml::Value::String(key)) if key.is_empty() => Ok(None),
+        Some(toml::Value::String(key)) => Ok(Some(key.clone())),
+        Some(_) => Err(format!(
+            "{}: storage.encryption_key must be a string",
+            path.display()
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// The environment is process-wide; tests that change it take turns
+    static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+    /// Run `f` with each variable set (or removed for `None`), restoring the
+    /// previous values afterwards
+    fn with_env<T>(vars: &[(&str, Option<&str>)], f: impl FnOnce() -> T) -> T {
+        let _guard = ENV_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let saved: Vec<_> = vars.iter().map(|(name, _)| (*name, std::env::var_os(name))).collect();
+        for (name, value) in vars {
+            match value {
+                Some(value) => std::env::set_var(name, value),
+                None => std::env::remove_var(name),
+            }
+        }
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(f));
+        for (name, value) in saved {
+            match value {
+                Some(value) => std::env::set_var(name, value),
+                None => std::env::remove_var(name),
+            }
+        }
+        result.unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    }
+
+    #[test]
+    fn config_dir_env_overrides_the_default() {
+        with_env(&[(CONFIG_DIR_ENV, Some("/tmp/iris-config"))], || {
+            assert_eq!(config_dir().unwrap(), PathBuf::from("/tmp/iris-config"));
+        });
+    }
+
+    #[test]
+    fn data_dir_env_overrides_the_default() {
+        with_env(&[(DATA_DIR_ENV, Some("/tmp/iris-data"))], || {
+            assert_eq!(data_dir().unwrap(), PathBuf::from("/tmp/iris-data"));
+        });
+    }
+
+    #[test]
+    fn empty_overrides_are_ignored() {
+        with_env(&[(CONFIG_DIR_ENV, Some("")), (DATA_DIR_ENV, None)], || {
+            let default = data_dir().unwrap();
+            assert_eq!(config_dir().unwrap(), default);
+        });
+    }
+
+    #[test]
+    fn database_env_names_the_file_itself() {
+        let vars = [(DATABASE_ENV, Some("/srv/notes/work.db")), (DATA_DIR_ENV, Some("/tmp/iris-data"))];
+        with_env(&vars, || {
+            assert_eq!(resolve_database_path().unwrap(), PathBuf::from("/srv/notes/work.db"));
+        });
+    }
+
+    #[test]
+    fn database_path_defaults_to_notes_db_in_the_data_dir() {
+        with_env(&[(DATABASE_ENV, None), (DATA_DIR_ENV, Some("/tmp/iris-data"))], || {
+            assert_eq!(resolve_database_path().unwrap(), PathBuf::from("/tmp/iris-data/notes.db"));
+        });
+        with_env(&[(DATABASE_ENV, Some("")), (DATA_DIR_ENV, Some("/tmp/iris-data"))], || {
+            assert_eq!(resolve_database_path().unwrap(), PathBuf::from("/tmp/iris-data/notes.db"));
+        });
+    }
+
+    #[test]
+    fn database_key_env_wins_over_the_config_file() {
+        let dir = std::env::temp_dir().join(format!("irisnotes-paths-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(CONFIG_FILE), "[storage]\nencryption_key = \"from-file\"\n").unwrap();
+        let dir_str = dir.to_str().unwrap();
+
+        with_env(&[(CONFIG_DIR_ENV, Some(dir_str)), (DATABASE_KEY_ENV, Some("from-env"))], || {
+            assert_eq!(resolve_database_key().unwrap().as_deref(), Some("from-env"));
+        });
+        with_env(&[(CONFIG_DIR_ENV, Some(dir_str)), (DATABASE_KEY_ENV, None)], || {
+            assert_eq!(resolve_database_key().unwrap().as_deref(), Some("from-file"));
+        });
+        std::fs::write(dir.join(CONFIG_FILE), "[storage]\nencryption_key = 42\n").unwrap();
+        with_env(&[(CONFIG_DIR_ENV, Some(dir_str)), (DATABASE_KEY_ENV, Some(""))], || {
+            assert!(resolve_database_key().unwrap_err().contains("must be a string"));
+        });
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,391 @@
+//! Quick-search queries shared by the IrisNotes apps
+//!
+//! The quick overlay and the main app's `search_notes` command both take the
+//! same query syntax (`title words`, `~content`, `@book`, `#section`, `/` for
+//! root notes) and run it here, so a query finds the same notes in either.
+//! Content terms go through FTS5 (`irisnotes_text::fts_match_query`) with a
+//! substring fallback; results carry a normalized BM25 score and the note's
+//! full path.
+
+mod query;
+
+pub use query::highlight_term;
+
+use irisnotes_text::{fts_match_query, normalize_rank, strip_html, word_count};
+use query::{escape_like, parse_query, ParsedQuery};
+use rusqlite::Connection;
+use serde::Serialize;
+
+/// One note matched by a search
+#[derive(Debug, Serialize, Clone)]
+pub struct SearchResult {
+    pub id: String,
+    pub title: String,
+    pub snippet: String,
+    pub content_preview: String, // First ~60 chars of plain text content
+    pub book_name: Option<String>,
+    pub section_name: Option<String>,
+    pub path: String,       // Ancestor titles from the root, "Book / Section / ..."; empty at root level
+    pub match_type: String, // "title", "content", "parent", "root", or "recent" for a blank query
+    pub word_count: i32,    // Words in the note's plain text (each Chinese/Japanese character counts as one)
+    pub created_at: String, // ISO-8601 UTC, e.g. 2024-01-31T09:15:00Z
+    pub updated_at: String, // ISO-8601 UTC, e.g. 2024-01-31T09:15:00Z
+    pub score: f64,         // Normalized BM25 relevance (0-1), NO_SCORE if FTS did not rank it
+    #[serde(skip)]
+    pub book_id: Option<String>, // Top-level book at any depth, used for per-book capping
+}
+
+/// Score of results FTS did not rank: title, book/section and substring-only matches
+pub const NO_SCORE: f64 = -1.0;
+
+/// Results returned when the caller sets no limit (`[quick] search_limit` in the quick app)
+pub const DEFAULT_LIMIT: usize = 30;
+/// Accepted result limits
+pub const LIMIT_RANGE: std::ops::RangeInclusive<usize> = 1..=500;
+// How many extra rows to fetch when a per-book cap may trim results
+const PER_BOOK_FETCH_FACTOR: usize = 4;
+/// Ancestors walked up from an item (for its path, or to find its book). A
+/// parent chain that loops (see `irisnotes doctor`) is cut off here instead
+/// of walked forever.
+pub const MAX_PATH_DEPTH: usize = 256;
+
+/// Run a quick-search query against the database, returning at most `limit` results.
+/// `max_per_book` caps how many results may come from a single top-level book.
+pub fn run_search(
+    conn: &Connection,
+    query: &str,
+    max_per_book: Option<usize>,
+    limit: usize,
+) -> Result<Vec<SearchResult>, String> {
+    let parsed = parse_query(query);
+
+    // If nothing to search, return empty; a blank query lists recent notes instead
+    if parsed.title_tokens.is_empty() && parsed.content.is_none() && parsed.book.is_none() && parsed.section.is_none() && !parsed.root_only && !query.trim().is_empty() {
+        return Ok(vec![]);
+    }
+
+    let results = query_notes(conn, &parsed, max_per_book, limit, false)?;
+
+    // Chained ~content values are matched by FTS alone, which only sees whole
+    // words and prefixes; when it finds nothing, retry them as substrings
+    if results.is_empty() && !parsed.content_chain.is_empty() {
+        return query_notes(conn, &parsed, max_per_book, limit, true);
+    }
+
+    Ok(results)
+}
+
+/// The `limit` most recently modified live notes, newest first: what a blank
+/// quick-search query lists, and the main app's "jump back in" list
+pub fn recent_notes(conn: &Connection, limit: usize) -> Result<Vec<SearchResult>, String> {
+    run_search(conn, "", None, limit)
+}
+
+// Run one search query; `substring_content` matches every ~content value with
+// LIKE instead of FTS, so results are unranked
+fn query_notes(
+    conn: &Connection,
+    parsed: &ParsedQuery,
+    max_per_book: Option<usize>,
+    limit: usize,
+    substring_content: bool,
+) -> Result<Vec<SearchResult>, String> {
+    let has_title = !parsed.title_tokens.is_empty();
+    let has_content = parsed.content.is_some();
+    let has_book = parsed.book.is_some();
+    let has_section = parsed.section.is_some();
+    let root_only = parsed.root_only;
+
+    // Build dynamic params and WHERE conditions
+    let mut params: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
+    let mut conditions: Vec<String> = Vec::new();
+    let content_fts = parsed
+        .content_query
+        .as_deref()
+        .filter(|_| !substring_content)
+        .and_then(fts_match_query);
+
+    // Relevance comes from the ~content FTS query; its parameter is the first in the SQL
+    let rank_column = match content_fts {
+        Some(ref content_fts) => {
+            params.push(Box::new(content_fts.clone()));
+            "(SELECT fts.rank FROM items_fts fts WHERE items_fts MATCH ? AND fts.item_id = i.id)"
+        }
+        None => "NULL",
+    };
+
+    // Title filter: one LIKE per token, AND-combined
+    if has_title {
+        let mut title_conds = Vec::new();
+        for token in &parsed.title_tokens {
+            let escaped = escape_like(&token.to_lowercase());
+            title_conds.push("LOWER(i.title) LIKE '%' || ? || '%' ESCAPE '\\'".to_string());
+            params.push(Box::new(escaped));
+        }
+        conditions.push(format!("({})", title_conds.join(" AND ")));
+    }
+
+    // Content filter (~content: FTS + LIKE fallback)
+    if let Some(ref content) = parsed.content {
+        let mut content_conds = Vec::new();
+        if let Some(ref content_fts) = content_fts {
+            params.push(Box::new(content_fts.clone()));
+            content_conds.push("i.id IN (SELECT fts.item_id FROM items_fts fts WHERE items_fts MATCH ?)".to_string());
+        }
+        // A substring match on the first value alone would ignore the chained values
+        if parsed.content_chain.is_empty() || substring_content {
+            let content_like = "LOWER(i.content) LIKE '%' || ? || '%' ESCAPE '\\'";
+            params.push(Box::new(escape_like(&content.to_lowercase())));
+            let mut chain = content_like.to_string();
+            for (operator, value) in &parsed.content_chain {
+                // FTS5's NOT is binary: `a NOT b` means a AND NOT b
+                let operator = if operator == "NOT" { "AND NOT" } else { operator.as_str() };
+                chain.push_str(&format!(" {} {}", operator, content_like));
+                params.push(Box::new(escape_like(value)));
+            }
+            content_conds.push(format!("({})", chain));
+        }
+        conditions.push(format!("({})", content_conds.join(" OR ")));
+    }
+
+    // Book filter (@book)
+    if let Some(ref book) = parsed.book {
+        let book_lower = escape_like(&book.to_lowercase());
+        params.push(Box::new(book_lower.clone()));
+        params.push(Box::new(book_lower));
+        conditions.push(
+            "((p.type = 'book' AND LOWER(p.title) LIKE '%' || ? || '%' ESCAPE '\\') OR (pp.type = 'book' AND LOWER(pp.title) LIKE '%' || ? || '%' ESCAPE '\\'))".to_string()
+        );
+    }
+
+    // Section filter (#section)
+    if let Some(ref section) = parsed.section {
+        let section_lower = escape_like(&section.to_lowercase());
+        params.push(Box::new(section_lower));
+        conditions.push(
+            "(p.type = 'section' AND LOWER(p.title) LIKE '%' || ? || '%' ESCAPE '\\')".to_string()
+        );
+    }
+
+    // Root filter (/)
+    if root_only {
+        conditions.push("i.parent_id IS NULL".to_string());
+    }
+
+    // Without any filter, list the most recently modified notes
+    let recent = conditions.is_empty();
+    let (where_clause, order_by) = if recent {
+        ("1".to_string(), "COALESCE(i.updated_at, i.created_at) DESC")
+    } else {
+        (conditions.join("\n          AND "), "i.title")
+    };
+
+    // Over-fetch when capping per book so trimming still leaves a full page
+    let fetch_limit = match max_per_book {
+        Some(_) => limit * PER_BOOK_FETCH_FACTOR,
+        None => limit,
+    };
+    params.push(Box::new(fetch_limit as i64));
+
+    let sql = format!("
+        SELECT
+            i.id,
+            i.title,
+            p.title as parent_title,
+            p.type as parent_type,
+            pp.title as grandparent_title,
+            i.content as raw_content,
+            (
+                WITH RECURSIVE ancestors(id, type, parent_id, depth) AS (
+                    SELECT a.id, a.type, a.parent_id, 1 FROM items a WHERE a.id = i.parent_id
+                    UNION ALL
+                    SELECT a.id, a.type, a.parent_id, ancestors.depth + 1
+                    FROM items a JOIN ancestors ON a.id = ancestors.parent_id
+                    WHERE ancestors.depth < {max_depth}
+                )
+                SELECT id FROM ancestors WHERE type = 'book' ORDER BY depth DESC LIMIT 1
+            ) as book_id,
+            COALESCE(strftime('%Y-%m-%dT%H:%M:%SZ', i.created_at), i.created_at) as created_at,
+            COALESCE(strftime('%Y-%m-%dT%H:%M:%SZ', i.updated_at), i.updated_at) as updated_at,
+            {} as rank,
+            (
+                WITH RECURSIVE ancestors(title, parent_id, depth) AS (
+                    SELECT a.title, a.parent_id, 1 FROM items a WHERE a.id = i.parent_id
+                    UNION ALL
+                    SELECT a.title, a.parent_id, ancestors.depth + 1
+                    FROM items a JOIN ancestors ON a.id = ancestors.parent_id
+                    WHERE ancestors.depth < {max_depth}
+                )
+                SELECT group_concat(title, ' / ') FROM (SELECT title FROM ancestors ORDER BY depth DESC)
+            ) as path
+        FROM items i
+        LEFT JOIN items p ON i.parent_id = p.id
+        LEFT JOIN items pp ON p.parent_id = pp.id
+        WHERE i.type = 'note'
+          AND i.deleted_at IS NULL
+          AND {}
+        ORDER BY {}
+        LIMIT ?
+    ", rank_column, where_clause, order_by, max_depth = MAX_PATH_DEPTH);
+
+    let param_refs: Vec<&dyn rusqlite::types::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+    let mut stmt = conn.prepare_cached(&sql).map_err(|e| e.to_string())?;
+
+    let results = stmt
+        .query_map(param_refs.as_slice(), |row| {
+            let parent_title: Option<String> = row.get(2)?;
+            let parent_type: Option<String> = row.get(3)?;
+            let grandparent_title: Option<String> = row.get(4)?;
+            let raw_content: String = row.get::<_, Option<String>>(5)?.unwrap_or_default();
+            let book_id: Option<String> = row.get(6)?;
+            let created_at: String = row.get::<_, Option<String>>(7)?.unwrap_or_default();
+            let updated_at: String = row.get::<_, Option<String>>(8)?.unwrap_or_default();
+            let rank: Option<f64> = row.get(9)?;
+            let path: String = row.get::<_, Option<String>>(10)?.unwrap_or_default();
+
+            let plain_content = strip_html(&raw_content);
+            let word_count = word_count(&plain_content) as i32;
+            let content_preview = if plain_content.chars().count() > 80 {
+                let preview: String = plain_content.chars().take(80).collect();
+                format!("{}...", preview)
+            } else if plain_content.is_empty() {
+                "Empty note".to_string()
+            } else {
+                plain_content
+            };
+
+            let (book_name, section_name) = match parent_type.as_deref() {
+                Some("section") => (grandparent_title.clone(), parent_title.clone()),
+                Some("book") => (parent_title.clone(), None),
+                _ => (None, None),
+            };
+
+            let note_title: String = row.get(1)?;
+            let match_type = if has_title {
+                "title"
+            } else if has_content {
+                "content"
+            } else if root_only {
+                "root"
+            } else if has_book || has_section {
+                "parent"
+            } else {
+                "recent"
+            }.to_string();
+
+            Ok(SearchResult {
+                id: row.get(0)?,
+                title: note_title,
+                snippet: String::new(),
+                content_preview,
+                book_name,
+                section_name,
+                path,
+                match_type,
+                word_count,
+                created_at,
+                updated_at,
+                score: rank.map_or(NO_SCORE, normalize_rank),
+                book_id,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+
+    let mut search_results: Vec<SearchResult> = results.flatten().collect();
+
+    if let Some(cap) = max_per_book {
+        search_results = cap_results_per_book(search_results, cap, limit);
+    }
+
+    Ok(search_results)
+}
+
+// Keep at most `cap` results per top-level book and `limit` overall, preserving order
+// Root-level notes have no book and are never capped
+fn cap_results_per_book(results: Vec<SearchResult>, cap: usize, limit: usize) -> Vec<SearchResult> {
+    use std::collections::HashMap;
+
+    let mut per_book: HashMap<String, usize> = HashMap::new();
+    results
+        .into_iter()
+        .filter(|result| match &result.book_id {
+            Some(book_id) => {
+                let count = per_book.entry(book_id.clone()).or_insert(0);
+                *count += 1;
+                *count <= cap
+            }
+            None => true,
+        })
+        .take(limit)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../../../schema/base.sql")).unwrap();
+        conn
+    }
+
+    fn insert_note(conn: &Connection, id: &str, title: &str, content: &str) {
+        conn.execute(
+            "INSERT INTO items (id, type, title, content, content_plaintext, sort_order)
+             VALUES (?1, 'note', ?2, ?3, ?4, ?1)",
+            [id, title, content, &strip_html(content)],
+        )
+        .unwrap();
+    }
+
+    fn titles(results: &[SearchResult]) -> Vec<&str> {
+        results.iter().map(|result| result.title.as_str()).collect()
+    }
+
+    #[test]
+    fn like_wildcards_in_the_query_match_literally() {
+        let conn = memory_db();
+        insert_note(&conn, "a", "50% off", "");
+        insert_note(&conn, "b", "500 things", "");
+        insert_note(&conn, "c", "snake_case", "");
+        insert_note(&conn, "d", "snakescase", "");
+
+        assert_eq!(titles(&run_search(&conn, "50%", None, 10).unwrap()), vec!["50% off"]);
+        assert_eq!(titles(&run_search(&conn, "e_c", None, 10).unwrap()), vec!["snake_case"]);
+    }
+
+    #[test]
+    fn content_filter_matches_through_fts() {
+        let conn = memory_db();
+        insert_note(&conn, "a", "Cake", "<p>Dark <strong>chocolate</strong> sponge</p>");
+        insert_note(&conn, "b", "Bread", "<p>Flour and water</p>");
+
+        let results = run_search(&conn, "~chocolate", None, 10).unwrap();
+        assert_eq!(titles(&results), vec!["Cake"]);
+        assert_eq!(results[0].match_type, "content");
+        assert!(results[0].score > 0.0);
+    }
+
+    #[test]
+    fn the_per_book_cap_finds_the_book_at_any_depth() {
+        let conn = memory_db();
+        // Sections nested in sections predate the hierarchy checks
+        conn.execute_batch(
+            "INSERT INTO items (id, type, title, sort_order) VALUES ('b', 'book', 'Book', 'a');
+             INSERT INTO items (id, type, title, parent_id, sort_order) VALUES ('s1', 'section', 'Outer', 'b', 'a');
+             INSERT INTO items (id, type, title, parent_id, sort_order) VALUES ('s2', 'section', 'Inner', 's1', 'a');
+             INSERT INTO items (id, type, title, parent_id, sort_order) VALUES ('c', 'book', 'Other', NULL, 'b');",
+        )
+        .unwrap();
+        for (id, parent) in [("n1", "s2"), ("n2", "s2"), ("n3", "s1"), ("n4", "c")] {
+            insert_note(&conn, id, &format!("Plan {}", id), "");
+            conn.execute("UPDATE items SET parent_id = ?1 WHERE id = ?2", [parent, id])
+                .unwrap();
+        }
+
+        let results = run_search(&conn, "plan", Some(1), 10).unwrap();
+        assert_eq!(titles(&results), vec!["Plan n1", "Plan n4"]);
+        assert_eq!(results[0].book_id.as_deref(), Some("b"));
+    }
+}
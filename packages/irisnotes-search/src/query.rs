@@ -0,0 +1,191 @@
+//! Parsing of the quick-search query syntax
+
+/// Parsed search query with field-specific filters
+pub(crate) struct ParsedQuery {
+    /// Free text tokens for title search (AND-combined)
+    pub(crate) title_tokens: Vec<String>,
+    /// ~content filter, as plain text (the first value when operators chain several)
+    pub(crate) content: Option<String>,
+    /// ~content in FTS syntax: quoted values stay phrases, `~cake OR pie` chains values
+    pub(crate) content_query: Option<String>,
+    /// Values chained to ~content by AND/OR/NOT, as (operator, plain text)
+    pub(crate) content_chain: Vec<(String, String)>,
+    /// @book filter
+    pub(crate) book: Option<String>,
+    /// #section filter
+    pub(crate) section: Option<String>,
+    /// / means root notes only
+    pub(crate) root_only: bool,
+}
+
+pub(crate) fn parse_query(input: &str) -> ParsedQuery {
+    let mut title_tokens = Vec::new();
+    let mut content = None;
+    let mut content_query = None;
+    let mut content_chain = Vec::new();
+    let mut book = None;
+    let mut section = None;
+    let mut root_only = false;
+
+    let chars: Vec<char> = input.chars().collect();
+    let len = chars.len();
+    let mut i = 0;
+
+    while i < len {
+        // Skip whitespace
+        if chars[i].is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        if chars[i] == '/' && (i + 1 >= len || chars[i + 1].is_whitespace()) {
+            root_only = true;
+            i += 1;
+        } else if chars[i] == '@' || chars[i] == '#' || chars[i] == '~' {
+            let prefix = chars[i];
+            i += 1;
+            let quoted = chars.get(i) == Some(&'"');
+            let value = extract_value(&chars, &mut i);
+            if !value.is_empty() {
+                match prefix {
+                    '@' => book = Some(value.to_lowercase()),
+                    '#' => section = Some(value.to_lowercase()),
+                    '~' => {
+                        let mut query = content_term(&value, quoted);
+                        content_chain.clear();
+                        while let Some((operator, chained, quoted)) = chained_content(&chars, &mut i) {
+                            query.push_str(&format!(" {} {}", operator, content_term(&chained, quoted)));
+                            content_chain.push((operator, chained.to_lowercase()));
+                        }
+                        content = Some(value.to_lowercase());
+                        content_query = Some(query);
+                    }
+                    _ => {}
+                }
+            }
+        } else {
+            // Regular text token — title search
+            let start = i;
+            while i < len && !chars[i].is_whitespace() {
+                i += 1;
+            }
+            let token: String = chars[start..i].iter().collect();
+            title_tokens.push(token);
+        }
+    }
+
+    ParsedQuery { title_tokens, content, content_query, content_chain, book, section, root_only }
+}
+
+/// A ~content value in FTS syntax: quoted values are matched as a phrase
+fn content_term(value: &str, quoted: bool) -> String {
+    let value = value.to_lowercase();
+    if quoted {
+        format!("\"{}\"", value)
+    } else {
+        value
+    }
+}
+
+/// `AND`/`OR`/`NOT` plus the value after it (`~` optional) and whether it was
+/// quoted, following a ~content value. Leaves `i` untouched and returns None
+/// when the input does not continue so.
+fn chained_content(chars: &[char], i: &mut usize) -> Option<(String, String, bool)> {
+    let len = chars.len();
+    let mut j = *i;
+    while j < len && chars[j].is_whitespace() {
+        j += 1;
+    }
+    let start = j;
+    while j < len && !chars[j].is_whitespace() {
+        j += 1;
+    }
+    let operator: String = chars[start..j].iter().collect();
+    if !matches!(operator.as_str(), "AND" | "OR" | "NOT") {
+        return None;
+    }
+    while j < len && chars[j].is_whitespace() {
+        j += 1;
+    }
+    if chars.get(j) == Some(&'~') {
+        j += 1;
+    }
+    // A filter is not a content value: `~cake AND @work` keeps @work a book filter
+    if matches!(chars.get(j), None | Some('@' | '#' | '/')) {
+        return None;
+    }
+    let quoted = chars[j] == '"';
+    let value = extract_value(chars, &mut j);
+    if value.is_empty() {
+        return None;
+    }
+    *i = j;
+    Some((operator, value, quoted))
+}
+
+/// Term for the main app to highlight when opening a result: the ~content
+/// filter if present (that is the passage that matched), else the title words
+pub fn highlight_term(query: &str) -> Option<String> {
+    let parsed = parse_query(query);
+    let term = parsed.content.unwrap_or_else(|| parsed.title_tokens.join(" "));
+    (!term.is_empty()).then_some(term)
+}
+
+/// Extract a value after @ or #, supporting quoted strings: @"my book" or @word
+fn extract_value(chars: &[char], i: &mut usize) -> String {
+    let len = chars.len();
+    if *i >= len {
+        return String::new();
+    }
+
+    if chars[*i] == '"' {
+        // Quoted value: read until closing quote
+        *i += 1; // skip opening quote
+        let start = *i;
+        while *i < len && chars[*i] != '"' {
+            *i += 1;
+        }
+        let value: String = chars[start..*i].iter().collect();
+        if *i < len {
+            *i += 1; // skip closing quote
+        }
+        value
+    } else {
+        // Unquoted: read until whitespace
+        let start = *i;
+        while *i < len && !chars[*i].is_whitespace() {
+            *i += 1;
+        }
+        chars[start..*i].iter().collect()
+    }
+}
+
+/// Escape SQL LIKE wildcards (% and _) so they match literally
+pub(crate) fn escape_like(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_like_escapes_wildcards_and_the_escape_character() {
+        assert_eq!(escape_like("50%"), "50\\%");
+        assert_eq!(escape_like("snake_case"), "snake\\_case");
+        assert_eq!(escape_like("a\\b"), "a\\\\b");
+        assert_eq!(escape_like("plain"), "plain");
+    }
+
+    #[test]
+    fn parse_query_splits_filters_from_title_words() {
+        let parsed = parse_query("cake @\"My Book\" #Desserts ~\"dark chocolate\" OR cocoa /");
+        assert_eq!(parsed.title_tokens, vec!["cake"]);
+        assert_eq!(parsed.book.as_deref(), Some("my book"));
+        assert_eq!(parsed.section.as_deref(), Some("desserts"));
+        assert_eq!(parsed.content.as_deref(), Some("dark chocolate"));
+        assert_eq!(parsed.content_query.as_deref(), Some("\"dark chocolate\" OR cocoa"));
+        assert!(parsed.root_only);
+    }
+}
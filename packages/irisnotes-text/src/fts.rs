@@ -0,0 +1,144 @@
+//! Search box input to FTS5 `MATCH` expressions
+//!
+//! FTS5 rejects stray quotes and most punctuation outside strings, so user
+//! input cannot be passed to `MATCH` as typed. `fts_match_query` rebuilds it
+//! from terms FTS5 always accepts: every word is quoted, and only the
+//! structure the user asked for (phrases and `AND`/`OR`/`NOT`) is kept.
+//! `normalize_rank` turns the resulting `rank` into a score for display, and
+//! `query_terms` lists the words and phrases to highlight in the hits.
+
+/// Operators FTS5 recognizes; like FTS5, only the uppercase spelling counts
+const OPERATORS: &[&str] = &["AND", "OR", "NOT"];
+
+enum Part<'a> {
+    Term { text: &'a str, phrase: bool },
+    Operator(&'a str),
+}
+
+/// Split a query into terms and operators. Terms never contain `"` and
+/// always have at least one letter or digit; anything else is skipped.
+fn parts(input: &str) -> Vec<Part<'_>> {
+    let mut parts = Vec::new();
+    let mut rest = input;
+
+    loop {
+        rest = rest.trim_start();
+        let Some(first) = rest.chars().next() else {
+            break;
+        };
+        let (text, phrase) = if first == '"' {
+            let inner = &rest[1..];
+            let end = inner.find('"').unwrap_or(inner.len());
+            rest = inner.get(end + 1..).unwrap_or("");
+            (&inner[..end], true)
+        } else {
+            let end = rest
+                .find(|c: char| c.is_whitespace() || c == '"')
+                .unwrap_or(rest.len());
+            let word = &rest[..end];
+            rest = &rest[end..];
+            (word, false)
+        };
+
+        if !phrase && OPERATORS.contains(&text) {
+            parts.push(Part::Operator(text));
+        } else if text.chars().any(char::is_alphanumeric) {
+            parts.push(Part::Term { text, phrase });
+        }
+    }
+    parts
+}
+
+/// Build an FTS5 `MATCH` expression from a search query.
+///
+/// `"quoted text"` matches as an exact phrase, and `AND`, `OR` and `NOT`
+/// between two terms are kept. Every other word becomes a prefix term
+/// (`cake` also matches `cakes`); adjacent terms must all match. Operators
+/// with nothing on one side are dropped, and an unterminated quote runs to
+/// the end of the input. Returns `None` when nothing searchable is left.
+pub fn fts_match_query(input: &str) -> Option<String> {
+    let mut expression = Vec::new();
+    let mut operator = None;
+    for part in parts(input) {
+        match part {
+            Part::Operator(op) if !expression.is_empty() => {
+                operator.get_or_insert(op);
+            }
+            Part::Operator(_) => {}
+            Part::Term { text, phrase } => {
+                if let Some(op) = operator.take() {
+                    expression.push(op.to_string());
+                }
+                // Quoting makes any term valid FTS5, whatever punctuation it holds
+                if phrase {
+                    expression.push(format!("\"{}\"", text));
+                } else {
+                    expression.push(format!("\"{}\"*", text));
+                }
+            }
+        }
+    }
+    (!expression.is_empty()).then(|| expression.join(" "))
+}
+
+/// The words and phrases of a query, without operators or quotes, as they
+/// would be matched by `fts_match_query`. Used to highlight hits.
+pub fn query_terms(input: &str) -> Vec<&str> {
+    parts(input)
+        .into_iter()
+        .filter_map(|part| match part {
+            Part::Term { text, .. } => Some(text),
+            Part::Operator(_) => None,
+        })
+        .collect()
+}
+
+/// Map an FTS5 `rank` (BM25, more negative is more relevant) onto `0.0..1.0`,
+/// where higher is more relevant. The scale does not depend on the other
+/// results, so the same note scores the same in every result list.
+pub fn normalize_rank(rank: f64) -> f64 {
+    let relevance = (-rank).max(0.0);
+    relevance / (1.0 + relevance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn words_become_prefix_terms() {
+        assert_eq!(fts_match_query("chocolate cake").as_deref(), Some("\"chocolate\"* \"cake\"*"));
+    }
+
+    #[test]
+    fn phrases_and_operators_are_kept() {
+        assert_eq!(
+            fts_match_query("\"packing list\" OR japan NOT trip").as_deref(),
+            Some("\"packing list\" OR \"japan\"* NOT \"trip\"*")
+        );
+        // Lowercase operators are ordinary words, as in FTS5
+        assert_eq!(fts_match_query("this or that").as_deref(), Some("\"this\"* \"or\"* \"that\"*"));
+    }
+
+    #[test]
+    fn dangling_operators_and_punctuation_are_dropped() {
+        assert_eq!(fts_match_query("AND cake OR").as_deref(), Some("\"cake\"*"));
+        assert_eq!(fts_match_query("c++ (draft) *").as_deref(), Some("\"c++\"* \"(draft)\"*"));
+        assert_eq!(fts_match_query("\"unterminated phrase").as_deref(), Some("\"unterminated phrase\""));
+        assert_eq!(fts_match_query("  - * \"\" OR  "), None);
+    }
+
+    #[test]
+    fn query_terms_lists_words_and_phrases() {
+        assert_eq!(query_terms("\"packing list\" AND japan"), vec!["packing list", "japan"]);
+    }
+
+    #[test]
+    fn normalize_rank_maps_bm25_onto_unit_interval() {
+        assert_eq!(normalize_rank(0.0), 0.0);
+        assert_eq!(normalize_rank(3.5), 0.0);
+        assert_eq!(normalize_rank(-1.0), 0.5);
+        assert!(normalize_rank(-10.0) > normalize_rank(-2.0));
+        assert!(normalize_rank(-1e9) < 1.0);
+    }
+}
@@ -0,0 +1,252 @@
+//! Plain-text helpers shared by the IrisNotes apps
+//!
+//! The main app's CLI, its Markdown converter and the quick search overlay all
+//! turn stored note HTML into text. They call the functions here so entity
+//! decoding and line-break handling behave the same everywhere. Both search
+//! implementations build their full-text queries with `fts_match_query`, and
+//! displayed word counts come from `word_count`.
+
+mod fts;
+
+pub use fts::{fts_match_query, normalize_rank, query_terms};
+
+/// Character for the digits of a numeric reference (`8217` or `x2019`).
+/// NUL, surrogates and out-of-range values are rejected.
+fn decode_numeric(digits: &str) -> Option<char> {
+    let (digits, radix) = match digits.strip_prefix(['x', 'X']) {
+        Some(hex) => (hex, 16),
+        None => (digits, 10),
+    };
+    if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+        return None;
+    }
+    u32::from_str_radix(digits, radix)
+        .ok()
+        .filter(|&code| code != 0)
+        .and_then(char::from_u32)
+}
+
+/// Decode the named entities the editor emits plus decimal (`&#8217;`) and
+/// hex (`&#x2019;`) references. Malformed references are left as written.
+pub fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        result.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest.find(';').filter(|&end| end <= 10).and_then(|end| {
+            let entity = &rest[1..end];
+            let c = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => entity.strip_prefix('#').and_then(decode_numeric),
+            };
+            c.map(|c| (c, end))
+        });
+        match decoded {
+            Some((c, end)) => {
+                result.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Elements whose closing tag ends a line in `strip_html` output
+const BLOCK_TAGS: &[&str] = &[
+    "p",
+    "div",
+    "li",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "blockquote",
+    "pre",
+    "tr",
+];
+
+/// Strip HTML tags and decode common entities for plain text output
+pub fn strip_html(html: &str) -> String {
+    // Remove HTML tags; line breaks and block ends become newlines
+    let mut text = String::with_capacity(html.len());
+    let mut tag = String::new();
+    let mut in_tag = false;
+    // Quote character of the attribute value being read, where `>` is literal
+    let mut quote: Option<char> = None;
+    let mut in_pre = false;
+
+    for c in html.chars() {
+        match c {
+            '<' if !in_tag => {
+                in_tag = true;
+                tag.clear();
+            }
+            '"' | '\'' if in_tag && quote.is_none_or(|q| q == c) => {
+                // Quotes only open a value after `=`, so `<p don't>` stays harmless
+                if quote.is_some() {
+                    quote = None;
+                } else if tag.trim_end().ends_with('=') {
+                    quote = Some(c);
+                }
+                tag.push(c);
+            }
+            '>' if in_tag && quote.is_none() => {
+                in_tag = false;
+                let tag = tag.trim_start();
+                let closing = tag.starts_with('/');
+                let name: String = tag
+                    .trim_start_matches('/')
+                    .chars()
+                    .take_while(|c| c.is_ascii_alphanumeric())
+                    .collect::<String>()
+                    .to_ascii_lowercase();
+                if name == "pre" {
+                    in_pre = !closing;
+                }
+                let breaks_line = matches!(name.as_str(), "br" | "hr")
+                    || (closing && BLOCK_TAGS.contains(&name.as_str()));
+                if breaks_line {
+                    text.push('\n');
+                }
+            }
+            _ if in_tag => tag.push(c),
+            // Source formatting is plain whitespace, except inside <pre>
+            '\n' | '\r' | '\t' if !in_pre => text.push(' '),
+            _ => text.push(c),
+        }
+    }
+
+    // Decode named and numeric HTML entities
+    let text = decode_entities(&text);
+
+    // Collapse runs of spaces, trim around line breaks and keep at most one blank line
+    let mut result = String::with_capacity(text.len());
+    let mut newlines = 0;
+    let mut space = false;
+    for c in text.chars() {
+        if c == '\n' {
+            newlines += 1;
+            space = false;
+        } else if c.is_whitespace() {
+            space = true;
+        } else {
+            if !result.is_empty() {
+                if newlines > 0 {
+                    result.push_str(&"\n".repeat(newlines.min(2)));
+                } else if space {
+                    result.push(' ');
+                }
+            }
+            newlines = 0;
+            space = false;
+            result.push(c);
+        }
+    }
+    result
+}
+
+/// Han and kana characters, which run together without spaces
+/// (Korean separates words with spaces, so Hangul is not included)
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}' // Hiragana and Katakana
+        | '\u{3400}'..='\u{4DBF}' // CJK Extension A
+        | '\u{4E00}'..='\u{9FFF}' // CJK Unified Ideographs
+        | '\u{F900}'..='\u{FAFF}' // CJK Compatibility Ideographs
+        | '\u{20000}'..='\u{2FFFF}' // CJK Extensions B and later
+    )
+}
+
+/// Count the words of plain text (run HTML through `strip_html` first).
+/// Whitespace separates words, except that every Chinese or Japanese character
+/// counts as a word of its own, since those scripts are written without
+/// spaces. Tokens with no letters or digits (`-`, `。`) are not words.
+pub fn word_count(text: &str) -> usize {
+    text.split_whitespace()
+        .map(|token| {
+            let cjk = token.chars().filter(|&c| is_cjk(c)).count();
+            // Each run of other characters between CJK ones is one more word
+            let others = token
+                .split(is_cjk)
+                .filter(|run| run.chars().any(char::is_alphanumeric))
+                .count();
+            cjk + others
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_html_removes_tags_and_decodes_entities() {
+        assert_eq!(strip_html("<p>Fish &amp; <em>chips</em></p>"), "Fish & chips");
+        assert_eq!(strip_html("it&#8217;s &#x2019; &lt;b&gt;"), "it\u{2019}s \u{2019} <b>");
+        assert_eq!(strip_html("&bogus; &#0; &"), "&bogus; &#0; &");
+    }
+
+    #[test]
+    fn strip_html_collapses_source_whitespace() {
+        assert_eq!(strip_html("<p>  one\n\ttwo   three </p>"), "one two three");
+        // Inside <pre> line breaks survive; indentation after them does not
+        assert_eq!(strip_html("<pre>a\n  b</pre>"), "a\nb");
+    }
+
+    #[test]
+    fn strip_html_ends_lines_at_blocks_and_breaks() {
+        assert_eq!(strip_html("<p>one</p><p>two</p>"), "one\ntwo");
+        assert_eq!(strip_html("<div><p>outer <p>inner</p></p></div><p>next</p>"), "outer inner\n\nnext");
+        assert_eq!(strip_html("<ul><li><p>a</p></li><li>b</li></ul>"), "a\n\nb");
+    }
+
+    #[test]
+    fn strip_html_keeps_at_most_one_blank_line() {
+        assert_eq!(strip_html("a<br>b"), "a\nb");
+        assert_eq!(strip_html("a<br><br>b"), "a\n\nb");
+        assert_eq!(strip_html("a<br/><br /><BR><br>b"), "a\n\nb");
+        assert_eq!(strip_html("<br><p>a</p><br><br>"), "a");
+    }
+
+    #[test]
+    fn strip_html_ignores_gt_inside_attribute_values() {
+        assert_eq!(strip_html("<a title=\"a>b\">x</a>"), "x");
+        assert_eq!(strip_html("<img alt='x > 5' src=\"c.png\">if x > 5"), "if x > 5");
+        assert_eq!(strip_html("<span title=\"it's > 1\">ok</span>"), "ok");
+        // An apostrophe outside a value does not start one
+        assert_eq!(strip_html("<p don't>text</p>"), "text");
+    }
+
+    #[test]
+    fn word_count_splits_on_whitespace() {
+        assert_eq!(word_count(""), 0);
+        assert_eq!(word_count("  one two\nthree  "), 3);
+        assert_eq!(word_count("well - that's it ..."), 3);
+    }
+
+    #[test]
+    fn word_count_counts_each_cjk_character() {
+        assert_eq!(word_count("日本語のテキスト"), 8);
+        assert_eq!(word_count("我喜欢Rust。"), 4);
+        assert_eq!(word_count("東京 Tokyo"), 3);
+        // Hangul is written with spaces between words
+        assert_eq!(word_count("안녕하세요 세계"), 2);
+    }
+}
@@ -204,6 +204,51 @@ BEGIN
     UPDATE settings SET updated_at = datetime('now') WHERE key = NEW.key;
 END;
 
+-- Change log - durable feed of item changes for integrations (`irisnotes tail`)
+-- Populated by triggers. Soft deletes (deleted_at set) are logged as 'delete';
+-- bookkeeping-only updates (updated_at bumps, view tracking) are not logged.
+-- Retention: keeps the newest N rows, N = settings.changelog_retention (default 10000).
+CREATE TABLE IF NOT EXISTS changelog (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    item_id TEXT NOT NULL,
+    operation TEXT NOT NULL CHECK (operation IN ('insert', 'update', 'delete')),
+    changed_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_changelog_item_id ON changelog(item_id);
+
+CREATE TRIGGER IF NOT EXISTS changelog_items_insert AFTER INSERT ON items BEGIN
+    INSERT INTO changelog (item_id, operation) VALUES (NEW.id, 'insert');
+END;
+
+CREATE TRIGGER IF NOT EXISTS changelog_items_update AFTER UPDATE ON items
+WHEN OLD.type IS NOT NEW.type
+    OR OLD.title IS NOT NEW.title
+    OR OLD.content IS NOT NEW.content
+    OR OLD.parent_id IS NOT NEW.parent_id
+    OR OLD.sort_order IS NOT NEW.sort_order
+    OR OLD.metadata IS NOT NEW.metadata
+    OR OLD.deleted_at IS NOT NEW.deleted_at
+BEGIN
+    INSERT INTO changelog (item_id, operation)
+    VALUES (
+        NEW.id,
+        CASE WHEN OLD.deleted_at IS NULL AND NEW.deleted_at IS NOT NULL THEN 'delete' ELSE 'update' END
+    );
+END;
+
+CREATE TRIGGER IF NOT EXISTS changelog_items_delete AFTER DELETE ON items BEGIN
+    INSERT INTO changelog (item_id, operation) VALUES (OLD.id, 'delete');
+END;
+
+CREATE TRIGGER IF NOT EXISTS changelog_retention AFTER INSERT ON changelog BEGIN
+    DELETE FROM changelog
+    WHERE seq <= NEW.seq - COALESCE(
+        (SELECT CAST(value AS INTEGER) FROM settings WHERE key = 'changelog_retention'),
+        10000
+    );
+END;
+
 -- Tree view for easy querying
 CREATE VIEW IF NOT EXISTS tree_items AS
 SELECT